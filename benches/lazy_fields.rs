@@ -0,0 +1,106 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use rusthl7::message::*;
+use rusthl7::parse_options::ParseOptions;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::convert::TryFrom;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Counts allocations, so the benefit of splitting fields lazily can be seen directly rather than inferred from timings.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+fn get_sample_message() -> &'static str {
+    "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rPID|||555-44-4444||EVERYWOMAN^EVE^E^^^^L|JONES|19620320|F|||153 FERNWOOD DR.^^STATESVILLE^OH^35292||(206)3345232|(206)752-121||||AC555444444||67-A4335^OH^20030520\rOBR|1|845439^GHH OE|1045813^GHH LAB|15545^GLUCOSE|||200202150730|||||||||555-55-5555^PRIMARY^PATRICIA P^^^^MD^^|||||||||F||||||444-44-4444^HIPPOCRATES^HOWARD H^^^^MD\rOBX|1|SN|1554-5^GLUCOSE^POST 12H CFST:MCNC:PT:SER/PLAS:QN||^182|mg/dl|70_105|H|||F"
+}
+
+/// Reads every field's value, which never needs the field splitting up.
+fn read_values(m: &Message) -> usize {
+    m.segments
+        .iter()
+        .flat_map(|s| s.fields.iter())
+        .map(|f| f.value().len())
+        .sum()
+}
+
+/// Reads every subcomponent, which splits every field.
+fn read_subcomponents(m: &Message) -> usize {
+    m.segments
+        .iter()
+        .flat_map(|s| s.fields.iter())
+        .map(|f| f.subcomponents().iter().flatten().flatten().count())
+        .sum()
+}
+
+const LAZY: ParseOptions = ParseOptions {
+    repair: false,
+    require_uppercase_segment_ids: false,
+    validate_segments: false,
+    trim_whitespace: false,
+    lazy_fields: true,
+};
+
+/// Returns the number of allocations made by parsing the sample with the options and then running `read` over it.
+fn count_allocations(options: &ParseOptions, read: fn(&Message) -> usize) -> usize {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let m = Message::parse_with_options(get_sample_message(), options).unwrap();
+    read(&m);
+    ALLOCATIONS.load(Ordering::Relaxed) - before
+}
+
+fn parse_and_read_values(c: &mut Criterion) {
+    println!(
+        "Allocations to parse and read every value: {} eager, {} lazy",
+        count_allocations(&ParseOptions::default(), read_values),
+        count_allocations(&LAZY, read_values)
+    );
+    c.bench_function("ORU parse, read values", |b| {
+        b.iter(|| {
+            let m = Message::try_from(get_sample_message()).unwrap();
+            read_values(&m)
+        })
+    });
+    c.bench_function("ORU lazy parse, read values", |b| {
+        b.iter(|| {
+            let m = Message::parse_with_options(get_sample_message(), &LAZY).unwrap();
+            read_values(&m)
+        })
+    });
+}
+
+fn parse_and_read_subcomponents(c: &mut Criterion) {
+    println!(
+        "Allocations to parse and read every subcomponent: {} eager, {} lazy",
+        count_allocations(&ParseOptions::default(), read_subcomponents),
+        count_allocations(&LAZY, read_subcomponents)
+    );
+    c.bench_function("ORU parse, read subcomponents", |b| {
+        b.iter(|| {
+            let m = Message::try_from(get_sample_message()).unwrap();
+            read_subcomponents(&m)
+        })
+    });
+    c.bench_function("ORU lazy parse, read subcomponents", |b| {
+        b.iter(|| {
+            let m = Message::parse_with_options(get_sample_message(), &LAZY).unwrap();
+            read_subcomponents(&m)
+        })
+    });
+}
+
+criterion_group!(benches, parse_and_read_values, parse_and_read_subcomponents);
+criterion_main!(benches);
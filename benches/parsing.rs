@@ -0,0 +1,47 @@
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use rusthl7::message::*;
+use rusthl7::raw::RawMessage;
+use rusthl7::segments::Segment;
+use rusthl7::separators::Separators;
+use std::convert::TryFrom;
+
+fn get_sample_message() -> &'static str {
+    "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rPID|||555-44-4444||EVERYWOMAN^EVE^E^^^^L|JONES|19620320|F|||153 FERNWOOD DR.^^STATESVILLE^OH^35292||(206)3345232|(206)752-121||||AC555444444||67-A4335^OH^20030520\rOBR|1|845439^GHH OE|1045813^GHH LAB|15545^GLUCOSE|||200202150730|||||||||555-55-5555^PRIMARY^PATRICIA P^^^^MD^^|||||||||F||||||444-44-4444^HIPPOCRATES^HOWARD H^^^^MD\rOBX|1|SN|1554-5^GLUCOSE^POST 12H CFST:MCNC:PT:SER/PLAS:QN||^182|mg/dl|70_105|H|||F"
+}
+
+/// Compares the ways of getting at the sample message's content, each reading MSH-10 so there's comparable work at the end.
+/// Throughput is reported per message.
+fn parsing_paths(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Parse ORU");
+    group.throughput(Throughput::Elements(1));
+
+    group.bench_function("Message", |b| {
+        b.iter(|| {
+            let m = Message::try_from(get_sample_message()).unwrap();
+            assert_eq!(m.query("MSH-10"), "CNTRL-3456");
+        })
+    });
+
+    group.bench_function("Segments", |b| {
+        b.iter(|| {
+            let delims = Separators::from_msh(get_sample_message()).unwrap();
+            let segments: Vec<Segment> = get_sample_message()
+                .split(delims.segment)
+                .map(|line| Segment::parse(line, &delims).unwrap())
+                .collect();
+            assert_eq!(segments[0].fields[9].value(), "CNTRL-3456");
+        })
+    });
+
+    group.bench_function("RawMessage", |b| {
+        b.iter(|| {
+            let m = RawMessage::new(get_sample_message()).unwrap();
+            assert_eq!(m.field("MSH", 10), Some("CNTRL-3456"));
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, parsing_paths);
+criterion_main!(benches);
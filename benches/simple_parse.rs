@@ -14,12 +14,32 @@ fn message_parse(c: &mut Criterion) {
     });
 }
 
+fn message_parse_from_bytes(c: &mut Criterion) {
+    c.bench_function("ORU parse (from bytes)", |b| {
+        let bytes = get_sample_message().as_bytes();
+        b.iter(|| {
+            let source = std::str::from_utf8(bytes).unwrap();
+            let _ = Message::try_from(source).unwrap();
+        })
+    });
+}
+
+fn message_parse_from_bytes_unchecked(c: &mut Criterion) {
+    c.bench_function("ORU parse (from bytes, unchecked)", |b| {
+        let bytes = get_sample_message().as_bytes();
+        b.iter(|| {
+            // Safety: sample message is a 7-bit ASCII literal
+            let _ = unsafe { Message::parse_bytes_unchecked(bytes) }.unwrap();
+        })
+    });
+}
+
 fn get_segments_by_name(c: &mut Criterion) {
     c.bench_function("Get Segment By Name", |b| {
         let m = Message::try_from(get_sample_message()).unwrap();
 
         b.iter(|| {
-            let _segs = m.segments_by_name("OBR").unwrap();
+            let _segs: Vec<_> = m.segments_by_name("OBR").collect();
             //assert!(segs.len() == 1);
         })
     });
@@ -64,6 +84,8 @@ fn get_pid_and_read_field_via_index(c: &mut Criterion) {
 criterion_group!(
     benches,
     message_parse,
+    message_parse_from_bytes,
+    message_parse_from_bytes_unchecked,
     get_segments_by_name,
     get_pid_and_read_field_via_vec,
     get_pid_and_read_field_via_query,
@@ -74,6 +96,8 @@ criterion_group!(
 criterion_group!(
     benches,
     message_parse,
+    message_parse_from_bytes,
+    message_parse_from_bytes_unchecked,
     get_segments_by_name,
     get_pid_and_read_field_via_vec,
     get_pid_and_read_field_via_query
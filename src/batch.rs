@@ -0,0 +1,271 @@
+/*!
+Parsing of HL7 batch files, where several messages are wrapped in file (`FHS`/`FTS`) and/or batch (`BHS`/`BTS`) header and trailer segments.
+
+```text
+FHS|^~\&|...        file header (optional)
+BHS|^~\&|...        batch header (optional)
+MSH|^~\&|...        first message
+...
+MSH|^~\&|...        second message
+...
+BTS|2               batch trailer, with the count of messages in the batch (optional)
+FTS|1               file trailer, with the count of batches in the file (optional)
+```
+*/
+
+use super::message::Message;
+use super::segments::Segment;
+use super::separators::Separators;
+use super::*;
+use std::convert::TryFrom;
+
+/// A batch file (or a bare message/sequence of messages with no wrapper at all), parsed into its header/trailer segments and messages.
+///
+/// Only a single batch per file is supported.  The header and trailer segments are parsed with the separators declared in the FHS/BHS,
+/// while each message is parsed with the separators from its own MSH.
+#[derive(Debug, PartialEq)]
+pub struct BatchFile<'a> {
+    pub source: &'a str,
+    pub file_header: Option<Segment<'a>>,
+    pub batch_header: Option<Segment<'a>>,
+    pub messages: Vec<Message<'a>>,
+    pub batch_trailer: Option<Segment<'a>>,
+    pub file_trailer: Option<Segment<'a>>,
+}
+
+impl<'a> BatchFile<'a> {
+    /// Parses a batch file, validating that the message count in the BTS (BTS-1) and batch count in the FTS (FTS-1) match the content where
+    /// they're present.  Input without any batch segments is treated as a batch of the message(s) it contains.
+    ///
+    /// Segments must be separated by `\r` as the spec requires, although line feeds following the batch segments and the end of each message
+    /// are tolerated as files are often edited by hand.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::batch::BatchFile;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let hl7 = "BHS|^~\\&|GHH LAB\rMSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-1|P|2.4\rPID|||555-44-4444\r\
+    ///     MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-2|P|2.4\rBTS|2";
+    /// let batch = BatchFile::parse(hl7)?;
+    /// assert_eq!(batch.messages.len(), 2);
+    /// assert_eq!(batch.messages[1].query("MSH-10"), "CNTRL-2");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn parse(input: &'a str) -> Result<BatchFile<'a>, Hl7ParseError> {
+        let mut batch = BatchFile {
+            source: input,
+            file_header: None,
+            batch_header: None,
+            messages: Vec::new(),
+            batch_trailer: None,
+            file_trailer: None,
+        };
+
+        let mut header_delims = Separators::default();
+        let mut message_span: Option<(usize, usize)> = None; // byte range of the message currently being read
+        let mut offset = 0;
+
+        for line in input.split('\r') {
+            let start = offset + (line.len() - line.trim_start_matches('\n').len());
+            offset += line.len() + 1;
+            let line = line.trim_matches('\n');
+            if line.is_empty() {
+                continue;
+            }
+
+            match line.get(..3).unwrap_or(line) {
+                "MSH" => {
+                    batch.close_message(message_span.take())?;
+                    message_span = Some((start, start + line.len()));
+                }
+                name if message_span.is_some() && !is_batch_segment(name) => {
+                    // part of the current message
+                    message_span = message_span.map(|(from, _)| (from, start + line.len()));
+                }
+                "FHS"
+                    if batch.is_empty()
+                        && message_span.is_none()
+                        && batch.file_header.is_none() =>
+                {
+                    header_delims = Separators::from_header(line, "FHS")?;
+                    batch.file_header = Some(Segment::parse(line, &header_delims)?);
+                }
+                "BHS"
+                    if batch.batch_header.is_none()
+                        && batch.messages.is_empty()
+                        && message_span.is_none() =>
+                {
+                    header_delims = Separators::from_header(line, "BHS")?;
+                    batch.batch_header = Some(Segment::parse(line, &header_delims)?);
+                }
+                "BHS" => {
+                    return Err(Hl7ParseError::Generic(
+                        "Only a single batch (BHS) per file is supported".to_string(),
+                    ))
+                }
+                "BTS" if batch.batch_trailer.is_none() => {
+                    batch.close_message(message_span.take())?;
+                    let bts = Segment::parse(line, &header_delims)?;
+                    check_count(&bts, batch.messages.len(), "messages")?;
+                    batch.batch_trailer = Some(bts);
+                }
+                "FTS" if batch.file_trailer.is_none() => {
+                    batch.close_message(message_span.take())?;
+                    let fts = Segment::parse(line, &header_delims)?;
+                    let batches = if batch.batch_header.is_some() || !batch.messages.is_empty() {
+                        1
+                    } else {
+                        0
+                    };
+                    check_count(&fts, batches, "batches")?;
+                    batch.file_trailer = Some(fts);
+                }
+                name => {
+                    return Err(Hl7ParseError::Generic(format!(
+                        "Unexpected '{}' segment in batch file",
+                        name
+                    )))
+                }
+            }
+
+            if batch.file_trailer.is_some() && message_span.is_some() {
+                return Err(Hl7ParseError::Generic(
+                    "Message found after the file trailer (FTS)".to_string(),
+                ));
+            }
+        }
+        batch.close_message(message_span)?;
+
+        Ok(batch)
+    }
+
+    /// Returns true if no segments (of any kind) have been found yet.
+    fn is_empty(&self) -> bool {
+        self.batch_header.is_none() && self.messages.is_empty() && self.batch_trailer.is_none()
+    }
+
+    /// Parses the message in the given byte range of the source (if any) and adds it to the batch.
+    fn close_message(&mut self, span: Option<(usize, usize)>) -> Result<(), Hl7ParseError> {
+        if let Some((from, to)) = span {
+            if self.batch_trailer.is_some() {
+                return Err(Hl7ParseError::Generic(
+                    "Message found after the batch trailer (BTS)".to_string(),
+                ));
+            }
+            self.messages
+                .push(Message::try_from(&self.source[from..to])?);
+        }
+        Ok(())
+    }
+}
+
+fn is_batch_segment(name: &str) -> bool {
+    matches!(name, "FHS" | "BHS" | "BTS" | "FTS")
+}
+
+/// Validates the count in the first field of a trailer (BTS-1 or FTS-1) matches what was found, if the count is present.
+fn check_count(trailer: &Segment, found: usize, what: &str) -> Result<(), Hl7ParseError> {
+    let declared = match trailer.fields.get(1).map(|f| f.value()) {
+        None | Some("") => return Ok(()),
+        Some(declared) => declared,
+    };
+
+    match declared.parse::<usize>() {
+        Ok(count) if count == found => Ok(()),
+        _ => Err(Hl7ParseError::Generic(format!(
+            "{} declares {} {}, but {} were found",
+            trailer.fields[0].value(),
+            declared,
+            what,
+            found
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MSG_1: &str =
+        "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-1|P|2.4\rPID|||555-44-4444";
+    const MSG_2: &str =
+        "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-2|P|2.4\rOBX|1|NM|1554-5^GLUCOSE||182";
+
+    #[test]
+    fn ensure_wrapped_batch_is_parsed() -> Result<(), Hl7ParseError> {
+        let hl7 = format!(
+            "FHS|^~\\&|GHH LAB\rBHS|^~\\&|GHH LAB||||200202150930\r{}\r{}\rBTS|2\rFTS|1",
+            MSG_1, MSG_2
+        );
+        let batch = BatchFile::parse(&hl7)?;
+
+        assert_eq!(
+            batch.file_header.as_ref().unwrap().fields[2].value(),
+            "GHH LAB"
+        );
+        assert_eq!(
+            batch.batch_header.as_ref().unwrap().fields[6].value(),
+            "200202150930"
+        );
+        assert_eq!(batch.messages.len(), 2);
+        assert_eq!(batch.messages[0].as_str(), MSG_1);
+        assert_eq!(batch.messages[1].as_str(), MSG_2);
+        assert_eq!(batch.messages[1].query("OBX-5"), "182");
+        assert_eq!(batch.batch_trailer.unwrap().fields[1].value(), "2");
+        assert!(batch.file_trailer.is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_bare_message_is_a_single_message_batch() -> Result<(), Hl7ParseError> {
+        let batch = BatchFile::parse(MSG_1)?;
+
+        assert_eq!(batch.file_header, None);
+        assert_eq!(batch.batch_header, None);
+        assert_eq!(batch.messages.len(), 1);
+        assert_eq!(batch.messages[0].as_str(), MSG_1);
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_line_feeds_between_segments_are_tolerated() -> Result<(), Hl7ParseError> {
+        let hl7 = format!("BHS|^~\\&|GHH LAB\r\n{}\r\n{}\r\nBTS|2\r\n", MSG_1, MSG_2);
+        let batch = BatchFile::parse(&hl7)?;
+
+        assert_eq!(batch.messages.len(), 2);
+        assert_eq!(batch.messages[0].as_str(), MSG_1);
+        assert_eq!(batch.messages[1].as_str(), MSG_2);
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_count_mismatches_are_rejected() {
+        let hl7 = format!("BHS|^~\\&|GHH LAB\r{}\r{}\rBTS|3", MSG_1, MSG_2);
+        match BatchFile::parse(&hl7) {
+            Err(Hl7ParseError::Generic(e)) => {
+                assert_eq!(e, "BTS declares 3 messages, but 2 were found")
+            }
+            _ => panic!("Expected a count mismatch"),
+        }
+
+        let hl7 = format!("FHS|^~\\&\rBHS|^~\\&\r{}\rBTS|1\rFTS|2", MSG_1);
+        assert!(BatchFile::parse(&hl7).is_err());
+
+        // counts are optional
+        let hl7 = format!("BHS|^~\\&\r{}\rBTS", MSG_1);
+        assert!(BatchFile::parse(&hl7).is_ok());
+    }
+
+    #[test]
+    fn ensure_misplaced_segments_are_rejected() {
+        for hl7 in [
+            format!("PID|1\r{}", MSG_1),
+            format!("BHS|^~\\&\r{}\rBTS|1\r{}", MSG_1, MSG_2),
+            format!("BHS|^~\\&\r{}\rBHS|^~\\&\r{}", MSG_1, MSG_2),
+            format!("{}\rFHS|^~\\&", MSG_1),
+        ] {
+            assert!(BatchFile::parse(&hl7).is_err(), "{:?} should fail", hl7);
+        }
+    }
+}
@@ -0,0 +1,296 @@
+/*!
+Construction of outbound messages from scratch, see `MessageBuilder`.
+*/
+
+use super::escape_sequence;
+use super::message;
+use super::separators::Separators;
+use super::timestamp;
+use std::borrow::Cow;
+
+/// Builds a message segment by segment, taking care of the delimiters and escaping so values can be supplied as-is.
+///
+/// - `segment()` starts a new segment, and `field()` adds a field to it with the given value as its first component
+/// - `component()` adds another component to the current field, and `repeat()` starts a new repeat of it
+/// - For the MSH, the field separator (MSH-1) and encoding characters (MSH-2) are written automatically, so the first `field()` is MSH-3
+///
+/// MSH-7 and MSH-10 can be set with `timestamp()` and `control_id()`, padding the MSH with empty fields as needed.  Where they're left empty,
+/// the current time (which requires the `chrono` feature) and a generated control id are used, as per `Message::build_ack()`.
+/// ## Example:
+/// ```
+/// # use rusthl7::builder::MessageBuilder;
+/// let hl7 = MessageBuilder::new()
+///     .segment("MSH").field("GHH LAB").field("ELAB-3").field("GHH OE").field("BLDG4")
+///     .field("").field("").field("ORU").component("R01").field("").field("P").field("2.4")
+///     .timestamp("200202150930")
+///     .control_id("CNTRL-3456")
+///     .segment("PID").field("").field("").field("555-44-4444")
+///     .segment("NTE").field("").field("").field("Smith & Sons")
+///     .build();
+/// assert_eq!(hl7, "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rPID|||555-44-4444\rNTE|||Smith \\T\\ Sons");
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct MessageBuilder {
+    separators: Separators,
+    timestamp: Option<String>,
+    control_id: Option<String>,
+    /// `(name, fields)`, where each field is a list of repeats, each a list of (escaped) components
+    segments: Vec<(String, Vec<Vec<Vec<String>>>)>,
+}
+
+impl Default for MessageBuilder {
+    fn default() -> MessageBuilder {
+        MessageBuilder::with_separators(Separators::default())
+    }
+}
+
+impl MessageBuilder {
+    /// Creates a builder using the default separators (`|^~\&`).
+    pub fn new() -> MessageBuilder {
+        MessageBuilder::default()
+    }
+
+    /// Creates a builder using the given separators, which are written to MSH-1/MSH-2.
+    pub fn with_separators(separators: Separators) -> MessageBuilder {
+        MessageBuilder {
+            separators,
+            timestamp: None,
+            control_id: None,
+            segments: Vec::new(),
+        }
+    }
+
+    /// Starts a new segment with the given name (eg `PID`).
+    pub fn segment(mut self, name: &str) -> MessageBuilder {
+        self.segments.push((name.to_string(), Vec::new()));
+        self
+    }
+
+    /// Adds a field to the current segment, with the (unescaped) value as its first component.
+    ///
+    /// # Panics
+    /// If no segment has been started.
+    pub fn field(mut self, value: &str) -> MessageBuilder {
+        let value = self.escape(value);
+        self.current_fields().push(vec![vec![value]]);
+        self
+    }
+
+    /// Adds a component, with the given (unescaped) value, to the current repeat of the current field.
+    ///
+    /// # Panics
+    /// If no field has been added to the current segment.
+    pub fn component(mut self, value: &str) -> MessageBuilder {
+        let value = self.escape(value);
+        self.current_repeat().push(value);
+        self
+    }
+
+    /// Starts a new repeat of the current field, subsequent `component()` calls add to the new repeat.
+    ///
+    /// # Panics
+    /// If no field has been added to the current segment.
+    pub fn repeat(mut self) -> MessageBuilder {
+        self.current_field().push(Vec::new());
+        self
+    }
+
+    /// Sets the date/time of the message (MSH-7), which should already be in HL7 format (eg `200202150930`).
+    pub fn timestamp(mut self, timestamp: &str) -> MessageBuilder {
+        self.timestamp = Some(timestamp.to_string());
+        self
+    }
+
+    /// Sets the message control id (MSH-10).
+    pub fn control_id(mut self, control_id: &str) -> MessageBuilder {
+        self.control_id = Some(control_id.to_string());
+        self
+    }
+
+    /// Renders the message, with segments separated by the segment separator (`\r`).
+    pub fn build(&self) -> String {
+        let delims = self.separators;
+        self.segments
+            .iter()
+            .map(|(name, fields)| {
+                let mut fields: Vec<String> = fields
+                    .iter()
+                    .map(|repeats| {
+                        repeats
+                            .iter()
+                            .map(|components| components.join(&delims.component.to_string()))
+                            .collect::<Vec<String>>()
+                            .join(&delims.repeat.to_string())
+                    })
+                    .collect();
+
+                if name != "MSH" {
+                    fields.insert(0, name.clone());
+                    return fields.join(&delims.field.to_string());
+                }
+
+                // `fields` starts from MSH-3, so MSH-n is at n - 3
+                self.fill_msh_field(&mut fields, 7, &self.timestamp, || {
+                    timestamp::timestamp_or_now(None).unwrap_or_default()
+                });
+                self.fill_msh_field(
+                    &mut fields,
+                    10,
+                    &self.control_id,
+                    message::generate_control_id,
+                );
+                format!(
+                    "MSH{}{}{}{}",
+                    delims.field,
+                    delims,
+                    delims.field,
+                    fields.join(&delims.field.to_string())
+                )
+            })
+            .collect::<Vec<String>>()
+            .join(&delims.segment.to_string())
+    }
+
+    /// Sets MSH-`number` in `fields` (which starts at MSH-3) to the explicit value if there is one, or the generated value if the field is empty.
+    fn fill_msh_field<F>(
+        &self,
+        fields: &mut Vec<String>,
+        number: usize,
+        explicit: &Option<String>,
+        generate: F,
+    ) where
+        F: FnOnce() -> String,
+    {
+        let idx = number - 3;
+        if fields.len() <= idx {
+            fields.resize(idx + 1, String::new());
+        }
+        match explicit {
+            Some(value) => fields[idx] = self.escape(value),
+            None if fields[idx].is_empty() => fields[idx] = generate(),
+            None => {}
+        }
+    }
+
+    fn escape(&self, value: &str) -> String {
+        escape_sequence::encode(Cow::Borrowed(value), &self.separators).into_owned()
+    }
+
+    fn current_fields(&mut self) -> &mut Vec<Vec<Vec<String>>> {
+        &mut self
+            .segments
+            .last_mut()
+            .expect("segment() must be called before adding fields")
+            .1
+    }
+
+    fn current_field(&mut self) -> &mut Vec<Vec<String>> {
+        self.current_fields()
+            .last_mut()
+            .expect("field() must be called before adding components or repeats")
+    }
+
+    fn current_repeat(&mut self) -> &mut Vec<String> {
+        // a field always has at least one repeat, as `field()` creates one
+        self.current_field().last_mut().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::Message;
+    use crate::Hl7ParseError;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn ensure_simple_ack_is_built() {
+        let hl7 = MessageBuilder::new()
+            .segment("MSH")
+            .field("CATH")
+            .field("StJohn")
+            .field("AcmeHIS")
+            .field("StJohn")
+            .field("20061019172719")
+            .field("")
+            .field("ACK")
+            .component("O01")
+            .field("MSGID12349876")
+            .field("P")
+            .field("2.3")
+            .segment("MSA")
+            .field("AA")
+            .field("MSGID12349876")
+            .build();
+
+        assert_eq!(hl7, "MSH|^~\\&|CATH|StJohn|AcmeHIS|StJohn|20061019172719||ACK^O01|MSGID12349876|P|2.3\rMSA|AA|MSGID12349876");
+    }
+
+    #[test]
+    fn ensure_repeats_and_escaping_round_trip() -> Result<(), Hl7ParseError> {
+        let hl7 = MessageBuilder::new()
+            .segment("MSH")
+            .field("GHH LAB")
+            .timestamp("200202150930")
+            .control_id("CNTRL-3456")
+            .segment("PID")
+            .field("1")
+            .field("")
+            .field("555-44-4444")
+            .component("")
+            .component("")
+            .component("GHH")
+            .repeat()
+            .component("123456789")
+            .field("")
+            .field("O'BRIEN^PAT|RICK")
+            .build();
+        let msg = Message::try_from(hl7.as_str())?;
+
+        assert_eq!(msg.query("MSH-3"), "GHH LAB");
+        assert_eq!(msg.query("MSH-7"), "200202150930");
+        assert_eq!(msg.query("MSH-10"), "CNTRL-3456");
+        assert_eq!(msg.query("PID-3"), "555-44-4444^^^GHH~123456789");
+        assert_eq!(
+            msg.segments[1].fields[5].value_decoded(),
+            "O'BRIEN^PAT|RICK"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_custom_separators_are_declared() {
+        let separators = Separators {
+            field: '#',
+            component: '@',
+            ..Separators::default()
+        };
+        let hl7 = MessageBuilder::with_separators(separators)
+            .segment("MSH")
+            .field("GHH LAB")
+            .timestamp("200202150930")
+            .control_id("1")
+            .segment("OBX")
+            .field("1")
+            .field("CE")
+            .field("GLU")
+            .component("Glucose")
+            .build();
+
+        assert_eq!(
+            hl7,
+            "MSH#@~\\&#GHH LAB####200202150930###1\rOBX#1#CE#GLU@Glucose"
+        );
+    }
+
+    #[test]
+    fn ensure_missing_control_id_is_generated() {
+        let hl7 = MessageBuilder::new()
+            .segment("MSH")
+            .field("GHH LAB")
+            .timestamp("200202150930")
+            .build();
+        let msg = Message::try_from(hl7.as_str()).unwrap();
+        assert!(!msg.query("MSH-10").is_empty());
+    }
+}
@@ -0,0 +1,273 @@
+/*!
+Structured views over the composite HL7 data types (eg `XTN` telephone numbers or `CE` coded elements), for when working with raw components gets unwieldy.
+
+These borrow from the underlying message source, so are cheap to create on demand.
+
+This module also contains a (partial) schema table recording the data type of the fields in common segments, so generic field access
+can be made self-describing, see `field_datatype()` and `DataType::component_names()`.
+*/
+
+pub mod ce;
+pub mod cx;
+pub mod pl;
+pub mod sn;
+pub mod xpn;
+pub mod xtn;
+
+/// The HL7 data types we have schema information for.
+/// See [the spec](https://hl7-definition.caristix.com/v2/HL7v2.5.1/DataTypes) for more info
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum DataType {
+    CE,
+    CQ,
+    CWE,
+    CX,
+    DLN,
+    DT,
+    EI,
+    HD,
+    ID,
+    IS,
+    MSG,
+    NM,
+    PL,
+    PT,
+    SI,
+    ST,
+    TS,
+    /// The data type is specified by another field, eg OBX-5 is of the type given in OBX-2
+    Varies,
+    VID,
+    XAD,
+    XCN,
+    XPN,
+    XTN,
+}
+
+impl DataType {
+    /// Returns the (human readable) names of the components of this type, in order.  Primitive types have no components so return an empty slice.
+    pub fn component_names(&self) -> &'static [&'static str] {
+        match self {
+            DataType::CE => &[
+                "identifier",
+                "text",
+                "name of coding system",
+                "alternate identifier",
+                "alternate text",
+                "name of alternate coding system",
+            ],
+            DataType::CQ => &["quantity", "units"],
+            DataType::CWE => &[
+                "identifier",
+                "text",
+                "name of coding system",
+                "alternate identifier",
+                "alternate text",
+                "name of alternate coding system",
+                "coding system version id",
+                "alternate coding system version id",
+                "original text",
+            ],
+            DataType::CX => &[
+                "id number",
+                "check digit",
+                "check digit scheme",
+                "assigning authority",
+                "identifier type code",
+                "assigning facility",
+                "effective date",
+                "expiration date",
+                "assigning jurisdiction",
+                "assigning agency or department",
+            ],
+            DataType::DLN => &[
+                "license number",
+                "issuing state, province, country",
+                "expiration date",
+            ],
+            DataType::EI => &[
+                "entity identifier",
+                "namespace id",
+                "universal id",
+                "universal id type",
+            ],
+            DataType::HD => &["namespace id", "universal id", "universal id type"],
+            DataType::MSG => &["message code", "trigger event", "message structure"],
+            DataType::PL => &[
+                "point of care",
+                "room",
+                "bed",
+                "facility",
+                "location status",
+                "person location type",
+                "building",
+                "floor",
+                "location description",
+                "comprehensive location identifier",
+                "assigning authority for location",
+            ],
+            DataType::PT => &["processing id", "processing mode"],
+            DataType::TS => &["time", "degree of precision"],
+            DataType::VID => &[
+                "version id",
+                "internationalization code",
+                "international version id",
+            ],
+            DataType::XAD => &[
+                "street address",
+                "other designation",
+                "city",
+                "state or province",
+                "zip or postal code",
+                "country",
+                "address type",
+                "other geographic designation",
+                "county/parish code",
+                "census tract",
+                "address representation code",
+                "address validity range",
+                "effective date",
+                "expiration date",
+            ],
+            DataType::XCN => &[
+                "id number",
+                "family name",
+                "given name",
+                "second and further given names or initials thereof",
+                "suffix",
+                "prefix",
+                "degree",
+                "source table",
+                "assigning authority",
+                "name type code",
+                "identifier check digit",
+                "check digit scheme",
+                "identifier type code",
+                "assigning facility",
+                "name representation code",
+                "name context",
+                "name validity range",
+                "name assembly order",
+                "effective date",
+                "expiration date",
+                "professional suffix",
+                "assigning jurisdiction",
+                "assigning agency or department",
+            ],
+            DataType::XPN => &[
+                "family name",
+                "given name",
+                "second and further given names or initials thereof",
+                "suffix",
+                "prefix",
+                "degree",
+                "name type code",
+                "name representation code",
+                "name context",
+                "name validity range",
+                "name assembly order",
+                "effective date",
+                "expiration date",
+                "professional suffix",
+            ],
+            DataType::XTN => &[
+                "telephone number",
+                "telecommunication use code",
+                "telecommunication equipment type",
+                "email address",
+                "country code",
+                "area/city code",
+                "local number",
+                "extension",
+                "any text",
+                "extension prefix",
+                "speed dial code",
+                "unformatted telephone number",
+            ],
+            DataType::DT
+            | DataType::ID
+            | DataType::IS
+            | DataType::NM
+            | DataType::SI
+            | DataType::ST
+            | DataType::Varies => &[],
+        }
+    }
+
+    /// Returns the name of the given (1-based) component of this type, or `None` if it doesn't have that many components.
+    pub fn component_name(&self, component_number: usize) -> Option<&'static str> {
+        self.component_names()
+            .get(component_number.checked_sub(1)?)
+            .copied()
+    }
+}
+
+/// Looks up the data type of the given segment field (by HL7 field number, so `("PID", 5)` is PID-5) in the v2.5.1 based schema table.
+/// Returns `None` for segments or fields that aren't in the table, which currently covers the commonly used fields of
+/// MSH, EVN, PID, NK1, PV1, AL1, OBR, OBX, ROL, TXA, MSA and QRD.
+/// ## Example:
+/// ```
+/// # use rusthl7::datatypes::{field_datatype, DataType};
+/// assert_eq!(field_datatype("PID", 5), Some(DataType::XPN));
+/// assert_eq!(DataType::XPN.component_name(1), Some("family name"));
+/// ```
+pub fn field_datatype(segment: &str, field_number: usize) -> Option<DataType> {
+    use DataType::*;
+
+    let fields: &[DataType] = match segment {
+        "MSH" => &[
+            ST, ST, HD, HD, HD, HD, TS, ST, MSG, ST, PT, VID, NM, ST, ID, ID, ID, ID, CE,
+        ],
+        "EVN" => &[ID, TS, TS, IS, XCN, TS],
+        "PID" => &[
+            SI, CX, CX, CX, XPN, XPN, TS, IS, XPN, CE, XAD, IS, XTN, XTN, CE, CE, CE, CX, ST, DLN,
+            CX, CE, ST, ID, NM, CE, CE, CE, TS, ID,
+        ],
+        "NK1" => &[SI, XPN, CE, XAD, XTN, XTN, CE],
+        "PV1" => &[SI, IS, PL, IS, CX, PL, XCN, XCN, XCN, IS],
+        "AL1" => &[SI, CE, CE, CE, ST, DT],
+        "OBR" => &[SI, EI, EI, CE, ID, TS, TS, TS],
+        "OBX" => &[
+            SI, ID, CE, ST, Varies, CE, ST, IS, NM, ID, ID, TS, ST, TS, CE, XCN,
+        ],
+        "ROL" => &[EI, ID, CE, XCN, TS, TS],
+        "TXA" => &[
+            SI, IS, ID, TS, XCN, TS, TS, TS, XCN, XCN, XCN, EI, EI, EI, EI, ST, ID, ID, ID, ID, ST,
+        ],
+        "MSA" => &[ID, ST, ST],
+        "QRD" => &[TS, ID, ID, ST, ID, TS, CQ, XCN, CE, CE],
+        _ => &[],
+    };
+
+    fields.get(field_number.checked_sub(1)?).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ensure_field_datatypes_are_found() {
+        assert_eq!(field_datatype("MSH", 9), Some(DataType::MSG));
+        assert_eq!(field_datatype("PID", 3), Some(DataType::CX));
+        assert_eq!(field_datatype("PID", 13), Some(DataType::XTN));
+        assert_eq!(field_datatype("OBX", 3), Some(DataType::CE));
+        assert_eq!(field_datatype("OBX", 5), Some(DataType::Varies));
+        assert_eq!(field_datatype("PID", 0), None);
+        assert_eq!(field_datatype("PID", 99), None);
+        assert_eq!(field_datatype("ZZZ", 1), None);
+    }
+
+    #[test]
+    fn ensure_component_names_are_found() {
+        assert_eq!(DataType::XPN.component_name(1), Some("family name"));
+        assert_eq!(DataType::XPN.component_name(2), Some("given name"));
+        assert_eq!(DataType::MSG.component_name(3), Some("message structure"));
+        assert_eq!(
+            DataType::XTN.component_name(2),
+            Some("telecommunication use code")
+        );
+        assert_eq!(DataType::XPN.component_name(0), None);
+        assert_eq!(DataType::ST.component_name(1), None);
+    }
+}
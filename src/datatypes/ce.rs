@@ -0,0 +1,119 @@
+use crate::separators::Separators;
+
+/// A CE (Coded Element) or CWE (Coded With Exceptions) value, as used by many fields holding coded data (eg OBX-3, DG1-3, AL1-3).
+/// Absent components are returned as `""`.
+///
+/// Only the first six components (shared by CE and CWE) are exposed, CWE's coding system versions and original text are available via the underlying field.
+/// See [the spec](https://hl7-definition.caristix.com/v2/HL7v2.5.1/DataTypes/CE) for more info
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct CodedElement<'a> {
+    pub identifier: &'a str,
+    pub text: &'a str,
+    pub name_of_coding_system: &'a str,
+    pub alternate_identifier: &'a str,
+    pub alternate_text: &'a str,
+    pub name_of_alternate_coding_system: &'a str,
+}
+
+impl<'a> CodedElement<'a> {
+    /// Builds a CE from its (already split) components, eg from `Field::components()`.
+    pub fn from_components(components: &[&'a str]) -> CodedElement<'a> {
+        let component = |idx: usize| components.get(idx).copied().unwrap_or("");
+
+        CodedElement {
+            identifier: component(0),
+            text: component(1),
+            name_of_coding_system: component(2),
+            alternate_identifier: component(3),
+            alternate_text: component(4),
+            name_of_alternate_coding_system: component(5),
+        }
+    }
+
+    /// The code (CE.1), eg `2951-2`
+    #[inline]
+    pub fn code(&self) -> &'a str {
+        self.identifier
+    }
+
+    /// The human readable description of the code (CE.2), eg `SODIUM`
+    #[inline]
+    pub fn text(&self) -> &'a str {
+        self.text
+    }
+
+    /// The coding system the code is from (CE.3), eg `LN` for LOINC
+    #[inline]
+    pub fn system(&self) -> &'a str {
+        self.name_of_coding_system
+    }
+}
+
+/// Parses a single (non-repeating) CE/CWE value into its components.
+/// ## Example:
+/// ```
+/// # use rusthl7::datatypes::ce::parse_ce;
+/// # use rusthl7::separators::Separators;
+/// let ce = parse_ce("2951-2^SODIUM^LN", &Separators::default());
+/// assert_eq!(ce.code(), "2951-2");
+/// assert_eq!(ce.system(), "LN");
+/// assert_eq!(ce.alternate_identifier, "");
+/// ```
+pub fn parse_ce<'a>(value: &'a str, delims: &Separators) -> CodedElement<'a> {
+    let mut components = value.split(delims.component);
+    let mut next = || components.next().unwrap_or("");
+
+    CodedElement {
+        identifier: next(),
+        text: next(),
+        name_of_coding_system: next(),
+        alternate_identifier: next(),
+        alternate_text: next(),
+        name_of_alternate_coding_system: next(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ensure_full_ce_is_parsed() {
+        let d = Separators::default();
+        let ce = parse_ce("70618^PENICILLIN^RXNORM^PCN^Penicillin^L", &d);
+
+        assert_eq!(ce.code(), "70618");
+        assert_eq!(ce.text(), "PENICILLIN");
+        assert_eq!(ce.system(), "RXNORM");
+        assert_eq!(ce.alternate_identifier, "PCN");
+        assert_eq!(ce.alternate_text, "Penicillin");
+        assert_eq!(ce.name_of_alternate_coding_system, "L");
+    }
+
+    #[test]
+    fn ensure_partial_ce_is_parsed() {
+        let d = Separators::default();
+
+        let ce = parse_ce("F", &d);
+        assert_eq!(ce.code(), "F");
+        assert_eq!(ce.text(), "");
+        assert_eq!(ce.system(), "");
+
+        let ce = parse_ce("^Headache", &d);
+        assert_eq!(ce.code(), "");
+        assert_eq!(ce.text(), "Headache");
+        assert_eq!(ce.name_of_alternate_coding_system, "");
+
+        assert_eq!(parse_ce("", &d), parse_ce("^^^^^", &d));
+    }
+
+    #[test]
+    fn ensure_ce_uses_message_delimiters() {
+        let d = Separators {
+            component: '@',
+            ..Separators::default()
+        };
+        let ce = parse_ce("2951-2@SODIUM@LN", &d);
+        assert_eq!(ce.text(), "SODIUM");
+    }
+}
@@ -0,0 +1,63 @@
+use crate::fields::Field;
+
+/// A CX (Extended Composite ID) value, as used for identifiers such as PID-3 (patient identifier list) and PV1-19 (visit number).
+/// Absent components are returned as `""`.
+/// See [the spec](https://hl7-definition.caristix.com/v2/HL7v2.5.1/DataTypes/CX) for more info
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Cx<'a> {
+    /// CX.1, the identifier itself, eg an MRN
+    pub id: &'a str,
+    /// CX.2
+    pub check_digit: &'a str,
+    /// CX.3, eg `M10` or `M11`
+    pub check_digit_scheme: &'a str,
+    /// CX.4, the system/organisation that issued the identifier.  This is a HD, so may contain subcomponents.
+    pub assigning_authority: &'a str,
+    /// CX.5, eg `MR` (medical record number) or `SS` (social security number)
+    pub identifier_type_code: &'a str,
+}
+
+impl<'a> Cx<'a> {
+    /// Reads the CX value in the given (zero based) repeat of the field.
+    pub fn from_repeat(field: &Field<'a>, repeat: usize) -> Cx<'a> {
+        let components = field.components().get(repeat);
+        let component = |idx: usize| components.and_then(|c| c.get(idx)).copied().unwrap_or("");
+
+        Cx {
+            id: component(0),
+            check_digit: component(1),
+            check_digit_scheme: component(2),
+            assigning_authority: component(3),
+            identifier_type_code: component(4),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::separators::Separators;
+    use crate::Hl7ParseError;
+
+    #[test]
+    fn ensure_cx_components_are_mapped() -> Result<(), Hl7ParseError> {
+        let d = Separators::default();
+        let field = Field::parse("12345^7^M10^GHH&1.2.3&ISO^MR~555-44-4444^^^^SS", &d)?;
+
+        let mrn = Cx::from_repeat(&field, 0);
+        assert_eq!(mrn.id, "12345");
+        assert_eq!(mrn.check_digit, "7");
+        assert_eq!(mrn.check_digit_scheme, "M10");
+        assert_eq!(mrn.assigning_authority, "GHH&1.2.3&ISO");
+        assert_eq!(mrn.identifier_type_code, "MR");
+
+        let ssn = Cx::from_repeat(&field, 1);
+        assert_eq!(ssn.id, "555-44-4444");
+        assert_eq!(ssn.assigning_authority, "");
+        assert_eq!(ssn.identifier_type_code, "SS");
+
+        // out of range repeats are empty
+        assert_eq!(Cx::from_repeat(&field, 2).id, "");
+        Ok(())
+    }
+}
@@ -0,0 +1,71 @@
+use crate::fields::Field;
+
+/// A PL (Person Location) value, as used for a patient's location such as PV1-3 (assigned patient location).
+/// Absent components are returned as `""`.
+/// See [the spec](https://hl7-definition.caristix.com/v2/HL7v2.5.1/DataTypes/PL) for more info
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Pl<'a> {
+    /// PL.1, eg the ward or nursing unit
+    pub point_of_care: &'a str,
+    /// PL.2
+    pub room: &'a str,
+    /// PL.3
+    pub bed: &'a str,
+    /// PL.4, the facility the location is in.  This is a HD, so may contain subcomponents.
+    pub facility: &'a str,
+    /// PL.5, eg `O` (occupied) or `U` (unoccupied) for a bed
+    pub location_status: &'a str,
+    /// PL.6, the kind of location, eg `N` (nursing unit) or `C` (clinic)
+    pub person_location_type: &'a str,
+    /// PL.7
+    pub building: &'a str,
+    /// PL.8
+    pub floor: &'a str,
+}
+
+impl<'a> Pl<'a> {
+    /// Reads the PL value in the given (zero based) repeat of the field.
+    pub fn from_repeat(field: &Field<'a>, repeat: usize) -> Pl<'a> {
+        let components = field.components().get(repeat);
+        let component = |idx: usize| components.and_then(|c| c.get(idx)).copied().unwrap_or("");
+
+        Pl {
+            point_of_care: component(0),
+            room: component(1),
+            bed: component(2),
+            facility: component(3),
+            location_status: component(4),
+            person_location_type: component(5),
+            building: component(6),
+            floor: component(7),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::separators::Separators;
+    use crate::Hl7ParseError;
+
+    #[test]
+    fn ensure_pl_components_are_mapped() -> Result<(), Hl7ParseError> {
+        let field = Field::parse("4E^401^B^GHH&1.2.3&ISO^O^N^MAIN^4", &Separators::default())?;
+
+        let location = Pl::from_repeat(&field, 0);
+        assert_eq!(location.point_of_care, "4E");
+        assert_eq!(location.room, "401");
+        assert_eq!(location.bed, "B");
+        assert_eq!(location.facility, "GHH&1.2.3&ISO");
+        assert_eq!(location.location_status, "O");
+        assert_eq!(location.person_location_type, "N");
+        assert_eq!(location.building, "MAIN");
+        assert_eq!(location.floor, "4");
+
+        // missing components and repeats are empty
+        let location = Pl::from_repeat(&Field::parse("4E", &Separators::default())?, 0);
+        assert_eq!(location.room, "");
+        assert_eq!(Pl::from_repeat(&field, 1).point_of_care, "");
+        Ok(())
+    }
+}
@@ -0,0 +1,208 @@
+use crate::Hl7ParseError;
+
+/// A SN (Structured Numeric) value, as used for results that aren't a simple number (eg OBX-5 when OBX-2 is `SN`), such as `>^50`,
+/// `^1^-^10` (a range) or `^1^:^128` (a ratio).
+/// See [the spec](https://hl7-definition.caristix.com/v2/HL7v2.5.1/DataTypes/SN) for more info
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Sn<'a> {
+    /// SN.1, one of `>`, `<`, `>=`, `<=`, `=` or `<>`.  `None` means equal to.
+    pub comparator: Option<&'a str>,
+    /// SN.2
+    pub num1: f64,
+    /// SN.3, one of `-` (range), `+` (categorical), `/` or `:` (ratio), or `.` (decimal, eg `1.5` as `^1^.^5`)
+    pub separator: Option<char>,
+    /// SN.4, the second number when there is a separator
+    pub num2: Option<f64>,
+}
+
+const COMPARATORS: &[&str] = &[">", "<", ">=", "<=", "=", "<>"];
+const COMPARATORS_LONGEST_FIRST: &[&str] = &[">=", "<=", "<>", ">", "<", "="];
+const SEPARATORS: &[char] = &['-', '+', '/', '.', ':'];
+
+impl<'a> Sn<'a> {
+    /// Builds a SN from its (already split) components.  Senders often skip the SN structure, so some shorthand forms are also accepted:
+    /// - A lone component is a plain number (eg `182`), optionally prefixed with its comparator (eg `>50`)
+    /// - Two numbers without a comparator (eg `1^10`) are a range, as though sent as `^1^-^10`
+    pub fn from_components(components: &[&'a str]) -> Result<Sn<'a>, Hl7ParseError> {
+        match components {
+            [value] => {
+                let value = value.trim();
+                // check the two char comparators first, so `>=` isn't read as `>` followed by `=50`
+                let comparator = COMPARATORS_LONGEST_FIRST
+                    .iter()
+                    .find(|c| value.starts_with(**c))
+                    .map(|c| &value[..c.len()]);
+                return Ok(Sn {
+                    comparator,
+                    num1: parse_nm(&value[comparator.map_or(0, str::len)..])?,
+                    separator: None,
+                    num2: None,
+                });
+            }
+            [low, high] if parse_nm(low).is_ok() => {
+                return Ok(Sn {
+                    comparator: None,
+                    num1: parse_nm(low)?,
+                    separator: Some('-'),
+                    num2: Some(parse_nm(high)?),
+                });
+            }
+            _ => {}
+        }
+
+        let component = |idx: usize| match components.get(idx).map(|c| c.trim()) {
+            None | Some("") => None,
+            Some(value) => Some(value),
+        };
+        let invalid = |reason: &str| {
+            Hl7ParseError::InvalidNumeric(format!("{} in SN {:?}", reason, components))
+        };
+
+        let comparator = component(0);
+        if let Some(comparator) = comparator {
+            if !COMPARATORS.contains(&comparator) {
+                return Err(invalid("unknown comparator"));
+            }
+        }
+
+        let separator = match component(2) {
+            None => None,
+            Some(sep) => match sep.chars().collect::<Vec<char>>()[..] {
+                [c] if SEPARATORS.contains(&c) => Some(c),
+                _ => return Err(invalid("unknown separator")),
+            },
+        };
+
+        let num1 = parse_nm(component(1).ok_or_else(|| invalid("missing first number"))?)?;
+        let num2 = component(3).map(parse_nm).transpose()?;
+        if separator.is_some() != num2.is_some() {
+            return Err(invalid(
+                "separator and second number must be given together",
+            ));
+        }
+
+        Ok(Sn {
+            comparator,
+            num1,
+            separator,
+            num2,
+        })
+    }
+}
+
+/// Parses a NM (numeric) value, ie an optional sign followed by digits with an optional decimal point, ignoring surrounding whitespace.
+/// This is stricter than `f64::from_str()`, which would also accept values like `inf` or `1e5` that aren't valid HL7.
+/// ## Example:
+/// ```
+/// # use rusthl7::datatypes::sn::parse_nm;
+/// assert_eq!(parse_nm(" -12.5 ").unwrap(), -12.5);
+/// assert!(parse_nm("1e5").is_err());
+/// ```
+pub fn parse_nm(value: &str) -> Result<f64, Hl7ParseError> {
+    let trimmed = value.trim();
+    let unsigned = trimmed.strip_prefix(['+', '-']).unwrap_or(trimmed);
+
+    let mut digits = 0;
+    let mut points = 0;
+    for c in unsigned.chars() {
+        match c {
+            '0'..='9' => digits += 1,
+            '.' => points += 1,
+            _ => return Err(Hl7ParseError::InvalidNumeric(value.to_string())),
+        }
+    }
+    if digits == 0 || points > 1 {
+        return Err(Hl7ParseError::InvalidNumeric(value.to_string()));
+    }
+
+    trimmed
+        .parse::<f64>()
+        .map_err(|_| Hl7ParseError::InvalidNumeric(value.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ensure_numbers_are_parsed() -> Result<(), Hl7ParseError> {
+        assert_eq!(parse_nm("182")?, 182.0);
+        assert_eq!(parse_nm("+0.5")?, 0.5);
+        assert_eq!(parse_nm(".5")?, 0.5);
+        assert_eq!(parse_nm("-7.")?, -7.0);
+
+        for value in [
+            "", " ", "-", ".", "1.2.3", "1e5", "inf", "NaN", "12 3", "abc",
+        ] {
+            assert!(parse_nm(value).is_err(), "{} should be invalid", value);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_structured_numerics_are_parsed() -> Result<(), Hl7ParseError> {
+        let sn = Sn::from_components(&["", "182"])?;
+        assert_eq!(sn.comparator, None);
+        assert_eq!(sn.num1, 182.0);
+
+        let sn = Sn::from_components(&[">=", "50"])?;
+        assert_eq!(sn.comparator, Some(">="));
+        assert_eq!(sn.num1, 50.0);
+        assert_eq!(sn.separator, None);
+
+        let sn = Sn::from_components(&["", "1", ":", "128"])?;
+        assert_eq!(sn.separator, Some(':'));
+        assert_eq!(sn.num2, Some(128.0));
+
+        let sn = Sn::from_components(&["182"])?;
+        assert_eq!(sn.comparator, None);
+        assert_eq!(sn.num1, 182.0);
+        assert_eq!(sn.separator, None);
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_shorthand_structured_numerics_are_parsed() -> Result<(), Hl7ParseError> {
+        let sn = Sn::from_components(&[">50"])?;
+        assert_eq!(sn.comparator, Some(">"));
+        assert_eq!(sn.num1, 50.0);
+        assert_eq!(sn.num2, None);
+
+        let sn = Sn::from_components(&["<=0.5"])?;
+        assert_eq!(sn.comparator, Some("<="));
+        assert_eq!(sn.num1, 0.5);
+
+        let sn = Sn::from_components(&["1", "10"])?;
+        assert_eq!(sn.comparator, None);
+        assert_eq!(sn.num1, 1.0);
+        assert_eq!(sn.separator, Some('-'));
+        assert_eq!(sn.num2, Some(10.0));
+
+        for components in [&[">"][..], &["=>5"], &["1", "x"], &["> 5x"]] {
+            assert!(
+                Sn::from_components(components).is_err(),
+                "{:?} should be invalid",
+                components
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_bad_structured_numerics_are_errors() {
+        for components in [
+            &["~", "1"][..],
+            &["", ""],
+            &["", "1", "x", "2"],
+            &["", "1", "-"],
+            &["", "1", "", "2"],
+            &["", "abc"],
+        ] {
+            assert!(
+                Sn::from_components(components).is_err(),
+                "{:?} should be invalid",
+                components
+            );
+        }
+    }
+}
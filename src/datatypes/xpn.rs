@@ -0,0 +1,102 @@
+use crate::fields::Field;
+
+/// A XPN (Extended Person Name) value, as used for names such as PID-5 (patient name) and NK1-2 (next of kin).
+/// Absent components are returned as `""`.
+///
+/// Provider names (XCN, eg OBR-16) embed the same components after an id, so can be read with `from_xcn_repeat()`, although the XCN
+/// name type code is XCN.10 rather than following the degree.
+/// See [the spec](https://hl7-definition.caristix.com/v2/HL7v2.5.1/DataTypes/XPN) for more info
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Xpn<'a> {
+    /// XPN.1, just the surname (the first subcomponent), without any prefixes such as "van"
+    pub family: &'a str,
+    /// XPN.2
+    pub given: &'a str,
+    /// XPN.3, second and further given names or initials
+    pub middle: &'a str,
+    /// XPN.4, eg `JR` or `III`
+    pub suffix: &'a str,
+    /// XPN.5, eg `DR`
+    pub prefix: &'a str,
+    /// XPN.6, eg `MD`
+    pub degree: &'a str,
+    /// XPN.7, eg `L` (legal name) or `A` (alias)
+    pub name_type_code: &'a str,
+}
+
+impl<'a> Xpn<'a> {
+    /// Reads the XPN value in the given (zero based) repeat of the field.
+    pub fn from_repeat(field: &Field<'a>, repeat: usize) -> Xpn<'a> {
+        Xpn::read(field, repeat, 0)
+    }
+
+    /// Reads the name from the XCN value in the given (zero based) repeat of the field, skipping the id (XCN.1) that precedes it.
+    pub fn from_xcn_repeat(field: &Field<'a>, repeat: usize) -> Xpn<'a> {
+        Xpn::read(field, repeat, 1)
+    }
+
+    /// Reads the name from the components of the given repeat starting at `first_component`.  Only the first subcomponent of each
+    /// component is used, so the family name excludes any prefixes.
+    fn read(field: &Field<'a>, repeat: usize, first_component: usize) -> Xpn<'a> {
+        let components = field.subcomponents().get(repeat);
+        let component = |idx: usize| {
+            components
+                .and_then(|c| c.get(first_component + idx))
+                .and_then(|subs| subs.first())
+                .copied()
+                .unwrap_or("")
+        };
+
+        Xpn {
+            family: component(0),
+            given: component(1),
+            middle: component(2),
+            suffix: component(3),
+            prefix: component(4),
+            degree: component(5),
+            name_type_code: component(6),
+        }
+    }
+
+    /// Renders the name for display as "Given Middle Family", skipping any parts that are missing.
+    pub fn formatted(&self) -> String {
+        [self.given, self.middle, self.family]
+            .iter()
+            .filter(|part| !part.is_empty())
+            .copied()
+            .collect::<Vec<&str>>()
+            .join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::separators::Separators;
+    use crate::Hl7ParseError;
+
+    #[test]
+    fn ensure_family_name_prefixes_are_excluded() -> Result<(), Hl7ParseError> {
+        let field = Field::parse("GOGH&VAN^VINCENT^W^JR^DR^^A", &Separators::default())?;
+        let name = Xpn::from_repeat(&field, 0);
+
+        assert_eq!(name.family, "GOGH");
+        assert_eq!(name.suffix, "JR");
+        assert_eq!(name.prefix, "DR");
+        assert_eq!(name.degree, "");
+        assert_eq!(name.name_type_code, "A");
+        assert_eq!(name.formatted(), "VINCENT W GOGH");
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_empty_name_is_formatted_empty() {
+        let field = Field::parse("", &Separators::default()).unwrap();
+        let name = Xpn::from_repeat(&field, 0);
+        assert_eq!(name.family, "");
+        assert_eq!(name.formatted(), "");
+
+        // as is a repeat past the end of the field
+        assert_eq!(Xpn::from_repeat(&field, 3), name);
+    }
+}
@@ -0,0 +1,111 @@
+use crate::fields::Field;
+
+/// A single XTN (Extended Telecommunication Number) value, as found in PID-13/14 and NK1-5.
+/// Empty components are returned as `None`.
+/// See [the spec](https://hl7-definition.caristix.com/v2/HL7v2.5.1/DataTypes/XTN) for more info
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct XtnContact<'a> {
+    /// XTN.1, the number as free text (deprecated in favour of the split out components, but still widely used)
+    pub telephone_number: Option<&'a str>,
+    /// XTN.2, eg `PRN` (primary residence), `WPN` (work) or `NET` (email)
+    pub use_code: Option<&'a str>,
+    /// XTN.3, eg `PH` (telephone), `CP` (cellular) or `Internet`
+    pub equipment_type: Option<&'a str>,
+    pub email_address: Option<&'a str>,
+    pub country_code: Option<&'a str>,
+    pub area_code: Option<&'a str>,
+    pub local_number: Option<&'a str>,
+    pub extension: Option<&'a str>,
+    pub any_text: Option<&'a str>,
+}
+
+impl<'a> XtnContact<'a> {
+    /// Reads the XTN value in the given (zero based) repeat of the field.
+    pub fn from_repeat(field: &Field<'a>, repeat: usize) -> XtnContact<'a> {
+        let component = |idx| match field[(repeat, idx)] {
+            "" => None,
+            value => Some(value),
+        };
+
+        XtnContact {
+            telephone_number: component(0),
+            use_code: component(1),
+            equipment_type: component(2),
+            email_address: component(3),
+            country_code: component(4),
+            area_code: component(5),
+            local_number: component(6),
+            extension: component(7),
+            any_text: component(8),
+        }
+    }
+
+    /// Returns the phone number as just its digits, eg for matching or dialling.
+    /// The split out country/area/local components are preferred where provided, otherwise the digits are taken from the free text XTN.1.
+    /// Extensions aren't included.  Returns `None` if there's no number at all (eg an email-only contact).
+    pub fn normalized_number(&self) -> Option<String> {
+        let parts = match self.local_number {
+            Some(_) => vec![self.country_code, self.area_code, self.local_number],
+            None => vec![self.telephone_number],
+        };
+
+        let digits: String = parts
+            .into_iter()
+            .flatten()
+            .flat_map(|p| p.chars())
+            .filter(|c| c.is_ascii_digit())
+            .collect();
+
+        if digits.is_empty() {
+            None
+        } else {
+            Some(digits)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{separators::Separators, Hl7ParseError};
+
+    #[test]
+    fn ensure_home_and_work_numbers_are_parsed() -> Result<(), Hl7ParseError> {
+        let d = Separators::default();
+        let field = Field::parse("(206)334-5232^PRN^PH~^WPN^PH^^1^206^7521212^123", &d)?;
+
+        let contacts = field.as_contacts();
+        assert_eq!(contacts.len(), 2);
+
+        assert_eq!(contacts[0].telephone_number, Some("(206)334-5232"));
+        assert_eq!(contacts[0].use_code, Some("PRN"));
+        assert_eq!(contacts[0].equipment_type, Some("PH"));
+        assert_eq!(
+            contacts[0].normalized_number().as_deref(),
+            Some("2063345232")
+        );
+
+        assert_eq!(contacts[1].telephone_number, None);
+        assert_eq!(contacts[1].use_code, Some("WPN"));
+        assert_eq!(contacts[1].area_code, Some("206"));
+        assert_eq!(contacts[1].extension, Some("123"));
+        assert_eq!(
+            contacts[1].normalized_number().as_deref(),
+            Some("12067521212")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_email_contact_has_no_number() -> Result<(), Hl7ParseError> {
+        let d = Separators::default();
+        let field = Field::parse("^NET^Internet^eve@example.com", &d)?;
+
+        let contact = field.as_contacts()[0];
+        assert_eq!(contact.email_address, Some("eve@example.com"));
+        assert_eq!(contact.normalized_number(), None);
+
+        assert!(Field::parse("", &d)?.as_contacts().is_empty());
+        Ok(())
+    }
+}
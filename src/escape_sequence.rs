@@ -4,7 +4,8 @@ use regex::Regex;
 use crate::separators::Separators;
 use std::borrow::Cow;
 
-/// This struct provides the decoding functionality to parse escape sequences from the source string back to their original chars.
+/// This struct provides the decoding functionality to parse escape sequences from the source string back to their original chars,
+/// and the encoding functionality to go the other way (see `encode()`).
 ///
 /// For more info see [here](https://www.lyniate.com/knowledge-hub/hl7-escape-sequences/) or [here](https://confluence.hl7australia.com/display/OOADRM20181/Appendix+1+Parsing+HL7v2#Appendix1ParsingHL7v2-Dealingwithreservedcharactersanddelimiters)
 ///
@@ -41,6 +42,7 @@ pub struct EscapeSequence {
     component_buf: [u8; 1],
     subcomponent_buf: [u8; 1],
     escape_regex: Regex,
+    delims: Separators,
 }
 
 impl<'a> EscapeSequence {
@@ -64,6 +66,7 @@ impl<'a> EscapeSequence {
             component_buf: [0; 1],
             subcomponent_buf: [0; 1],
             escape_regex: regex,
+            delims,
         };
 
         // We need &str to inject into the output buffer, convert the `Char` here
@@ -78,6 +81,24 @@ impl<'a> EscapeSequence {
         return_val
     }
 
+    /// The inverse of `decode()`, call this to replace any delimiter chars in the given &str with their escape sequences so the value can be safely embedded in a message.
+    ///
+    /// The escape char, field, repeat, component and sub-component separators become `\E\`, `\F\`, `\R\`, `\S\` and `\T\` respectively, while carriage returns
+    /// and line feeds become `\X0D\` and `\X0A\` so they can't be mistaken for a segment separator.  If there's nothing to escape the input is returned as-is without allocating.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::escape_sequence::EscapeSequence;
+    /// # use rusthl7::separators::Separators;
+    /// let escaper = EscapeSequence::new(Separators::default());
+    /// assert_eq!(escaper.encode("Obstetrician & Gynaecologist"), r#"Obstetrician \T\ Gynaecologist"#);
+    /// ```
+    pub fn encode<S>(&self, input: S) -> Cow<'a, str>
+    where
+        S: Into<Cow<'a, str>>,
+    {
+        encode(input.into(), &self.delims)
+    }
+
     /// This is where the magic happens.  Call this to update any escape sequences in the given &str.
     pub fn decode<S>(&self, input: S) -> Cow<'a, str>
     where
@@ -157,18 +178,25 @@ impl<'a> EscapeSequence {
                                 output.extend_from_slice(sequence.as_bytes());
                                 output.extend_from_slice(&self.escape_buf);
                             } else if let Some(hex_code) = sequence.strip_prefix('X') {
-                                let hex = hex::decode(hex_code)
-                                    .expect("Unable to parse X-value into valid hex");
-                                println!("Converted hex code {} to {:?}", hex_code, hex);
-                                output.extend_from_slice(&hex);
+                                match hex::decode(hex_code) {
+                                    Ok(hex) => {
+                                        trace!("Converted hex code {} to {:?}", hex_code, hex);
+                                        output.extend_from_slice(&hex);
+                                    }
+                                    Err(_) => {
+                                        // malformed (eg an odd number of digits), leave it as-is rather than guess
+                                        trace!("Invalid hex sequence, extending output...");
+                                        output.extend_from_slice(
+                                            input[start_index..=end_index].as_bytes(),
+                                        );
+                                    }
+                                }
 
                             // TODO: Add more sequences
                             } else {
                                 // not a known sequence, must just be two backslashes randomly in a string
                                 trace!("Unknown sequence, extending output...");
-                                output.extend_from_slice(
-                                    input[start_index - 1..end_index].as_bytes(),
-                                );
+                                output.extend_from_slice(input[start_index..=end_index].as_bytes());
                                 // include both the initial escape char, and also the final one.
                             }
                         }
@@ -177,7 +205,11 @@ impl<'a> EscapeSequence {
                     i = end_index + 1; // move through buffer, we we've covered everything up to this point now
                 } // while more chars in input to loop through
 
-                Cow::Owned(String::from_utf8(output).unwrap())
+                // hex sequences can produce bytes that aren't valid UTF-8 (eg Latin-1 chars), replace those rather than panicking
+                match String::from_utf8(output) {
+                    Ok(decoded) => Cow::Owned(decoded),
+                    Err(e) => Cow::Owned(String::from_utf8_lossy(e.as_bytes()).into_owned()),
+                }
             }
             None => {
                 // no escape char in the string at all, just return what we have
@@ -187,6 +219,71 @@ impl<'a> EscapeSequence {
     }
 }
 
+/// Escapes any delimiter chars in the input, see `EscapeSequence::encode()`.  This doesn't need any of the setup `decode()` does, so is
+/// available crate-wide without having to build an `EscapeSequence`.
+pub(crate) fn encode<'a>(input: Cow<'a, str>, delims: &Separators) -> Cow<'a, str> {
+    let needs_escaping = |c: char| {
+        c == delims.escape_char
+            || c == delims.field
+            || c == delims.repeat
+            || c == delims.component
+            || c == delims.subcomponent
+            || c == '\r'
+            || c == '\n'
+    };
+
+    if !input.contains(needs_escaping) {
+        return input; // nothing to do, avoid allocating
+    }
+
+    let mut output = String::with_capacity(input.len() + 8);
+    for c in input.chars() {
+        let sequence = match c {
+            c if c == delims.escape_char => "E",
+            c if c == delims.field => "F",
+            c if c == delims.repeat => "R",
+            c if c == delims.component => "S",
+            c if c == delims.subcomponent => "T",
+            '\r' => "X0D",
+            '\n' => "X0A",
+            c => {
+                output.push(c);
+                continue;
+            }
+        };
+        output.push(delims.escape_char);
+        output.push_str(sequence);
+        output.push(delims.escape_char);
+    }
+    Cow::Owned(output)
+}
+
+/// Converts an (already escaped) value from one set of delimiters to another.  Existing escape sequences are kept, but wrapped in the new
+/// escape char, while literal text is escaped for the new delimiters (so eg a `#` becomes `\F\` when `#` is the new field separator).
+/// An unterminated escape sequence is treated as literal text.
+pub(crate) fn reencode<'a>(input: &'a str, from: &Separators, to: &Separators) -> Cow<'a, str> {
+    if from == to {
+        return Cow::Borrowed(input);
+    }
+
+    let mut output = String::with_capacity(input.len() + 8);
+    let mut rest = input;
+    while let Some(start) = rest.find(from.escape_char) {
+        let after = start + from.escape_char.len_utf8();
+        let end = match rest[after..].find(from.escape_char) {
+            Some(end) => after + end,
+            None => break,
+        };
+        output.push_str(&encode(Cow::Borrowed(&rest[..start]), to));
+        output.push(to.escape_char);
+        output.push_str(&rest[after..end]);
+        output.push(to.escape_char);
+        rest = &rest[end + from.escape_char.len_utf8()..];
+    }
+    output.push_str(&encode(Cow::Borrowed(rest), to));
+    Cow::Owned(output)
+}
+
 #[cfg(test)]
 mod tests {
     use std::str::FromStr;
@@ -327,4 +424,76 @@ mod tests {
         let output = escaper.decode(input);
         assert_eq!(output, input);
     }
+
+    #[test]
+    fn ensure_decode_leaves_malformed_hex_codes() {
+        let delims = Separators::default();
+        let escaper = EscapeSequence::new(delims);
+
+        // unterminated
+        let input = r#"Line 1\X0A"#;
+        assert_eq!(escaper.decode(input), input);
+
+        // not valid hex
+        let input = r#"Line 1\X0\Line 2\XZZ\"#;
+        assert_eq!(escaper.decode(input), input);
+
+        // not valid UTF-8
+        assert_eq!(escaper.decode(r#"caf\XE9\"#), "caf\u{FFFD}");
+    }
+
+    #[test]
+    fn ensure_decode_keeps_unknown_sequences_intact() {
+        let delims = Separators::default();
+        let escaper = EscapeSequence::new(delims);
+
+        let input = r#"C:\temp\file and \F\"#;
+        assert_eq!(escaper.decode(input), r#"C:\temp\file and |"#);
+    }
+
+    #[test]
+    fn ensure_encode_does_nothing_if_not_required() {
+        let escaper = EscapeSequence::new(Separators::default());
+
+        let input = "There are no delimiters here/there/.";
+        assert!(matches!(escaper.encode(input), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn ensure_encode_round_trips_through_decode() {
+        let delims = Separators::default();
+        let escaper = EscapeSequence::new(delims);
+
+        let input = "Pipes | hats ^ tildes ~ ands & slashes \\ and\r\nnewlines";
+        let encoded = escaper.encode(input);
+        assert_eq!(
+            encoded,
+            r#"Pipes \F\ hats \S\ tildes \R\ ands \T\ slashes \E\ and\X0D\\X0A\newlines"#
+        );
+        assert_eq!(escaper.decode(encoded), input);
+    }
+
+    #[test]
+    fn ensure_encode_handles_custom_delims() {
+        let delims = Separators::from_str("MSH^!@#$").unwrap();
+        let escaper = EscapeSequence::new(delims);
+
+        let output = escaper.encode("Escape this ^ and # please");
+        assert_eq!(output, "Escape this #F# and #E# please");
+    }
+
+    #[test]
+    fn ensure_values_are_reencoded_for_new_delims() {
+        let from = Separators::default();
+        let to = Separators::from_str("MSH#@~!$").unwrap();
+
+        assert_eq!(reencode("plain", &from, &to), "plain");
+        assert_eq!(reencode(r#"A \T\ B"#, &from, &to), "A !T! B");
+        assert_eq!(reencode("Suite #4 & 5!", &from, &to), "Suite !F!4 & 5!E!");
+        assert_eq!(reencode(r#"open \ended"#, &from, &to), r#"open \ended"#);
+        assert!(matches!(
+            reencode("A#B", &from, &from),
+            Cow::Borrowed("A#B")
+        ));
+    }
 }
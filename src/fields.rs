@@ -1,124 +1,797 @@
+use super::datatypes::ce::CodedElement;
+use super::datatypes::cx::Cx;
+use super::datatypes::sn::{self, Sn};
+use super::datatypes::xpn::Xpn;
+use super::datatypes::xtn::XtnContact;
+use super::escape_sequence::{self, EscapeSequence};
+use super::parse_options::ParseOptions;
 use super::separators::Separators;
 use super::*;
+use std::borrow::Cow;
 use std::fmt::Display;
 use std::ops::Index;
+use std::sync::OnceLock;
 
 /// Represents a single field inside the HL7.  Note that fields can include repeats, components and sub-components.
 /// See [the spec](http://www.hl7.eu/HL7v2x/v251/std251/ch02.html#Heading13) for more info
-#[derive(Debug, PartialEq)]
+///
+/// By default a field is split into its repeats, components and subcomponents as it's parsed, filling in the public fields of the same
+/// names.  Parsing with `ParseOptions::lazy_fields` instead keeps only the source, splitting it the first time it's needed (eg via
+/// `components()` or indexing), so fields that are only ever read with `value()` cost nothing beyond the parse.  The accessor methods
+/// work either way, the public fields are left empty for a lazy field.
 pub struct Field<'a> {
     pub source: &'a str,
     pub delims: Separators,
+    #[deprecated(
+        since = "0.6.0",
+        note = "use `repeats()`, which also works for fields parsed with `ParseOptions::lazy_fields`"
+    )]
     pub repeats: Vec<&'a str>,
+    #[deprecated(
+        since = "0.6.0",
+        note = "use `components()`, which also works for fields parsed with `ParseOptions::lazy_fields`"
+    )]
     pub components: Vec<Vec<&'a str>>,
+    #[deprecated(
+        since = "0.6.0",
+        note = "use `subcomponents()` or `subcomponents_mut()`, which also work for fields parsed with `ParseOptions::lazy_fields`"
+    )]
     pub subcomponents: Vec<Vec<Vec<&'a str>>>,
+    /// Whether surrounding whitespace is trimmed from each part when the field is split, see `ParseOptions::trim_whitespace`
+    trim_whitespace: bool,
+    /// Whether the field is split on demand into `parts`, rather than into the public fields as it's parsed
+    lazy: bool,
+    parts: OnceLock<FieldParts<'a>>,
 }
 
-impl<'a> Field<'a> {
-    /// Convert the given line of text into a field.
-    pub fn parse<S: Into<&'a str>>(
-        input: S,
-        delims: &Separators,
-    ) -> Result<Field<'a>, Hl7ParseError> {
-        let input = input.into();
-        let repeats: Vec<&'a str> = input.split(delims.repeat).collect();
+/// A field's source split on each of the delimiters, built on demand by `Field::parts()`.
+#[derive(Debug, PartialEq, Clone)]
+struct FieldParts<'a> {
+    repeats: Vec<&'a str>,
+    components: Vec<Vec<&'a str>>,
+    subcomponents: Vec<Vec<Vec<&'a str>>>,
+}
+
+impl<'a> FieldParts<'a> {
+    fn split(input: &'a str, delims: &Separators, trim_whitespace: bool) -> FieldParts<'a> {
+        let trim = |part: &'a str| if trim_whitespace { part.trim() } else { part };
+        let repeats: Vec<&'a str> = input.split(delims.repeat).map(trim).collect();
         let components: Vec<Vec<&'a str>> = repeats
             .iter()
-            .map(|r| r.split(delims.component).collect::<Vec<&'a str>>())
+            .map(|r| {
+                r.split(delims.component)
+                    .map(trim)
+                    .collect::<Vec<&'a str>>()
+            })
             .collect();
         let subcomponents: Vec<Vec<Vec<&'a str>>> = components
             .iter()
             .map(|r| {
                 r.iter()
-                    .map(|c| c.split(delims.subcomponent).collect::<Vec<&'a str>>())
+                    .map(|c| {
+                        c.split(delims.subcomponent)
+                            .map(trim)
+                            .collect::<Vec<&'a str>>()
+                    })
                     .collect::<Vec<Vec<&'a str>>>()
             })
             .collect();
-        let field = Field {
-            source: input,
-            delims: *delims,
+        FieldParts {
             repeats,
             components,
             subcomponents,
+        }
+    }
+}
+
+impl<'a> Field<'a> {
+    /// Convert the given line of text into a field.  This never fails, see `parse_with_options()` to defer splitting the field up until
+    /// it's needed.
+    pub fn parse<S: Into<&'a str>>(
+        input: S,
+        delims: &Separators,
+    ) -> Result<Field<'a>, Hl7ParseError> {
+        Ok(Field::from_source(
+            input.into(),
+            delims,
+            &ParseOptions::default(),
+        ))
+    }
+
+    /// As per `parse()`, but applying the field level `options`.  With `trim_whitespace` set the source and each repeat, component and
+    /// subcomponent have surrounding whitespace removed, so `value()` is the whole field trimmed while `rebuild()` is built from the
+    /// trimmed parts.  With `lazy_fields` set the field isn't split up until it's needed.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::fields::Field;
+    /// # use rusthl7::parse_options::ParseOptions;
+    /// # use rusthl7::separators::Separators;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let options = ParseOptions { trim_whitespace: true, ..Default::default() };
+    /// let f = Field::parse_with_options(" 1554-5 ^ GLUCOSE ", &Separators::default(), &options)?;
+    /// assert_eq!(f.value(), "1554-5 ^ GLUCOSE");
+    /// assert_eq!(f[(0, 1)], "GLUCOSE");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn parse_with_options<S: Into<&'a str>>(
+        input: S,
+        delims: &Separators,
+        options: &ParseOptions,
+    ) -> Result<Field<'a>, Hl7ParseError> {
+        Ok(Field::from_source(input.into(), delims, options))
+    }
+
+    /// Builds a field from its source as per the field level `options`.  `parse()` and `parse_with_options()` both wrap this, it's used
+    /// directly where a `Result` would only need unwrapping.
+    #[allow(deprecated)]
+    pub(crate) fn from_source(
+        input: &'a str,
+        delims: &Separators,
+        options: &ParseOptions,
+    ) -> Field<'a> {
+        let source = if options.trim_whitespace {
+            input.trim()
+        } else {
+            input
+        };
+        let (repeats, components, subcomponents) = if options.lazy_fields {
+            (Vec::new(), Vec::new(), Vec::new())
+        } else {
+            let parts = FieldParts::split(source, delims, options.trim_whitespace);
+            (parts.repeats, parts.components, parts.subcomponents)
         };
-        Ok(field)
+
+        Field {
+            source,
+            delims: *delims,
+            repeats,
+            components,
+            subcomponents,
+            trim_whitespace: options.trim_whitespace,
+            lazy: options.lazy_fields,
+            parts: OnceLock::new(),
+        }
+    }
+
+    /// Splits the source up on first use, and returns the cached result from then on.
+    fn parts(&self) -> &FieldParts<'a> {
+        self.parts
+            .get_or_init(|| FieldParts::split(self.source, &self.delims, self.trim_whitespace))
+    }
+
+    /// Returns true if the field has been split up, either as it was parsed or since.
+    fn is_split(&self) -> bool {
+        !self.lazy || self.parts.get().is_some()
+    }
+
+    /// Returns the field split into repeats, eg `a^b~c` is `["a^b", "c"]`.  There's always at least one (possibly empty) repeat.
+    #[inline]
+    #[allow(deprecated)]
+    pub fn repeats(&self) -> &[&'a str] {
+        if self.lazy {
+            &self.parts().repeats
+        } else {
+            &self.repeats
+        }
+    }
+
+    /// Returns each repeat split into components, eg `a^b~c` is `[["a", "b"], ["c"]]`.
+    #[inline]
+    #[allow(deprecated)]
+    pub fn components(&self) -> &[Vec<&'a str>] {
+        if self.lazy {
+            &self.parts().components
+        } else {
+            &self.components
+        }
+    }
+
+    /// Returns each component split into subcomponents, eg `a^b&c` is `[[["a"], ["b", "c"]]]`.
+    #[inline]
+    #[allow(deprecated)]
+    pub fn subcomponents(&self) -> &[Vec<Vec<&'a str>>] {
+        if self.lazy {
+            &self.parts().subcomponents
+        } else {
+            &self.subcomponents
+        }
+    }
+
+    /// Iterates the repeats of the field, each as a `RepeatView` over that repeat's components.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::fields::Field;
+    /// # use rusthl7::separators::Separators;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let f = Field::parse("DOE^JOHN~SMITH^JANE", &Separators::default())?;
+    /// let given: Vec<&str> = f.iter_repeats().filter_map(|r| r.get(1)).collect();
+    /// assert_eq!(given, vec!["JOHN", "JANE"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn iter_repeats(&self) -> impl Iterator<Item = RepeatView<'_, 'a>> {
+        self.repeats()
+            .iter()
+            .zip(self.components().iter().zip(self.subcomponents().iter()))
+            .map(|(value, (components, subcomponents))| RepeatView {
+                value,
+                components,
+                subcomponents,
+            })
+    }
+
+    /// Returns the subcomponents for editing in place, see `rebuild()`.  Only `subcomponents()` (and so `rebuild()`) reflect any edits,
+    /// `source`, `repeats()` and `components()` are left as parsed.
+    #[allow(deprecated)]
+    pub fn subcomponents_mut(&mut self) -> &mut Vec<Vec<Vec<&'a str>>> {
+        if !self.lazy {
+            return &mut self.subcomponents;
+        }
+        self.parts();
+        &mut self
+            .parts
+            .get_mut()
+            .expect("parts are initialised above")
+            .subcomponents
     }
 
     /// Used to hide the removal of NoneError for #2...  If passed `Some()` value it returns a field with that value.  If passed `None() it returns an `Err(Hl7ParseError::MissingRequiredValue{})`
+    /// naming the missing field (eg `Some("MSH-9")`) if one is given, to make it clear which part of a malformed message is the problem.
     pub fn parse_mandatory(
         input: Option<&'a str>,
         delims: &Separators,
+        field: Option<&str>,
     ) -> Result<Field<'a>, Hl7ParseError> {
         match input {
             Some(string_value) => Field::parse(string_value, delims),
-            None => Err(Hl7ParseError::MissingRequiredValue {}),
+            None => Err(Hl7ParseError::MissingRequiredValue {
+                field: field.map(|f| f.to_string()),
+            }),
         }
     }
 
     /// Converts a possibly blank string into a possibly blank field!  
     /// Note this handles optional fields, not the nul (`""`) value.
+    ///
+    /// Both an absent field and a present but empty one become `None` here, as they mean the same thing to the receiver.  Where the
+    /// difference matters (eg rebuilding a segment exactly), use the generic `Segment`, which keeps every field that was present in
+    /// `fields` (see `Field::is_empty()` and `Segment::rebuild()`).
+    ///
+    /// The explicit HL7 null (`""`) is present, so is returned as `Some`, check `is_null()` to tell it apart from a value.
     pub fn parse_optional(
         input: Option<&'a str>,
         delims: &Separators,
     ) -> Result<Option<Field<'a>>, Hl7ParseError> {
         match input {
             None => Ok(None),
-            Some(x) if x.is_empty() => Ok(None),
+            Some("") => Ok(None),
             Some(x) => Ok(Some(Field::parse(x, delims)?)),
         }
     }
 
+    /// Escapes an arbitrary value so it can be safely embedded as a single field value, replacing any delimiter chars with their escape
+    /// sequences (`\F\`, `\S\`, `\R\`, `\T\`, `\E\`, and `\X0D\`/`\X0A\` for line breaks).  This is the inverse of `value_decoded()`.
+    ///
+    /// As fields borrow their source this returns the escaped text, ready for `Field::parse()` or for building into a message.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::fields::Field;
+    /// # use rusthl7::separators::Separators;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let delims = Separators::default();
+    /// let escaped = Field::from_value("Smith & Sons | Est. 1901", &delims);
+    /// assert_eq!(escaped, r#"Smith \T\ Sons \F\ Est. 1901"#);
+    /// assert_eq!(Field::parse(&*escaped, &delims)?.value_decoded(), "Smith & Sons | Est. 1901");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_value(value: &str, delims: &Separators) -> String {
+        escape_sequence::encode(Cow::Borrowed(value), delims).into_owned()
+    }
+
+    /// Assembles the text of a field from a nested slice of repeats, each containing a list of component values.
+    /// Component values are escaped (see `EscapeSequence::encode()`) so any embedded delimiter chars can't corrupt the field structure,
+    /// this is the structured inverse of parsing.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::fields::Field;
+    /// # use rusthl7::separators::Separators;
+    /// let delims = Separators::default();
+    /// let name = Field::from_components(&[&["DOE", "JOHN"], &["SMITH & SONS"]], &delims);
+    /// assert_eq!(name, r#"DOE^JOHN~SMITH \T\ SONS"#);
+    /// ```
+    pub fn from_components(repeats: &[&[&str]], delims: &Separators) -> String {
+        let repeats = repeats
+            .iter()
+            .map(|components| components.iter().map(std::iter::once));
+        join_parts(repeats, delims, |out, c| {
+            out.push_str(&escape_sequence::encode(Cow::Borrowed(c), delims))
+        })
+    }
+
+    /// Reassembles the field text from `subcomponents` using `delims`, so a field that's been modified can be re-emitted.
+    /// Values are used as-is (they're expected to already be escaped), so for an unmodified field this is always equal to `source`.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::fields::Field;
+    /// # use rusthl7::separators::Separators;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let mut f = Field::parse("DOE^JOHN~SMITH^JANE", &Separators::default())?;
+    /// f.subcomponents_mut()[1][1][0] = "JAYNE";
+    /// assert_eq!(f.rebuild(), "DOE^JOHN~SMITH^JAYNE");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn rebuild(&self) -> String {
+        if !self.is_split() {
+            return self.source.to_string(); // never split, so can't have been edited
+        }
+        join_parts(self.subcomponents(), &self.delims, |out, s| out.push_str(s))
+    }
+
+    /// As per `rebuild()`, but assembling the field with different delimiters, eg to forward a message to a system expecting other
+    /// separators.  Each value is re-escaped for the new delimiters, so existing escape sequences take the new escape char and any
+    /// of the new delimiter chars occurring in the data are escaped.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::fields::Field;
+    /// # use rusthl7::separators::Separators;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let f = Field::parse("SUITE #4^A \\T\\ B", &Separators::default())?;
+    /// let hashes = Separators { field: '#', component: '@', ..Separators::default() };
+    /// assert_eq!(f.rebuild_with(&hashes), "SUITE \\F\\4@A \\T\\ B");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn rebuild_with(&self, delims: &Separators) -> String {
+        join_parts(self.subcomponents(), delims, |out, s| {
+            out.push_str(&escape_sequence::reencode(s, &self.delims, delims))
+        })
+    }
+
+    /// Copies this field into an owned `FieldBuf` that can be edited, and then built back into field text.
+    pub fn edit(&self) -> FieldBuf {
+        FieldBuf {
+            subcomponents: self
+                .subcomponents()
+                .iter()
+                .map(|components| {
+                    components
+                        .iter()
+                        .map(|subs| subs.iter().map(|s| s.to_string()).collect())
+                        .collect()
+                })
+                .collect(),
+        }
+    }
+
     /// Compatibility method to get the underlying value of this field.
     #[inline]
     pub fn value(&self) -> &'a str {
         self.source
     }
 
+    /// Returns the value of this field with any HL7 escape sequences (`\F\`, `\S\`, `\T\`, `\R\`, `\E\` and `\Xnn..\`) decoded back to
+    /// the chars they represent, see `EscapeSequence::decode()` for details.  `value()` is left untouched for those needing the raw source.
+    ///
+    /// Malformed sequences are left as-is rather than failing.  If you're decoding many values, caching an `EscapeSequence` will be faster.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::fields::Field;
+    /// # use rusthl7::separators::Separators;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let f = Field::parse(r#"Obstetrician \T\ Gynaecologist\X0D0A\"#, &Separators::default())?;
+    /// assert_eq!(f.value_decoded(), "Obstetrician & Gynaecologist\r\n");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn value_decoded(&self) -> String {
+        if !self.source.contains(self.delims.escape_char) {
+            return self.source.to_string(); // skip building the decoder
+        }
+        EscapeSequence::new(self.delims)
+            .decode(self.source)
+            .into_owned()
+    }
+
+    /// Returns each component of the given (zero-based) repeat as an owned string with escape sequences decoded (as per
+    /// `value_decoded()`), or an empty `Vec` if there's no such repeat.  Subcomponents are left joined within their component.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::fields::Field;
+    /// # use rusthl7::separators::Separators;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let f = Field::parse(r#"SMITH \T\ SONS^EST. 1901"#, &Separators::default())?;
+    /// assert_eq!(f.components_decoded(0), vec!["SMITH & SONS", "EST. 1901"]);
+    /// assert!(f.components_decoded(1).is_empty());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn components_decoded(&self, repeat: usize) -> Vec<String> {
+        let components = match self.components().get(repeat) {
+            Some(components) => components,
+            None => return Vec::new(),
+        };
+        let decoder = EscapeSequence::new(self.delims);
+        components
+            .iter()
+            .map(|c| decoder.decode(*c).into_owned())
+            .collect()
+    }
+
+    /// Interprets this field as a HL7 `TS`/`DTM` timestamp (eg MSH-7, or a date of birth in PID-7), see `timestamp::parse_hl7_datetime()` for
+    /// the supported formats.  Only the first component of the first repeat is used, as `TS` values may include a degree of precision component.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::fields::Field;
+    /// # use rusthl7::separators::Separators;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let dob = Field::parse("19620320", &Separators::default())?;
+    /// assert_eq!(dob.as_datetime()?.to_string(), "1962-03-20 00:00:00");
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "chrono")]
+    pub fn as_datetime(&self) -> Result<chrono::NaiveDateTime, Hl7ParseError> {
+        timestamp::parse_hl7_datetime(self[(0, 0)])
+    }
+
+    /// Returns the number of repeats in the field.  An empty field has a single (empty) repeat.
+    #[inline]
+    pub fn repeat_count(&self) -> usize {
+        self.components().len()
+    }
+
+    /// Returns the number of components in the given (zero-based) repeat, or 0 if there's no such repeat.
+    #[inline]
+    pub fn component_count(&self, repeat: usize) -> usize {
+        self.components().get(repeat).map_or(0, |r| r.len())
+    }
+
+    /// Returns the number of subcomponents in the given (zero-based) repeat and component, or 0 if there's no such component.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::fields::Field;
+    /// # use rusthl7::separators::Separators;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let f = Field::parse("x&x^y&y&y~a", &Separators::default())?;
+    /// assert_eq!(f.repeat_count(), 2);
+    /// assert_eq!(f.component_count(0), 2);
+    /// assert_eq!(f.subcomponent_count(0, 1), 3);
+    /// assert_eq!(f.subcomponent_count(1, 1), 0);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn subcomponent_count(&self, repeat: usize, component: usize) -> usize {
+        self.subcomponents()
+            .get(repeat)
+            .and_then(|r| r.get(component))
+            .map_or(0, |c| c.len())
+    }
+
     /// Export value to str
     #[inline]
     pub fn as_str(&self) -> &'a str {
         self.source
     }
 
+    /// Returns true if the field is present but empty (eg MSH-8 in `...|200202150930||ORU^R01|...`).  A field that's absent entirely
+    /// (eg beyond the last field separator) has no `Field` at all, so the two can always be told apart in `Segment.fields`.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.source.is_empty()
+    }
+
+    /// Returns true if this field holds the explicit HL7 null value (`""`), meaning the receiver should delete any existing value.
+    /// This is distinct from an empty field, which means the value is absent/unchanged.
+    #[inline]
+    pub fn is_null(&self) -> bool {
+        self.source == "\"\""
+    }
+
+    /// Returns the value of this field, or `None` if it's either absent (empty) or the explicit HL7 null (`""`).
+    /// This collapses the absent/null distinction for display and storage, where both mean "no value", use `is_null()` when the difference matters.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::fields::Field;
+    /// # use rusthl7::separators::Separators;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let d = Separators::default();
+    /// assert_eq!(Field::parse("F", &d)?.effective_value(), Some("F"));
+    /// assert_eq!(Field::parse("\"\"", &d)?.effective_value(), None);
+    /// assert_eq!(Field::parse("", &d)?.effective_value(), None);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn effective_value(&self) -> Option<&'a str> {
+        if self.source.is_empty() || self.is_null() {
+            None
+        } else {
+            Some(self.source)
+        }
+    }
+
+    /// Interprets this field as an XTN (telecommunication number, eg PID-13), returning one contact per repeat (so none for an empty field).
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::fields::Field;
+    /// # use rusthl7::separators::Separators;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let phones = Field::parse("(206)334-5232^PRN^PH~(206)752-1212^WPN^PH", &Separators::default())?;
+    /// let contacts = phones.as_contacts();
+    /// assert_eq!(contacts[1].use_code, Some("WPN"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn as_contacts(&self) -> Vec<XtnContact<'a>> {
+        if self.source.is_empty() {
+            return Vec::new();
+        }
+        (0..self.repeats().len())
+            .map(|repeat| XtnContact::from_repeat(self, repeat))
+            .collect()
+    }
+
+    /// Interprets the first repeat of this field as a CX identifier (eg PID-3), use `Cx::from_repeat()` for any further repeats.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::fields::Field;
+    /// # use rusthl7::separators::Separators;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let ids = Field::parse("12345^^^GHH^MR", &Separators::default())?;
+    /// assert_eq!(ids.as_cx().id, "12345");
+    /// assert_eq!(ids.as_cx().identifier_type_code, "MR");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn as_cx(&self) -> Cx<'a> {
+        Cx::from_repeat(self, 0)
+    }
+
+    /// Interprets the first repeat of this field as a CE/CWE coded element (eg OBX-3).
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::fields::Field;
+    /// # use rusthl7::separators::Separators;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let code = Field::parse("1554-5^GLUCOSE^LN", &Separators::default())?;
+    /// assert_eq!(code.as_ce().code(), "1554-5");
+    /// assert_eq!(code.as_ce().system(), "LN");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn as_ce(&self) -> CodedElement<'a> {
+        CodedElement::from_components(&self.components()[0])
+    }
+
+    /// Interprets the first repeat of this field as a XPN person name (eg PID-5).
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::fields::Field;
+    /// # use rusthl7::separators::Separators;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let name = Field::parse("EVERYWOMAN^EVE^E^^^^L", &Separators::default())?;
+    /// assert_eq!(name.as_person_name().family, "EVERYWOMAN");
+    /// assert_eq!(name.as_person_name().formatted(), "EVE E EVERYWOMAN");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn as_person_name(&self) -> Xpn<'a> {
+        Xpn::from_repeat(self, 0)
+    }
+
+    /// Interprets the first component of the first repeat as a `NM` (numeric) value, ignoring surrounding whitespace.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::fields::Field;
+    /// # use rusthl7::separators::Separators;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let result = Field::parse(" 5.5 ", &Separators::default())?;
+    /// assert_eq!(result.as_f64()?, 5.5);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn as_f64(&self) -> Result<f64, Hl7ParseError> {
+        sn::parse_nm(self.components()[0][0])
+    }
+
+    /// Interprets the first component of the first repeat as an integer (eg a `SI` set id), ignoring surrounding whitespace.
+    pub fn as_i64(&self) -> Result<i64, Hl7ParseError> {
+        let value = self.components()[0][0];
+        value
+            .trim()
+            .parse::<i64>()
+            .map_err(|_| Hl7ParseError::InvalidNumeric(value.to_string()))
+    }
+
+    /// Interprets the first repeat as a `SN` (structured numeric) value, eg `>^50` or the `^182` OBX-5 of a `SN` result.
+    /// A plain number (eg `182`) is also accepted, as are the shorthand `>50` and `1^10` (a range) forms, see `Sn::from_components()`.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::fields::Field;
+    /// # use rusthl7::separators::Separators;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let result = Field::parse("<^0.5", &Separators::default())?;
+    /// let sn = result.as_structured_numeric()?;
+    /// assert_eq!(sn.comparator, Some("<"));
+    /// assert_eq!(sn.num1, 0.5);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn as_structured_numeric(&self) -> Result<Sn<'a>, Hl7ParseError> {
+        Sn::from_components(&self.components()[0])
+    }
+
     /// Access string reference of a Field component by String index
     /// Adjust the index by one as medical people do not count from zero
+    ///
+    /// Accepts up to three dot-separated 1-based indexes (repeat, component, subcomponent), eg `R2.C1.S3`.  Only the digits in each part are considered.
+    /// Out-of-range or malformed indexes return `""`.
     pub fn query<'b, S>(&self, sidx: S) -> &'a str
     where
         S: Into<&'b str>,
     {
-        let sidx = sidx.into();
-        let parts = sidx.split('.').collect::<Vec<&str>>();
+        self.find(sidx.into()).unwrap_or("")
+    }
+
+    /// As per `query()`, but returns `None` rather than `""` for out-of-range or malformed indexes.
+    pub(crate) fn find(&self, sidx: &str) -> Option<&'a str> {
+        let indexes: Option<Vec<usize>> = sidx
+            .split('.')
+            .map(|part| {
+                let stringnums = part
+                    .chars()
+                    .filter(|c| c.is_ascii_digit())
+                    .collect::<String>();
+                // convert to zero based, treating `0` as malformed
+                stringnums.parse::<usize>().ok()?.checked_sub(1)
+            })
+            .collect();
 
-        if parts.len() == 1 {
-            let stringnums = parts[0]
-                .chars()
-                .filter(|c| c.is_digit(10))
-                .collect::<String>();
-            let idx: usize = stringnums.parse().unwrap();
+        match indexes?.as_slice() {
+            [repeat] => self.repeats().get(*repeat).copied(),
+            [repeat, component] => self.components().get(*repeat)?.get(*component).copied(),
+            [repeat, component, subcomponent] => self
+                .subcomponents()
+                .get(*repeat)?
+                .get(*component)?
+                .get(*subcomponent)
+                .copied(),
+            _ => None,
+        }
+    }
+}
 
-            self[idx - 1]
-        } else if parts.len() == 2 {
-            let stringnums = parts[0]
-                .chars()
-                .filter(|c| c.is_digit(10))
-                .collect::<String>();
+/// A single repeat of a field, as returned from `Field::iter_repeats()`.  Component indexes are zero-based, as per indexing a `Field`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct RepeatView<'f, 'a> {
+    value: &'a str,
+    components: &'f [&'a str],
+    subcomponents: &'f [Vec<&'a str>],
+}
 
-            let idx0: usize = stringnums.parse().unwrap();
+impl<'f, 'a> RepeatView<'f, 'a> {
+    /// Returns the raw text of the repeat.
+    #[inline]
+    pub fn value(&self) -> &'a str {
+        self.value
+    }
 
-            let stringnums = parts[1]
-                .chars()
-                .filter(|c| c.is_digit(10))
-                .collect::<String>();
+    /// Returns the repeat split into components.  There's always at least one (possibly empty) component.
+    #[inline]
+    pub fn components(&self) -> &'f [&'a str] {
+        self.components
+    }
 
-            let idx1: usize = stringnums.parse().unwrap();
+    /// Returns the given component, or `None` if the repeat doesn't have that many.
+    #[inline]
+    pub fn get(&self, component: usize) -> Option<&'a str> {
+        self.components.get(component).copied()
+    }
 
-            self[(idx0 - 1, idx1 - 1)]
-        } else {
-            ""
+    /// Returns the given component split into subcomponents, or an empty slice if the repeat doesn't have that many components.
+    pub fn subcomponents(&self, component: usize) -> &'f [&'a str] {
+        self.subcomponents
+            .get(component)
+            .map_or(&[], |subs| subs.as_slice())
+    }
+}
+
+/// An owned, editable copy of a field's repeats, components and subcomponents, as returned from `Field::edit()`.
+///
+/// Values are stored as raw field text, so anything set here should already be escaped (see `Field::from_value()`) if it may contain
+/// delimiter chars.
+/// ## Example:
+/// ```
+/// # use rusthl7::Hl7ParseError;
+/// # use rusthl7::fields::Field;
+/// # use rusthl7::separators::Separators;
+/// # fn main() -> Result<(), Hl7ParseError> {
+/// let delims = Separators::default();
+/// let mut name = Field::parse("EVERYWOMAN^EVE^E", &delims)?.edit();
+/// name.set_component(0, 0, "EVERYPERSON".to_string());
+/// name.set_component(1, 0, "DOE".to_string());
+/// assert_eq!(name.build(&delims), "EVERYPERSON^EVE^E~DOE");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct FieldBuf {
+    pub subcomponents: Vec<Vec<Vec<String>>>,
+}
+
+impl FieldBuf {
+    /// Replaces the given (zero-based) component of a repeat, including any subcomponents it had.  Repeats and components past the
+    /// end of the field are added (empty) as needed.
+    pub fn set_component(&mut self, repeat: usize, component: usize, value: String) {
+        if self.subcomponents.len() <= repeat {
+            self.subcomponents
+                .resize(repeat + 1, vec![vec![String::new()]]);
         }
+        let components = &mut self.subcomponents[repeat];
+        if components.len() <= component {
+            components.resize(component + 1, vec![String::new()]);
+        }
+        components[component] = vec![value];
+    }
+
+    /// Assembles the field text using the given delimiters, the same as `Field::rebuild()`.
+    pub fn build(&self, delims: &Separators) -> String {
+        join_parts(&self.subcomponents, delims, |out, s| out.push_str(s))
     }
 }
 
+/// Joins nested repeats → components → subcomponents back into field text with the given delimiters, with `push_value` appending
+/// each (sub)component value to the output (so callers can escape values as they go).
+fn join_parts<R, C, S, V>(
+    repeats: R,
+    delims: &Separators,
+    mut push_value: impl FnMut(&mut String, &str),
+) -> String
+where
+    R: IntoIterator<Item = C>,
+    C: IntoIterator<Item = S>,
+    S: IntoIterator<Item = V>,
+    V: AsRef<str>,
+{
+    let mut out = String::new();
+    for (r, components) in repeats.into_iter().enumerate() {
+        if r > 0 {
+            out.push(delims.repeat);
+        }
+        for (c, subcomponents) in components.into_iter().enumerate() {
+            if c > 0 {
+                out.push(delims.component);
+            }
+            for (s, value) in subcomponents.into_iter().enumerate() {
+                if s > 0 {
+                    out.push(delims.subcomponent);
+                }
+                push_value(&mut out, value.as_ref());
+            }
+        }
+    }
+    out
+}
+
 impl<'a> Display for Field<'a> {
     /// Required for to_string() and other formatter consumers
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -126,10 +799,46 @@ impl<'a> Display for Field<'a> {
     }
 }
 
+impl<'a> PartialEq for Field<'a> {
+    /// Fields are equal if they were parsed from the same source with the same separators and options, and have the same subcomponents where
+    /// either has been edited.
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+            && self.delims == other.delims
+            && self.trim_whitespace == other.trim_whitespace
+            && (!self.is_split() && !other.is_split()
+                || self.subcomponents() == other.subcomponents())
+    }
+}
+
+impl<'a> std::fmt::Debug for Field<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Field")
+            .field("source", &self.source)
+            .field("delims", &self.delims)
+            .field("subcomponents", &self.subcomponents())
+            .finish()
+    }
+}
+
 impl<'a> Clone for Field<'a> {
-    /// Creates a new Message object using a clone of the original's source
+    /// Creates a new Field over the original's source, any edits made via `subcomponents_mut()` aren't copied
     fn clone(&self) -> Self {
-        Field::parse(self.source, &self.delims.clone()).unwrap()
+        let options = ParseOptions {
+            trim_whitespace: self.trim_whitespace,
+            lazy_fields: self.lazy,
+            ..Default::default()
+        };
+        Field::from_source(self.source, &self.delims, &options)
+    }
+}
+
+/// Serializes as nested arrays of repeats → components → subcomponents, ie `"a^b&c"` becomes `[[["a"], ["b", "c"]]]`.
+/// Values are emitted as-is, without decoding any escape sequences.
+#[cfg(feature = "serde")]
+impl<'a> serde::Serialize for Field<'a> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.subcomponents().serialize(serializer)
     }
 }
 
@@ -137,11 +846,7 @@ impl<'a> Index<usize> for Field<'a> {
     type Output = &'a str;
     /// Access string reference of a Field component by numeric index
     fn index(&self, idx: usize) -> &Self::Output {
-        if idx > self.repeats.len() - 1 {
-            return &""; //TODO: We're returning &&str here which doesn't seem right?!?
-        }
-
-        &self.repeats[idx]
+        self.repeats().get(idx).unwrap_or(&"")
     }
 }
 
@@ -149,11 +854,10 @@ impl<'a> Index<(usize, usize)> for Field<'a> {
     type Output = &'a str;
     /// Access string reference of a Field subcomponent by numeric index
     fn index(&self, idx: (usize, usize)) -> &Self::Output {
-        if idx.0 > self.repeats.len() - 1 || idx.1 > self.components[idx.0].len() - 1 {
-            return &""; //TODO: We're returning &&str here which doesn't seem right?!?
-        }
-
-        &self.components[idx.0][idx.1]
+        self.components()
+            .get(idx.0)
+            .and_then(|r| r.get(idx.1))
+            .unwrap_or(&"")
     }
 }
 
@@ -161,14 +865,11 @@ impl<'a> Index<(usize, usize, usize)> for Field<'a> {
     type Output = &'a str;
     /// Access string reference of a Field subcomponent by numeric index
     fn index(&self, idx: (usize, usize, usize)) -> &Self::Output {
-        if idx.0 > self.repeats.len() - 1
-            || idx.1 > self.components[idx.0].len() - 1
-            || idx.2 > self.subcomponents[idx.0][idx.1].len() - 1
-        {
-            return &""; //TODO: We're returning &&str here which doesn't seem right?!?
-        }
-
-        &self.subcomponents[idx.0][idx.1][idx.2]
+        self.subcomponents()
+            .get(idx.0)
+            .and_then(|r| r.get(idx.1))
+            .and_then(|c| c.get(idx.2))
+            .unwrap_or(&"")
     }
 }
 
@@ -179,58 +880,23 @@ impl<'a> Index<String> for Field<'a> {
     /// Access string reference of a Field component by String index
     #[cfg(feature = "string_index")]
     fn index(&self, sidx: String) -> &Self::Output {
-        let parts = sidx.split('.').collect::<Vec<&str>>();
-        match parts.len() {
-            1 => {
-                let stringnums = parts[0]
-                    .chars()
-                    .filter(|c| c.is_digit(10))
-                    .collect::<String>();
-                let idx: usize = stringnums.parse().unwrap();
-
-                &self[idx - 1]
-            }
-            2 => {
-                let stringnums = parts[0]
-                    .chars()
-                    .filter(|c| c.is_digit(10))
-                    .collect::<String>();
-
-                let idx0: usize = stringnums.parse().unwrap();
-
-                let stringnums = parts[1]
-                    .chars()
-                    .filter(|c| c.is_digit(10))
-                    .collect::<String>();
-
-                let idx1: usize = stringnums.parse().unwrap();
-
-                &self[(idx0 - 1, idx1 - 1)]
-            }
-            3 => {
-                let stringnums = parts[0]
-                    .chars()
-                    .filter(|c| c.is_digit(10))
-                    .collect::<String>();
-
-                let idx0: usize = stringnums.parse().unwrap();
-
-                let stringnums = parts[1]
-                    .chars()
-                    .filter(|c| c.is_digit(10))
-                    .collect::<String>();
-
-                let idx1: usize = stringnums.parse().unwrap();
-
-                let stringnums = parts[2]
-                    .chars()
-                    .filter(|c| c.is_digit(10))
-                    .collect::<String>();
-
-                let idx2: usize = stringnums.parse().unwrap();
+        // Each part is a 1 based number with an optional letter prefix (eg `R1.C2`), anything unparseable is treated as out of range
+        let parts = sidx
+            .split('.')
+            .map(|part| {
+                part.chars()
+                    .filter(|c| c.is_ascii_digit())
+                    .collect::<String>()
+                    .parse::<usize>()
+                    .ok()?
+                    .checked_sub(1)
+            })
+            .collect::<Option<Vec<usize>>>();
 
-                &self[(idx0 - 1, idx1 - 1, idx2 - 1)]
-            }
+        match parts.as_deref() {
+            Some(&[idx]) => &self[idx],
+            Some(&[idx0, idx1]) => &self[(idx0, idx1)],
+            Some(&[idx0, idx1, idx2]) => &self[(idx0, idx1, idx2)],
             _ => &"",
         }
     }
@@ -251,14 +917,70 @@ impl<'a> Index<&str> for Field<'a> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn ensure_effective_value_collapses_absent_and_null() -> Result<(), Hl7ParseError> {
+        let d = Separators::default();
+
+        let value = Field::parse("F", &d)?;
+        assert!(!value.is_null());
+        assert_eq!(value.effective_value(), Some("F"));
+
+        let null = Field::parse("\"\"", &d)?;
+        assert!(null.is_null());
+        assert_eq!(null.effective_value(), None);
+
+        let absent = Field::parse("", &d)?;
+        assert!(!absent.is_null());
+        assert_eq!(absent.effective_value(), None);
+
+        // a quoted value isn't null
+        assert_eq!(Field::parse("\"F\"", &d)?.effective_value(), Some("\"F\""));
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_optional_fields_distinguish_absent_empty_and_null() -> Result<(), Hl7ParseError> {
+        let d = Separators::default();
+
+        assert_eq!(Field::parse_optional(None, &d)?, None);
+        assert_eq!(Field::parse_optional(Some(""), &d)?, None);
+
+        let null = Field::parse_optional(Some("\"\""), &d)?.expect("null is present");
+        assert!(null.is_null());
+        assert!(!null.is_empty());
+        assert_eq!(null.effective_value(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_counts_match_the_field_shape() -> Result<(), Hl7ParseError> {
+        let f = Field::parse("x&x^y&y~a&a^b&b", &Separators::default())?;
+
+        assert_eq!(f.repeat_count(), 2);
+        assert_eq!(f.component_count(0), 2);
+        assert_eq!(f.component_count(1), 2);
+        assert_eq!(f.subcomponent_count(0, 0), 2);
+        assert_eq!(f.subcomponent_count(1, 1), 2);
+
+        // out of range is zero rather than a panic
+        assert_eq!(f.component_count(2), 0);
+        assert_eq!(f.subcomponent_count(0, 2), 0);
+        assert_eq!(f.subcomponent_count(2, 0), 0);
+
+        let empty = Field::parse("", &Separators::default())?;
+        assert_eq!(empty.repeat_count(), 1);
+        assert_eq!(empty.component_count(0), 1);
+        Ok(())
+    }
+
     #[test]
     fn test_conditional_parse_handles_none() {
         let d = Separators::default();
 
         //if we pass a none value, we get a None back
         match Field::parse_optional(None, &d) {
-            Ok(None) => assert!(true),
-            _ => assert!(false),
+            Ok(None) => (),
+            _ => panic!(),
         }
     }
 
@@ -268,8 +990,8 @@ mod tests {
 
         //an empty string (as seen when `split()`ing) should be none
         match Field::parse_optional(Some(""), &d) {
-            Ok(None) => assert!(true),
-            _ => assert!(false),
+            Ok(None) => (),
+            _ => panic!(),
         }
     }
 
@@ -280,17 +1002,144 @@ mod tests {
         //an empty string (as seen when `split()`ing) should be none
         match Field::parse_optional(Some("xxx"), &d) {
             Ok(Some(field)) => assert_eq!(field.value(), "xxx"),
-            _ => assert!(false),
+            _ => panic!(),
         }
     }
 
+    #[test]
+    fn ensure_edited_field_is_rebuilt() -> Result<(), Hl7ParseError> {
+        let d = Separators::default();
+        let hl7 = "PID|||555-44-4444||EVERYWOMAN^EVE^E^^^^L|JONES|19620320|F";
+        let segment = crate::segments::Segment::parse(hl7, &d)?;
+
+        let mut name = segment.fields[5].edit();
+        name.set_component(0, 0, Field::from_value("EVERY-PERSON & CO", &d));
+        let name = name.build(&d);
+        assert_eq!(name, "EVERY-PERSON \\T\\ CO^EVE^E^^^^L");
+
+        // serialize the whole segment back with the edited field in place
+        let rebuilt = segment
+            .fields
+            .iter()
+            .enumerate()
+            .map(|(i, f)| if i == 5 { name.clone() } else { f.rebuild() })
+            .collect::<Vec<String>>()
+            .join("|");
+        assert_eq!(
+            rebuilt,
+            "PID|||555-44-4444||EVERY-PERSON \\T\\ CO^EVE^E^^^^L|JONES|19620320|F"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_editing_pads_missing_repeats_and_components() -> Result<(), Hl7ParseError> {
+        let d = Separators::default();
+        let mut f = Field::parse("a&b^c", &d)?.edit();
+        assert_eq!(f.build(&d), "a&b^c");
+
+        f.set_component(0, 0, "x".to_string()); // replaces the subcomponents too
+        f.set_component(2, 1, "y".to_string());
+        assert_eq!(f.build(&d), "x^c~~^y");
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_numeric_values_are_coerced() -> Result<(), Hl7ParseError> {
+        let d = Separators::default();
+
+        let plain = Field::parse("182", &d)?;
+        assert_eq!(plain.as_f64()?, 182.0);
+        assert_eq!(plain.as_i64()?, 182);
+        assert_eq!(plain.as_structured_numeric()?.num1, 182.0);
+
+        let sn = Field::parse("^182", &d)?.as_structured_numeric()?;
+        assert_eq!(sn.comparator, None);
+        assert_eq!(sn.num1, 182.0);
+        assert_eq!(sn.separator, None);
+        assert_eq!(sn.num2, None);
+
+        let sn = Field::parse(">50", &d)?.as_structured_numeric()?;
+        assert_eq!((sn.comparator, sn.num1), (Some(">"), 50.0));
+
+        let sn = Field::parse("1^10", &d)?.as_structured_numeric()?;
+        assert_eq!(
+            (sn.num1, sn.separator, sn.num2),
+            (1.0, Some('-'), Some(10.0))
+        );
+
+        let decimal = Field::parse(" -1.25 ^mg", &d)?;
+        assert_eq!(decimal.as_f64()?, -1.25);
+        assert!(matches!(
+            decimal.as_i64(),
+            Err(Hl7ParseError::InvalidNumeric(_))
+        ));
+
+        let text = Field::parse("POSITIVE", &d)?;
+        assert!(matches!(
+            text.as_f64(),
+            Err(Hl7ParseError::InvalidNumeric(_))
+        ));
+        assert!(matches!(
+            text.as_i64(),
+            Err(Hl7ParseError::InvalidNumeric(_))
+        ));
+        assert!(matches!(
+            text.as_structured_numeric(),
+            Err(Hl7ParseError::InvalidNumeric(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_sample_identifiers_and_codes_are_mapped() -> Result<(), Hl7ParseError> {
+        let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rPID|||555-44-4444||EVERYWOMAN^EVE^E^^^^L\rOBX|1|SN|1554-5^GLUCOSE^POST 12H CFST:MCNC:PT:SER/PLAS:QN||^182|mg/dl|70_105|H|||F";
+        let msg = crate::message::Message::parse(hl7)?;
+
+        let pid_3 = msg.first_segment("PID").unwrap().fields[3].as_cx();
+        assert_eq!(pid_3.id, "555-44-4444");
+        assert_eq!(pid_3.check_digit, "");
+        assert_eq!(pid_3.assigning_authority, "");
+
+        let obx_3 = msg.first_segment("OBX").unwrap().fields[3].as_ce();
+        assert_eq!(obx_3.code(), "1554-5");
+        assert_eq!(obx_3.text(), "GLUCOSE");
+        assert_eq!(obx_3.system(), "POST 12H CFST:MCNC:PT:SER/PLAS:QN");
+        assert_eq!(obx_3.alternate_identifier, "");
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_sample_names_are_mapped() -> Result<(), Hl7ParseError> {
+        let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rPID|||555-44-4444||EVERYWOMAN^EVE^E^^^^L|JONES|19620320|F\rOBR|1|845439^GHH OE|1045813^GHH LAB|15545^GLUCOSE|||200202150730|||||||||555-55-5555^PRIMARY^PATRICIA P^^^^MD^^";
+        let msg = crate::message::Message::parse(hl7)?;
+
+        let patient = msg.first_segment("PID").unwrap().fields[5].as_person_name();
+        assert_eq!(patient.family, "EVERYWOMAN");
+        assert_eq!(patient.given, "EVE");
+        assert_eq!(patient.middle, "E");
+        assert_eq!(patient.suffix, "");
+        assert_eq!(patient.prefix, "");
+        assert_eq!(patient.name_type_code, "L");
+        assert_eq!(patient.formatted(), "EVE E EVERYWOMAN");
+
+        // OBR-16 is a XCN, with the name following the provider's id
+        let obr_16 = &msg.first_segment("OBR").unwrap().fields[16];
+        let provider = Xpn::from_xcn_repeat(obr_16, 0);
+        assert_eq!(provider.family, "PRIMARY");
+        assert_eq!(provider.given, "PATRICIA P");
+        assert_eq!(provider.degree, "MD");
+        assert_eq!(provider.formatted(), "PATRICIA P PRIMARY");
+        Ok(())
+    }
+
     #[test]
     fn test_parse_mandatory_handles_some_value() {
         let d = Separators::default();
 
-        match Field::parse_mandatory(Some("xxx"), &d) {
+        match Field::parse_mandatory(Some("xxx"), &d, None) {
             Ok(field) => assert_eq!(field.value(), "xxx"),
-            _ => assert!(false),
+            _ => panic!(),
         }
     }
 
@@ -298,50 +1147,57 @@ mod tests {
     fn test_parse_mandatory_throws_on_none() {
         let d = Separators::default();
 
-        match Field::parse_mandatory(None, &d) {
-            Err(Hl7ParseError::MissingRequiredValue()) => assert!(true),
-            _ => assert!(false),
+        match Field::parse_mandatory(None, &d, None) {
+            Err(Hl7ParseError::MissingRequiredValue { field: None }) => (),
+            _ => panic!(),
+        }
+
+        match Field::parse_mandatory(None, &d, Some("MSH-9")) {
+            Err(Hl7ParseError::MissingRequiredValue { field: Some(field) }) => {
+                assert_eq!(field, "MSH-9")
+            }
+            _ => panic!(),
         }
     }
     #[test]
     fn test_parse_repeats() {
         let d = Separators::default();
-        let f = Field::parse_mandatory(Some("x&x^y&y~a&a^b&b"), &d).unwrap();
-        assert_eq!(f.repeats.len(), 2)
+        let f = Field::parse_mandatory(Some("x&x^y&y~a&a^b&b"), &d, None).unwrap();
+        assert_eq!(f.repeats().len(), 2)
     }
 
     #[test]
     fn test_parse_components() {
         let d = Separators::default();
-        let f = Field::parse_mandatory(Some("xxx^yyy"), &d).unwrap();
-        assert_eq!(f.components[0].len(), 2)
+        let f = Field::parse_mandatory(Some("xxx^yyy"), &d, None).unwrap();
+        assert_eq!(f.components()[0].len(), 2)
     }
 
     #[test]
     fn test_parse_subcomponents() {
         let d = Separators::default();
-        let f = Field::parse_mandatory(Some("xxx^yyy&zzz"), &d).unwrap();
-        assert_eq!(f.subcomponents[0][1].len(), 2)
+        let f = Field::parse_mandatory(Some("xxx^yyy&zzz"), &d, None).unwrap();
+        assert_eq!(f.subcomponents()[0][1].len(), 2)
     }
 
     #[test]
     fn test_to_string() {
         let d = Separators::default();
-        let f = Field::parse_mandatory(Some("xxx^yyy&zzz"), &d).unwrap();
+        let f = Field::parse_mandatory(Some("xxx^yyy&zzz"), &d, None).unwrap();
         assert_eq!(f.to_string(), String::from("xxx^yyy&zzz"))
     }
 
     #[test]
     fn test_clone() {
         let d = Separators::default();
-        let f = Field::parse_mandatory(Some("xxx^yyy&zzz"), &d).unwrap();
+        let f = Field::parse_mandatory(Some("xxx^yyy&zzz"), &d, None).unwrap();
         assert_eq!(f.to_string(), f.clone().as_str())
     }
 
     #[test]
     fn test_uint_index() {
         let d = Separators::default();
-        let f = Field::parse_mandatory(Some("xxx^yyy&zzz"), &d).unwrap();
+        let f = Field::parse_mandatory(Some("xxx^yyy&zzz"), &d, None).unwrap();
         assert_eq!(f[(0, 1)], "yyy&zzz");
         assert_eq!(f[(0, 1, 1)], "zzz");
     }
@@ -349,12 +1205,28 @@ mod tests {
     #[test]
     fn test_string_query() {
         let d = Separators::default();
-        let f = Field::parse_mandatory(Some("x&x^y&y~a&a^b&b"), &d).unwrap();
+        let f = Field::parse_mandatory(Some("x&x^y&y~a&a^b&b"), &d, None).unwrap();
         let idx0 = String::from("R2");
         let oob = "R2.C3";
         assert_eq!(f.query(&*idx0), "a&a^b&b");
         assert_eq!(f.query("R2.C2"), "b&b");
         assert_eq!(f.query(oob), "");
+        assert_eq!(f.query("R2.C2.S2"), "b");
+        assert_eq!(f.query("R0"), "");
+        assert_eq!(f.query("Rx"), "");
+    }
+
+    #[test]
+    fn test_from_components_round_trips() {
+        let d = Separators::default();
+        let source = Field::from_components(&[&["DOE", "JOHN", "Q&A"], &["", "x^y"]], &d);
+        assert_eq!(source, r#"DOE^JOHN^Q\T\A~^x\S\y"#);
+
+        let f = Field::parse(&*source, &d).unwrap();
+        let decoder = EscapeSequence::new(d);
+        assert_eq!(f.components()[0], vec!["DOE", "JOHN", r#"Q\T\A"#]);
+        assert_eq!(decoder.decode(f.components()[0][2]), "Q&A");
+        assert_eq!(decoder.decode(f.components()[1][1]), "x^y");
     }
 
     #[cfg(feature = "string_index")]
@@ -363,10 +1235,202 @@ mod tests {
         #[test]
         fn test_string_index() {
             let d = Separators::default();
-            let f = Field::parse_mandatory(Some("x&x^y&y~a&a^b&b"), &d).unwrap();
+            let f = Field::parse_mandatory(Some("x&x^y&y~a&a^b&b"), &d, None).unwrap();
             assert_eq!(f["R2"], "a&a^b&b");
             assert_eq!(f["R2.C2"], "b&b");
             assert_eq!(f["R2.C3"], "");
         }
+
+        #[test]
+        fn ensure_malformed_string_index_is_out_of_range() {
+            let d = Separators::default();
+            let f = Field::parse_mandatory(Some("x&x^y&y~a&a^b&b"), &d, None).unwrap();
+            for idx in ["R0", "R", "", "R1.C0", "R1.C1.S0", "R1.C1.S1.X1"] {
+                assert_eq!(f[idx], "", "{:?} should be out of range", idx);
+            }
+        }
+    }
+
+    #[test]
+    fn ensure_rebuild_round_trips() -> Result<(), Hl7ParseError> {
+        let custom = Separators {
+            field: '#',
+            component: '@',
+            ..Separators::default()
+        };
+        let cases = [
+            ("", Separators::default()),
+            ("simple", Separators::default()),
+            ("x^y&z~~a^^b&&c^", Separators::default()),
+            (r"escaped\T\value^two", Separators::default()),
+            ("x@y&z~a@b", custom),
+        ];
+        for (source, delims) in cases.iter() {
+            assert_eq!(Field::parse(*source, delims)?.rebuild(), *source);
+        }
+
+        let mut f = Field::parse("x^y&z~a^b", &Separators::default())?;
+        f.subcomponents_mut()[0][1][1] = "Z";
+        f.subcomponents_mut()[1].push(vec!["c"]);
+        assert_eq!(f.rebuild(), "x^y&Z~a^b^c");
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_value_is_decoded() -> Result<(), Hl7ParseError> {
+        let d = Separators::default();
+
+        let f = Field::parse(r#"\F\\S\\T\\R\\E\ and \X0A\"#, &d)?;
+        assert_eq!(f.value_decoded(), "|^&~\\ and \n");
+        assert_eq!(f.value(), r#"\F\\S\\T\\R\\E\ and \X0A\"#); // raw value untouched
+
+        let f = Field::parse(r#"unterminated \X0A"#, &d)?;
+        assert_eq!(f.value_decoded(), r#"unterminated \X0A"#);
+
+        let f = Field::parse("nothing to decode", &d)?;
+        assert_eq!(f.value_decoded(), "nothing to decode");
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_repeats_are_iterated() -> Result<(), Hl7ParseError> {
+        let f = Field::parse("a&a^b&b~c^d", &Separators::default())?;
+        let repeats: Vec<RepeatView> = f.iter_repeats().collect();
+
+        assert_eq!(repeats.len(), 2);
+        assert_eq!(repeats[0].value(), "a&a^b&b");
+        assert_eq!(repeats[0].components(), ["a&a", "b&b"]);
+        assert_eq!(repeats[0].subcomponents(1), ["b", "b"]);
+        assert_eq!(repeats[1].get(0), Some("c"));
+        assert_eq!(repeats[1].get(2), None);
+        assert!(repeats[1].subcomponents(2).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_fields_are_trimmed_with_options() -> Result<(), Hl7ParseError> {
+        let d = Separators::default();
+        let options = ParseOptions {
+            trim_whitespace: true,
+            ..Default::default()
+        };
+        let source = " a & b ^ c ~ d ";
+
+        let untrimmed = Field::parse(source, &d)?;
+        assert_eq!(untrimmed.value(), source);
+        assert_eq!(untrimmed.subcomponents()[0][0], vec![" a ", " b "]);
+        assert_eq!(untrimmed.rebuild(), source);
+
+        let trimmed = Field::parse_with_options(source, &d, &options)?;
+        assert_eq!(trimmed.value(), "a & b ^ c ~ d");
+        assert_eq!(trimmed.repeats(), ["a & b ^ c", "d"]);
+        assert_eq!(trimmed.components()[0], vec!["a & b", "c"]);
+        assert_eq!(trimmed.subcomponents()[0][0], vec!["a", "b"]);
+        assert_eq!(trimmed.rebuild(), "a&b^c~d");
+        assert_ne!(trimmed, Field::parse("a & b ^ c ~ d", &d)?);
+        assert_eq!(trimmed.clone(), trimmed);
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_components_are_decoded() -> Result<(), Hl7ParseError> {
+        let f = Field::parse(r#"xxx^yyy\S\zzz~a\T\b"#, &Separators::default())?;
+        assert_eq!(f.components_decoded(0), vec!["xxx", "yyy^zzz"]);
+        assert_eq!(f.components_decoded(1), vec!["a&b"]);
+        assert!(f.components_decoded(2).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_from_value_round_trips_through_decode() -> Result<(), Hl7ParseError> {
+        let d = Separators::default();
+        let value = "a|b^c~d&e\\f\r\ng \\F\\";
+
+        let escaped = Field::from_value(value, &d);
+        assert_eq!(escaped, r#"a\F\b\S\c\R\d\T\e\E\f\X0D\\X0A\g \E\F\E\"#);
+
+        // a single value, with no structure leaking out
+        let f = Field::parse(&*escaped, &d)?;
+        assert_eq!(f.repeats().len(), 1);
+        assert_eq!(f.components()[0].len(), 1);
+        assert_eq!(f.subcomponents()[0][0].len(), 1);
+        assert_eq!(f.value_decoded(), value);
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_fields_are_split_lazily() -> Result<(), Hl7ParseError> {
+        let d = Separators::default();
+        let options = ParseOptions {
+            lazy_fields: true,
+            ..Default::default()
+        };
+        let f = Field::parse_with_options("a^b&c~d", &d, &options)?;
+        assert_eq!(f.value(), "a^b&c~d");
+        assert_eq!(f.rebuild(), "a^b&c~d");
+        assert!(f.parts.get().is_none());
+
+        assert_eq!(f.components()[0], vec!["a", "b&c"]);
+        assert!(f.parts.get().is_some());
+        // whether (or when) a field has been split doesn't affect equality
+        assert_eq!(f, Field::parse_with_options("a^b&c~d", &d, &options)?);
+        assert_eq!(f, Field::parse("a^b&c~d", &d)?);
+
+        // the cache mustn't stop parsed messages being shared across threads
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<crate::message::Message>();
+        Ok(())
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn ensure_indexing_an_empty_field_does_not_panic() -> Result<(), Hl7ParseError> {
+        // parsing always produces at least one repeat, but the collections are public so could be emptied
+        let mut f = Field::parse("", &Separators::default())?;
+        f.repeats.clear();
+        f.components.clear();
+        f.subcomponents.clear();
+        assert_eq!(f[0], "");
+        assert_eq!(f[(0, 0)], "");
+        assert_eq!(f[(0, 0, 0)], "");
+        Ok(())
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn ensure_public_parts_are_filled_unless_lazy() -> Result<(), Hl7ParseError> {
+        let d = Separators::default();
+        let mut f = Field::parse("x^y&z~a^b", &d)?;
+        assert_eq!(f.repeats, vec!["x^y&z", "a^b"]);
+        assert_eq!(f.components[1], vec!["a", "b"]);
+        assert_eq!(f.subcomponents[0][1], vec!["y", "z"]);
+
+        // edits to the public subcomponents are rebuilt, as they always have been
+        f.subcomponents[0][1][1] = "Z";
+        assert_eq!(f.rebuild(), "x^y&Z~a^b");
+
+        let options = ParseOptions {
+            lazy_fields: true,
+            ..Default::default()
+        };
+        let f = Field::parse_with_options("x^y&z~a^b", &d, &options)?;
+        assert!(f.repeats.is_empty());
+        assert_eq!(f.subcomponents()[0][1], vec!["y", "z"]);
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_out_of_range_indexes_do_not_panic() -> Result<(), Hl7ParseError> {
+        let f = Field::parse("a^b&c~d", &Separators::default())?;
+
+        assert_eq!(f[2], "");
+        assert_eq!(f[(0, 2)], "");
+        assert_eq!(f[(5, 0)], "");
+        assert_eq!(f[(0, 1, 2)], "");
+        assert_eq!(f[(1, 1, 0)], "");
+        assert_eq!(f[(5, 5, 5)], "");
+        assert_eq!(f[usize::MAX], "");
+        assert_eq!(f[(0, 1, 1)], "c");
+        Ok(())
     }
 }
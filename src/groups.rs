@@ -0,0 +1,441 @@
+/*!
+Nesting of a message's (flat) segments into the groups defined by its abstract message syntax, see `Message::groups()`.
+
+Only a handful of message structures are known (see `STRUCTURES`).  The grammar is applied leniently: missing required segments (or groups)
+aren't an error, so a message that doesn't strictly conform still gets grouped as sensibly as possible rather than rejected.
+*/
+
+use crate::message::Message;
+use crate::segments::Segment;
+
+/// A group of segments from the abstract message syntax, eg the `ORDER_OBSERVATION` group of an `ORU^R01` containing an OBR and its
+/// `OBSERVATION` groups.
+///
+/// `segments` holds the segments directly within this group (in message order), and `groups` any nested groups.  Segments the structure
+/// doesn't know about (eg Z segments) belong to the innermost group open at the time.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SegmentGroup<'m, 'a> {
+    pub name: &'static str,
+    pub segments: Vec<&'m Segment<'a>>,
+    pub groups: Vec<SegmentGroup<'m, 'a>>,
+}
+
+impl<'m, 'a> SegmentGroup<'m, 'a> {
+    fn new(name: &'static str) -> SegmentGroup<'m, 'a> {
+        SegmentGroup {
+            name,
+            segments: Vec::new(),
+            groups: Vec::new(),
+        }
+    }
+
+    /// Returns the segments directly within this group with the given name.
+    pub fn segments_by_name<'b>(
+        &'b self,
+        name: &'b str,
+    ) -> impl Iterator<Item = &'b Segment<'a>> + 'b {
+        self.segments
+            .iter()
+            .copied()
+            .filter(move |s| s.fields[0].source == name)
+    }
+
+    /// Returns the nested groups with the given name.
+    pub fn groups_by_name<'b>(
+        &'b self,
+        name: &'b str,
+    ) -> impl Iterator<Item = &'b SegmentGroup<'m, 'a>> + 'b {
+        self.groups.iter().filter(move |g| g.name == name)
+    }
+}
+
+/// How often an element may appear, as per the `[ ]` (optional) and `{ }` (repeating) notation of the abstract message syntax.
+#[derive(Debug, Clone, Copy)]
+enum Cardinality {
+    /// `X`
+    One,
+    /// `[X]`
+    Optional,
+    /// `{X}`
+    Repeating,
+    /// `[{X}]`
+    OptionalRepeating,
+}
+use Cardinality::*;
+
+impl Cardinality {
+    fn optional(self) -> bool {
+        matches!(self, Optional | OptionalRepeating)
+    }
+
+    fn repeating(self) -> bool {
+        matches!(self, Repeating | OptionalRepeating)
+    }
+}
+
+/// An element of a message structure, either a segment or a group of elements.
+#[derive(Debug)]
+enum Node {
+    Segment(&'static str, Cardinality),
+    Group(&'static str, Cardinality, &'static [Node]),
+}
+
+const ORU_R01: &[Node] = &[
+    Node::Segment("MSH", One),
+    Node::Segment("SFT", OptionalRepeating),
+    Node::Group(
+        "PATIENT_RESULT",
+        Repeating,
+        &[
+            Node::Group(
+                "PATIENT",
+                Optional,
+                &[
+                    Node::Segment("PID", One),
+                    Node::Segment("PD1", Optional),
+                    Node::Segment("NTE", OptionalRepeating),
+                    Node::Segment("NK1", OptionalRepeating),
+                    Node::Group(
+                        "VISIT",
+                        Optional,
+                        &[Node::Segment("PV1", One), Node::Segment("PV2", Optional)],
+                    ),
+                ],
+            ),
+            Node::Group(
+                "ORDER_OBSERVATION",
+                Repeating,
+                &[
+                    Node::Segment("ORC", Optional),
+                    Node::Segment("OBR", One),
+                    Node::Segment("NTE", OptionalRepeating),
+                    Node::Group(
+                        "TIMING_QTY",
+                        OptionalRepeating,
+                        &[
+                            Node::Segment("TQ1", One),
+                            Node::Segment("TQ2", OptionalRepeating),
+                        ],
+                    ),
+                    Node::Segment("CTD", Optional),
+                    Node::Group(
+                        "OBSERVATION",
+                        OptionalRepeating,
+                        &[
+                            Node::Segment("OBX", One),
+                            Node::Segment("NTE", OptionalRepeating),
+                        ],
+                    ),
+                    Node::Segment("FT1", OptionalRepeating),
+                    Node::Segment("CTI", OptionalRepeating),
+                    Node::Group(
+                        "SPECIMEN",
+                        OptionalRepeating,
+                        &[
+                            Node::Segment("SPM", One),
+                            Node::Segment("OBX", OptionalRepeating),
+                        ],
+                    ),
+                ],
+            ),
+        ],
+    ),
+    Node::Segment("DSC", Optional),
+];
+
+const ADT_A01: &[Node] = &[
+    Node::Segment("MSH", One),
+    Node::Segment("SFT", OptionalRepeating),
+    Node::Segment("EVN", One),
+    Node::Segment("PID", One),
+    Node::Segment("PD1", Optional),
+    Node::Segment("ROL", OptionalRepeating),
+    Node::Segment("NK1", OptionalRepeating),
+    Node::Segment("PV1", One),
+    Node::Segment("PV2", Optional),
+    Node::Segment("ROL", OptionalRepeating),
+    Node::Segment("DB1", OptionalRepeating),
+    Node::Segment("OBX", OptionalRepeating),
+    Node::Segment("AL1", OptionalRepeating),
+    Node::Segment("DG1", OptionalRepeating),
+    Node::Segment("DRG", Optional),
+    Node::Group(
+        "PROCEDURE",
+        OptionalRepeating,
+        &[
+            Node::Segment("PR1", One),
+            Node::Segment("ROL", OptionalRepeating),
+        ],
+    ),
+    Node::Segment("GT1", OptionalRepeating),
+    Node::Group(
+        "INSURANCE",
+        OptionalRepeating,
+        &[
+            Node::Segment("IN1", One),
+            Node::Segment("IN2", Optional),
+            Node::Segment("IN3", OptionalRepeating),
+            Node::Segment("ROL", OptionalRepeating),
+        ],
+    ),
+    Node::Segment("ACC", Optional),
+    Node::Segment("UB1", Optional),
+    Node::Segment("UB2", Optional),
+    Node::Segment("PDA", Optional),
+];
+
+const ACK: &[Node] = &[
+    Node::Segment("MSH", One),
+    Node::Segment("SFT", OptionalRepeating),
+    Node::Segment("UAC", Optional),
+    Node::Segment("MSA", One),
+    Node::Segment("ERR", OptionalRepeating),
+];
+
+/// The message structures `Message::groups()` knows about, as `(structure id, structure)`.
+const STRUCTURES: &[(&str, &[Node])] = &[("ACK", ACK), ("ADT_A01", ADT_A01), ("ORU_R01", ORU_R01)];
+
+/// Trigger events that share the structure of another, as `(message code_trigger event, structure id)`, for when MSH-9.3 isn't sent.
+const STRUCTURE_ALIASES: &[(&str, &str)] = &[
+    ("ADT_A04", "ADT_A01"),
+    ("ADT_A08", "ADT_A01"),
+    ("ADT_A13", "ADT_A01"),
+];
+
+impl Node {
+    /// Returns true if this node could begin with the named segment.
+    fn can_start(&self, segment: &str) -> bool {
+        match self {
+            Node::Segment(name, _) => *name == segment,
+            Node::Group(_, _, children) => starts_with(children, segment),
+        }
+    }
+
+    /// Returns true if the named segment appears anywhere within this node.
+    fn contains(&self, segment: &str) -> bool {
+        match self {
+            Node::Segment(name, _) => *name == segment,
+            Node::Group(_, _, children) => children.iter().any(|c| c.contains(segment)),
+        }
+    }
+
+    fn cardinality(&self) -> Cardinality {
+        match self {
+            Node::Segment(_, cardinality) | Node::Group(_, cardinality, _) => *cardinality,
+        }
+    }
+}
+
+/// Returns true if a sequence of nodes could begin with the named segment, ie it's one of the leading optional nodes or the first required one.
+fn starts_with(nodes: &[Node], segment: &str) -> bool {
+    for node in nodes {
+        if node.can_start(segment) {
+            return true;
+        }
+        if !node.cardinality().optional() {
+            return false;
+        }
+    }
+    false
+}
+
+/// Returns the structure for the message, from MSH-9.3 where it's given, otherwise derived from the message code and trigger event (MSH-9.1/9.2).
+fn structure_for(msg: &Message) -> Option<&'static [Node]> {
+    structure_named(&msg.message_type()?.structure_or_inferred())
+}
+
+/// Returns the structure with the given id (eg `ADT_A01`), or the one it shares (eg `ADT_A04` is an alias of `ADT_A01`).
+fn structure_named(id: &str) -> Option<&'static [Node]> {
+    let id = STRUCTURE_ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == id)
+        .map_or(id, |(_, structure)| structure);
+
+    STRUCTURES
+        .iter()
+        .find(|(structure, _)| *structure == id)
+        .map(|(_, nodes)| *nodes)
+}
+
+/// Returns how many times each segment may occur in the named structure, as `(segment, min, max)` with a `max` of `None` meaning
+/// unbounded.  A segment is only required if neither it nor any group it's in is optional, and is unbounded if it or any group it's
+/// in repeats.  The bounds of a segment appearing in several places (eg ROL) are summed.
+pub(crate) fn segment_bounds(id: &str) -> Option<Vec<(&'static str, usize, Option<usize>)>> {
+    let mut bounds = Vec::new();
+    add_bounds(structure_named(id)?, true, false, &mut bounds);
+    Some(bounds)
+}
+
+fn add_bounds(
+    nodes: &'static [Node],
+    required: bool,
+    repeating: bool,
+    bounds: &mut Vec<(&'static str, usize, Option<usize>)>,
+) {
+    for node in nodes {
+        let cardinality = node.cardinality();
+        let required = required && !cardinality.optional();
+        let repeating = repeating || cardinality.repeating();
+        match node {
+            Node::Segment(name, _) => {
+                let min = usize::from(required);
+                let max = if repeating { None } else { Some(1) };
+                match bounds.iter_mut().find(|(segment, _, _)| segment == name) {
+                    Some((_, total_min, total_max)) => {
+                        *total_min += min;
+                        *total_max = total_max.zip(max).map(|(a, b)| a + b);
+                    }
+                    None => bounds.push((name, min, max)),
+                }
+            }
+            Node::Group(_, _, children) => add_bounds(children, required, repeating, bounds),
+        }
+    }
+}
+
+/// Groups the message's segments as per its structure, returning the top level groups.  See `Message::groups()`.
+pub(crate) fn group_segments<'m, 'a>(msg: &'m Message<'a>) -> Vec<SegmentGroup<'m, 'a>> {
+    let nodes = match structure_for(msg) {
+        Some(nodes) => nodes,
+        None => return Vec::new(),
+    };
+
+    let segments: Vec<&Segment> = msg
+        .segments
+        .iter()
+        .filter(|s| !s.fields[0].source.is_empty()) // trailing segment separator
+        .collect();
+
+    // segments at the top level of the message (eg MSH) aren't in a group, so are collected in a root that's then discarded
+    let mut root = SegmentGroup::new("");
+    let known = |name: &str| nodes.iter().any(|n| n.contains(name));
+    let mut idx = 0;
+    while idx < segments.len() {
+        idx = fill(&mut root, nodes, &segments, idx, &known);
+        if let Some(segment) = segments.get(idx) {
+            // out of place at the top level (eg a second MSH), keep it with the root and carry on
+            root.segments.push(segment);
+            idx += 1;
+        }
+    }
+    root.groups
+}
+
+/// Adds segments to the group from `idx` for as long as they fit the group's nodes, recursing into nested groups.  Returns the index of the
+/// first segment that doesn't belong to the group.
+fn fill<'m, 'a>(
+    group: &mut SegmentGroup<'m, 'a>,
+    nodes: &'static [Node],
+    segments: &[&'m Segment<'a>],
+    mut idx: usize,
+    known: &dyn Fn(&str) -> bool,
+) -> usize {
+    // the next node a segment could match, missing (required) elements are skipped over rather than ending the group
+    let mut pos = 0;
+    while let Some(segment) = segments.get(idx) {
+        let name = segment.fields[0].source;
+        if !known(name) {
+            group.segments.push(segment);
+            idx += 1;
+            continue;
+        }
+
+        let matched = match nodes[pos..].iter().position(|n| n.can_start(name)) {
+            Some(offset) => pos + offset,
+            None => break,
+        };
+        match &nodes[matched] {
+            Node::Segment(..) => {
+                group.segments.push(segment);
+                idx += 1;
+            }
+            Node::Group(name, _, children) => {
+                let mut nested = SegmentGroup::new(name);
+                idx = fill(&mut nested, children, segments, idx, known);
+                group.groups.push(nested);
+            }
+        }
+        // a repeating element may match again, otherwise carry on from the element after it
+        pos = if nodes[matched].cardinality().repeating() {
+            matched
+        } else {
+            matched + 1
+        };
+    }
+    idx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Hl7ParseError;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn ensure_oru_observations_are_nested() -> Result<(), Hl7ParseError> {
+        let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rPID|||555-44-4444\rPV1|1|O\rOBR|1|845439^GHH OE\rOBX|1|NM|1554-5^GLUCOSE||182\rNTE|1||fasting\rOBX|2|NM|2951-2^SODIUM||140\rOBR|2|845440^GHH OE\rZOB|custom\rOBX|1|NM|2823-3^POTASSIUM||4.1\r";
+        let msg = Message::try_from(hl7)?;
+        let groups = msg.groups();
+
+        assert_eq!(groups.len(), 1);
+        let result = &groups[0];
+        assert_eq!(result.name, "PATIENT_RESULT");
+
+        let patient = result.groups_by_name("PATIENT").next().unwrap();
+        assert_eq!(patient.segments[0].query("F3"), "555-44-4444");
+        assert_eq!(patient.groups[0].name, "VISIT");
+
+        let orders: Vec<&SegmentGroup> = result.groups_by_name("ORDER_OBSERVATION").collect();
+        assert_eq!(orders.len(), 2);
+        assert_eq!(orders[0].segments[0].query("F1"), "1");
+        assert_eq!(orders[0].groups.len(), 2);
+        assert_eq!(orders[0].groups[0].segments.len(), 2); // OBX and its NTE
+        assert_eq!(orders[0].groups[1].segments[0].query("F5"), "140");
+
+        // unknown segments stay with the group they appear in
+        assert_eq!(orders[1].segments_by_name("ZOB").count(), 1);
+        assert_eq!(orders[1].groups[0].segments[0].query("F5"), "4.1");
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_adt_groups_are_found() -> Result<(), Hl7ParseError> {
+        let hl7 = "MSH|^~\\&|ADT1|GOOD HEALTH HOSPITAL|GHH LAB|GHH|200202150930||ADT^A04|CNTRL-3456|P|2.4\rEVN|A04|200202150930\rPID|||555-44-4444\rPV1|1|O\rPR1|1||P1\rROL|1|AD|PP\rPR1|2||P2\rIN1|1|PLAN1\rIN2|1\rIN1|2|PLAN2";
+        let msg = Message::try_from(hl7)?;
+        let groups = msg.groups();
+
+        let names: Vec<&str> = groups.iter().map(|g| g.name).collect();
+        assert_eq!(
+            names,
+            vec!["PROCEDURE", "PROCEDURE", "INSURANCE", "INSURANCE"]
+        );
+        assert_eq!(groups[0].segments.len(), 2); // PR1 and its ROL
+        assert_eq!(groups[2].segments.len(), 2); // IN1 and IN2
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_unknown_structures_have_no_groups() -> Result<(), Hl7ParseError> {
+        let hl7 =
+            "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||SIU^S12|CNTRL-3456|P|2.4\rSCH|1";
+        assert!(Message::try_from(hl7)?.groups().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_segment_bounds_follow_the_structure() {
+        let bounds = segment_bounds("ADT_A04").unwrap();
+        let find = |name: &str| bounds.iter().find(|(s, _, _)| *s == name).copied();
+
+        assert_eq!(find("PID"), Some(("PID", 1, Some(1))));
+        assert_eq!(find("PV2"), Some(("PV2", 0, Some(1))));
+        assert_eq!(find("ROL"), Some(("ROL", 0, None)));
+        assert_eq!(find("IN2"), Some(("IN2", 0, None))); // within a repeating group
+        assert_eq!(find("ZPD"), None);
+
+        let bounds = segment_bounds("ORU_R01").unwrap();
+        assert!(bounds.contains(&("OBR", 1, None)));
+        assert!(bounds.contains(&("PID", 0, None)));
+        assert_eq!(segment_bounds("ADT_A17"), None);
+    }
+}
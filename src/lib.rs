@@ -10,20 +10,171 @@ This crate tries to provide the tools to build HL7 systems without dictating _ho
 
 */
 
+pub mod batch;
+pub mod builder;
+pub mod datatypes;
 pub mod escape_sequence;
 pub mod fields;
+pub mod groups;
 pub mod message;
+pub mod mllp;
+pub mod owned_message;
+pub mod parse_options;
+pub mod profile;
+pub mod raw;
+pub mod reader;
+pub mod redact;
 pub mod segments;
 pub mod separators;
+pub mod timestamp;
+mod v2xml;
 
+/// Errors raised while parsing (or building) HL7 content.
+///
+/// This implements `std::error::Error` and `Display` (via `thiserror`), so it composes with `?`, `Box<dyn Error>`, `anyhow` and friends.
 #[derive(Debug, thiserror::Error)]
 pub enum Hl7ParseError {
+    /// Catch-all for failures that don't (yet) have their own variant, the message describes the problem.
     #[error("Unexpected error: {0}")]
     Generic(String),
 
+    /// The message didn't start with a valid MSH segment, so the separator chars couldn't be determined.
     #[error("Failure parsing MSH1/MSH2 while discovering separator chars: {0}")]
     Msh1Msh2(String),
 
-    #[error("Required value missing")]
-    MissingRequiredValue(),
+    /// A MSH segment was too short to be interpreted at all, eg a partial read that stopped before the encoding chars (MSH-2).
+    #[error("Invalid MSH segment: {0}")]
+    InvalidMsh(String),
+
+    /// A field that the spec requires to be present was missing from the segment, often due to a truncated message.
+    /// `field` names the missing field (eg `MSH-9`) when it's known.
+    #[error(
+        "Required value{} missing, the segment may be truncated or malformed",
+        .field.as_ref().map_or(String::new(), |f| format!(" {}", f))
+    )]
+    MissingRequiredValue { field: Option<String> },
+
+    /// A segment id wasn't in the required form (eg lowercase when `ParseOptions::require_uppercase_segment_ids` is set).
+    #[error("Invalid segment id '{0}', segment ids must be uppercase")]
+    InvalidSegmentId(String),
+
+    /// A value that should be numeric (eg a `NM` or `SN` field) couldn't be interpreted as a number.
+    #[error("Invalid numeric value: {0}")]
+    InvalidNumeric(String),
+
+    /// Reading the HL7 source failed (eg from `reader::Hl7Reader`).
+    #[error("IO error reading HL7 source: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// Wraps an error raised by one segment of a message, recording which one it was.  `index` is the zero based index into
+    /// `Message.segments` and `segment` is the segment's name (eg `OBX`).  The wrapped error is available via `Error::source()`, or
+    /// `root_cause()` to look through the context.
+    ///
+    /// Plain `Message::parse()` never returns this, as it only splits each segment into fields, which can't fail.  It's returned by the
+    /// segment by segment checks `Message::parse_with_options()` can make, see `ParseOptions`.
+    #[error("Segment {index} ({segment}): {source}")]
+    AtSegment {
+        index: usize,
+        segment: String,
+        source: Box<Hl7ParseError>,
+    },
+}
+
+impl Hl7ParseError {
+    /// Adds the position of the segment the error was raised by.
+    pub(crate) fn at_segment(self, index: usize, segment: &str) -> Hl7ParseError {
+        Hl7ParseError::AtSegment {
+            index,
+            segment: segment.to_string(),
+            source: Box::new(self),
+        }
+    }
+
+    /// Returns the underlying error, looking through any `AtSegment` context, so callers can match on what went wrong regardless of
+    /// where.
+    pub fn root_cause(&self) -> &Hl7ParseError {
+        match self {
+            Hl7ParseError::AtSegment { source, .. } => source.root_cause(),
+            other => other,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error;
+
+    #[test]
+    fn ensure_errors_display_usefully() {
+        assert_eq!(
+            Hl7ParseError::Generic("Boom".to_string()).to_string(),
+            "Unexpected error: Boom"
+        );
+        assert_eq!(
+            Hl7ParseError::Msh1Msh2("Message doesn't start with 'MSH'".to_string()).to_string(),
+            "Failure parsing MSH1/MSH2 while discovering separator chars: Message doesn't start with 'MSH'"
+        );
+        assert_eq!(
+            Hl7ParseError::InvalidMsh("truncated".to_string()).to_string(),
+            "Invalid MSH segment: truncated"
+        );
+        assert_eq!(
+            Hl7ParseError::MissingRequiredValue { field: None }.to_string(),
+            "Required value missing, the segment may be truncated or malformed"
+        );
+        assert_eq!(
+            Hl7ParseError::MissingRequiredValue {
+                field: Some("MSH-9".to_string())
+            }
+            .to_string(),
+            "Required value MSH-9 missing, the segment may be truncated or malformed"
+        );
+        assert_eq!(
+            Hl7ParseError::InvalidSegmentId("pid".to_string()).to_string(),
+            "Invalid segment id 'pid', segment ids must be uppercase"
+        );
+        assert_eq!(
+            Hl7ParseError::InvalidNumeric("abc".to_string()).to_string(),
+            "Invalid numeric value: abc"
+        );
+        assert_eq!(
+            Hl7ParseError::from(std::io::Error::other("boom")).to_string(),
+            "IO error reading HL7 source: boom"
+        );
+        assert_eq!(
+            Hl7ParseError::InvalidSegmentId("obx".to_string())
+                .at_segment(2, "obx")
+                .to_string(),
+            "Segment 2 (obx): Invalid segment id 'obx', segment ids must be uppercase"
+        );
+    }
+
+    #[test]
+    fn ensure_segment_context_wraps_the_cause() {
+        let err = Hl7ParseError::MissingRequiredValue {
+            field: Some("PID-3".to_string()),
+        }
+        .at_segment(1, "PID");
+
+        let source = err.source().unwrap();
+        assert_eq!(
+            source.to_string(),
+            "Required value PID-3 missing, the segment may be truncated or malformed"
+        );
+        assert!(matches!(
+            err.root_cause(),
+            Hl7ParseError::MissingRequiredValue { .. }
+        ));
+    }
+
+    #[test]
+    fn ensure_errors_box_into_std_error() {
+        fn parse() -> Result<(), Box<dyn Error>> {
+            Err(Hl7ParseError::MissingRequiredValue { field: None })?
+        }
+
+        let err = parse().unwrap_err();
+        assert!(err.downcast_ref::<Hl7ParseError>().is_some());
+    }
 }
@@ -1,25 +1,340 @@
+use super::groups::SegmentGroup;
+use super::owned_message::OwnedMessage;
+use super::parse_options::ParseOptions;
+use super::profile::{MessageProfile, ValidationError};
+use super::segments::msh::MessageType;
 use super::segments::Segment;
 use super::separators::Separators;
 use super::*;
 use std::convert::TryFrom;
 use std::fmt::Display;
-use std::ops::Index;
+use std::ops::{Index, Range};
 
 /// A Message is an entire HL7 message parsed into it's constituent segments, fields, repeats and subcomponents,
 /// and it consists of (1 or more) Segments.
 /// Message parses the source string into &str slices (minimising copying)
+/// With the `serde` feature enabled a Message serializes as its separators and a nested structure of segments → fields → repeats → components
+/// → subcomponents (see the `Segment` and `Field` impls).
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Message<'a> {
+    #[cfg_attr(feature = "serde", serde(skip))]
     pub source: &'a str,
     pub segments: Vec<Segment<'a>>,
     separators: Separators,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    layout: Layout,
+}
+
+/// How a message's source was split up when it was parsed, so an `OwnedMessage` can parse its copy of the source the same way again.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub(crate) struct Layout {
+    /// Segments were split on exactly `Separators::segment` (see `Message::parse_with_separators()`), rather than any line ending
+    pub(crate) exact_segment_separator: bool,
+    /// Fields after the segment name were trimmed, see `ParseOptions::trim_whitespace`
+    pub(crate) trim_whitespace: bool,
+    /// Fields are split on demand, see `ParseOptions::lazy_fields`
+    pub(crate) lazy_fields: bool,
+}
+
+impl Layout {
+    /// Returns the field level options the message was parsed with.
+    fn field_options(&self) -> ParseOptions {
+        ParseOptions {
+            trim_whitespace: self.trim_whitespace,
+            lazy_fields: self.lazy_fields,
+            ..Default::default()
+        }
+    }
+}
+
+/// The location of a single value within a message, as returned from `Message::locate()`.
+/// All values are zero-based indexes into the relevant collections, ie `message.segments[segment].fields[field].subcomponents()[repeat][component][subcomponent]`
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Location {
+    pub segment: usize,
+    pub field: usize,
+    pub repeat: usize,
+    pub component: usize,
+    pub subcomponent: usize,
+}
+
+/// A field (repeat) matched by `Message::find_fields()`.
+/// `occurrence` is the zero-based count of earlier segments with the same name (so the second OBX is occurrence 1), and `field`/`repeat`
+/// are zero-based indexes into `Segment.fields` and `Field::repeats()` respectively.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct FieldLocation<'a> {
+    pub segment: &'a str,
+    pub occurrence: usize,
+    pub field: usize,
+    pub repeat: usize,
+    pub value: &'a str,
+}
+
+/// A difference between two messages, as returned from `Message::diff()`.
+///
+/// Segments are matched up by name and `occurrence`, the zero-based count of earlier segments with the same name.  `field` is a zero-based
+/// index into `Segment.fields`, or `None` where the whole segment was added (`old` is `None`) or removed (`new` is `None`), in which
+/// case the values are the segment source.  A field is `None` on the side where it's absent (or empty).
+#[derive(Debug, PartialEq, Clone)]
+pub struct FieldDiff<'a, 'b> {
+    pub segment: String,
+    pub occurrence: usize,
+    pub field: Option<usize>,
+    pub old: Option<&'a str>,
+    pub new: Option<&'b str>,
+}
+
+/// The fields that typically change every time a message is sent, even when the content is identical: MSH-7 (Date/Time of Message)
+/// and MSH-10 (Message Control ID).  Intended for use with `Message::content_fingerprint()`.
+pub const VOLATILE_FIELDS: &[(&str, usize)] = &[("MSH", 7), ("MSH", 10)];
+
+/// A value returned from `Message::query_with_provenance()`, along with where in the message it came from.
+/// `segment_index` and `field_index` are zero-based indexes into `Message.segments` and `Segment.fields` respectively (`field_index` is `None`
+/// for a query returning a whole segment), and `byte_span` is the position of the value in the message source.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ValueWithProvenance<'a> {
+    pub value: &'a str,
+    pub segment_index: usize,
+    pub field_index: Option<usize>,
+    pub byte_span: Range<usize>,
+}
+
+impl<'a> Display for ValueWithProvenance<'a> {
+    /// Formats as eg `'EVERYWOMAN' from segments[1].fields[5] at bytes 96..106`, for logging
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "'{}' from segments[{}]", self.value, self.segment_index)?;
+        if let Some(field) = self.field_index {
+            write!(f, ".fields[{}]", field)?;
+        }
+        write!(
+            f,
+            " at bytes {}..{}",
+            self.byte_span.start, self.byte_span.end
+        )
+    }
+}
+
+/// The segments `Message::looks_complete()` expects to find for common message codes (MSH-9.1), as `(message code, required segments)`.
+pub const EXPECTED_SEGMENTS: &[(&str, &[&str])] = &[
+    ("ACK", &["MSA"]),
+    ("ADT", &["EVN", "PID"]),
+    ("DFT", &["EVN", "PID", "FT1"]),
+    ("MDM", &["EVN", "PID", "TXA"]),
+    ("ORM", &["PID", "ORC"]),
+    ("ORU", &["OBR"]),
+    ("SIU", &["SCH"]),
+    ("VXU", &["PID", "RXA"]),
+];
+
+/// A field containing chars that can't be represented in the character set declared in MSH-18, as returned from `Message::validate_charset()`.
+/// `segment` and `field` are zero-based indexes into `Message.segments` and `Segment.fields` respectively.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct CharsetViolation<'a> {
+    pub segment: usize,
+    pub field: usize,
+    pub value: &'a str,
+    /// The first char in the field that isn't valid for the declared charset
+    pub character: char,
+}
+
+/// The acknowledgment code (MSA-1) to reply with, see `Message::build_ack()`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum AckCode {
+    /// Application Accept, the message was processed successfully
+    AA,
+    /// Application Error, the message was processed but an error occurred (the sender may resend it)
+    AE,
+    /// Application Reject, the message was rejected outright (eg it failed validation, resending won't help)
+    AR,
+}
+
+impl Display for AckCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let code = match self {
+            AckCode::AA => "AA",
+            AckCode::AE => "AE",
+            AckCode::AR => "AR",
+        };
+        write!(f, "{}", code)
+    }
+}
+
+/// Hashes the value with 64-bit FNV-1a, which is fast and (unlike `DefaultHasher`) guaranteed stable across processes and Rust versions.
+pub(crate) fn fnv1a_64(value: &str) -> u64 {
+    const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    value.bytes().fold(FNV_OFFSET_BASIS, |hash, b| {
+        (hash ^ u64::from(b)).wrapping_mul(FNV_PRIME)
+    })
+}
+
+/// Returns the byte offset of `inner` within `outer`, or `None` if `inner` isn't a slice of `outer`.
+fn offset_within(outer: &str, inner: &str) -> Option<usize> {
+    let start = (inner.as_ptr() as usize).checked_sub(outer.as_ptr() as usize)?;
+    if start + inner.len() > outer.len() {
+        return None;
+    }
+    Some(start)
+}
+
+/// Returns the zero-based occurrence of a segment named `name`, given the counts of the segments seen so far (which are updated).
+fn next_occurrence<'s>(occurrences: &mut Vec<(&'s str, usize)>, name: &'s str) -> usize {
+    match occurrences.iter_mut().find(|(n, _)| *n == name) {
+        Some((_, count)) => {
+            *count += 1;
+            *count
+        }
+        None => {
+            occurrences.push((name, 0));
+            0
+        }
+    }
+}
+
+/// Splits the source into segment lines, accepting `\r`, `\n` or `\r\n` as the segment separator (a `\r\n` pair is a single separator,
+/// rather than producing an empty segment between them).  As with `str::split()`, a trailing separator yields a final empty line.
+///
+/// Only unescaped separators end a segment: one immediately preceded by an escape char that opens an escape sequence (eg `\<CR>`)
+/// is kept in the line, so a value carrying an escaped line break isn't split into a bogus segment.
+pub(crate) fn segment_lines<'s>(
+    source: &'s str,
+    separators: &Separators,
+) -> impl Iterator<Item = &'s str> {
+    split_segments(source, *separators, None)
+}
+
+/// Splits the source on unescaped segment separators, either exactly `separator` or (when `None`) any line ending as for
+/// `segment_lines()`.
+fn split_segments(
+    source: &str,
+    separators: Separators,
+    separator: Option<char>,
+) -> impl Iterator<Item = &str> {
+    let mut rest = Some(source);
+    std::iter::from_fn(move || {
+        let remaining = rest?;
+        match find_segment_end(remaining, &separators, separator) {
+            Some((idx, width)) => {
+                rest = Some(&remaining[idx + width..]);
+                Some(&remaining[..idx])
+            }
+            None => {
+                rest = None;
+                Some(remaining)
+            }
+        }
+    })
+}
+
+/// Returns the index and width of the first unescaped segment separator in `source`.  Escape chars pair up within a field (opening
+/// then closing a sequence such as `\T\`), so a separator straight after a closing escape char still ends the segment.
+fn find_segment_end(
+    source: &str,
+    separators: &Separators,
+    separator: Option<char>,
+) -> Option<(usize, usize)> {
+    let is_separator = |c: char| match separator {
+        Some(s) => c == s,
+        None => matches!(c, '\r' | '\n'),
+    };
+
+    let mut in_escape = false; // between an opening and closing escape char
+    let mut escaped = false; // the previous char opened an escape sequence
+    let mut chars = source.char_indices();
+    while let Some((idx, c)) = chars.next() {
+        if is_separator(c) {
+            let crlf = separator.is_none() && source[idx..].starts_with("\r\n");
+            if !escaped {
+                return Some((idx, if crlf { 2 } else { c.len_utf8() }));
+            }
+            if crlf {
+                chars.next();
+            }
+            in_escape = false;
+            escaped = false;
+        } else if c == separators.escape_char {
+            in_escape = !in_escape;
+            escaped = in_escape;
+        } else {
+            escaped = false;
+            if c == separators.field {
+                in_escape = false;
+            }
+        }
+    }
+    None
+}
+
+/// Reads the first character set declared in MSH-18 of the raw message, returning the encoding to decode it with.  The MSH is read
+/// byte-wise, which is safe as the delimiters and the MSH fields before MSH-18 are ASCII in every supported character set.
+#[cfg(feature = "encoding_rs")]
+fn declared_encoding(input: &[u8]) -> Result<&'static encoding_rs::Encoding, Hl7ParseError> {
+    use encoding_rs::*;
+
+    let msh = input
+        .split(|b| matches!(b, b'\r' | b'\n'))
+        .next()
+        .unwrap_or_default();
+    let charset = match (msh.get(3), msh.get(5)) {
+        (Some(&field), Some(&repeat)) if msh.starts_with(b"MSH") => msh
+            .split(|b| *b == field)
+            .nth(17)
+            .and_then(|f| f.split(|b| *b == repeat).next())
+            .unwrap_or_default(),
+        _ => b"",
+    };
+
+    // the HL7 names (table 0211) for each character set
+    let encoding = match charset {
+        b"" | b"ASCII" | b"ISO IR6" | b"UNICODE UTF-8" => UTF_8,
+        b"8859/1" | b"ISO IR100" => WINDOWS_1252,
+        b"8859/2" => ISO_8859_2,
+        b"8859/3" => ISO_8859_3,
+        b"8859/4" => ISO_8859_4,
+        b"8859/5" => ISO_8859_5,
+        b"8859/6" => ISO_8859_6,
+        b"8859/7" => ISO_8859_7,
+        b"8859/8" => ISO_8859_8,
+        b"8859/9" => WINDOWS_1254,
+        b"8859/15" => ISO_8859_15,
+        b"ISO IR87" | b"ISO IR159" | b"ISO 2022-1994" => ISO_2022_JP,
+        b"ISO IR14" => SHIFT_JIS,
+        b"GB 18030-2000" => GB18030,
+        b"KS X 1001" => EUC_KR,
+        b"BIG-5" => BIG5,
+        other => {
+            return Err(Hl7ParseError::Generic(format!(
+                "Unsupported character set '{}' in MSH-18",
+                String::from_utf8_lossy(other)
+            )))
+        }
+    };
+    Ok(encoding)
+}
+
+/// Generates a control id for outbound messages, combining the current time with a process wide counter so ids generated in quick
+/// succession still differ.
+pub(crate) fn generate_control_id() -> String {
+    use std::sync::atomic::{AtomicU16, Ordering};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    static COUNTER: AtomicU16 = AtomicU16::new(0);
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis());
+    format!(
+        "{:X}{:04X}",
+        millis,
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
 }
 
 impl<'a> Message<'a> {
     pub fn new(source: &'a str) -> Message<'a> {
         let separators = str::parse::<Separators>(source).unwrap();
-        let segments: Vec<Segment<'a>> = source
-            .split(separators.segment)
+        let segments: Vec<Segment<'a>> = segment_lines(source, &separators)
             .map(|line| Segment::parse(line, &separators).unwrap())
             .collect();
 
@@ -27,17 +342,339 @@ impl<'a> Message<'a> {
             source,
             segments,
             separators,
+            layout: Layout::default(),
         }
     }
 
-    /// Extracts generic elements for external use by matching first field to name
-    pub fn segments_by_name(&self, name: &str) -> Result<Vec<&Segment<'a>>, Hl7ParseError> {
-        let found: Vec<&Segment<'a>> = self
-            .segments
-            .iter()
-            .filter(|s| s.fields[0].source == name)
+    /// Parses a source that has already been parsed once with this `layout`, as `OwnedMessage::as_message()` does.  `segment_separators`
+    /// holds the separators for each segment where they vary (see `parse_with_segment_separators()`), and is empty otherwise.
+    pub(crate) fn from_layout(
+        source: &'a str,
+        separators: Separators,
+        layout: Layout,
+        segment_separators: &[Separators],
+    ) -> Message<'a> {
+        let exact = layout.exact_segment_separator.then_some(separators.segment);
+        let options = layout.field_options();
+        let segments = split_segments(source, separators, exact)
+            .enumerate()
+            .map(|(index, line)| {
+                let delims = segment_separators.get(index).unwrap_or(&separators);
+                Segment::split(line, delims, &options)
+            })
+            .collect();
+
+        Message {
+            source,
+            segments,
+            separators,
+            layout,
+        }
+    }
+
+    /// Returns how the source was split up when this message was parsed.
+    pub(crate) fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    /// Parses the source HL7 string into a message, equivalent to `Message::try_from()` for those who'd rather not import `TryFrom`.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::message::Message;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let source = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rPID|||555-44-4444";
+    /// let m = Message::parse(source)?;
+    /// assert_eq!(m.segments.len(), 2);
+    /// assert_eq!(m.to_string(), source);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn parse(source: &'a str) -> Result<Message<'a>, Hl7ParseError> {
+        Message::try_from(source)
+    }
+
+    /// Streams the segments named `name` from the source of a (potentially huge) message to `f` one at a time, without building a `Message`.
+    ///
+    /// Parsing a `Message` materialises every segment up front, which is heavy for eg an ORU carrying tens of thousands of OBX segments of waveform data.
+    /// This instead only parses the separators from the MSH, then parses each matching segment transiently, dropping it before moving on to the next,
+    /// so peak memory is bounded by the largest single segment.  Non-matching segments are skipped without being parsed.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::message::Message;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let source = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rOBX|1|NM|HR||72\rOBX|2|NM|HR||75";
+    /// let mut total = 0;
+    /// Message::for_each_segment(source, "OBX", |obx| total += obx.query("F5").parse::<u32>().unwrap())?;
+    /// assert_eq!(total, 147);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn for_each_segment<F>(source: &'a str, name: &str, mut f: F) -> Result<(), Hl7ParseError>
+    where
+        F: FnMut(&Segment<'a>),
+    {
+        let delimiters = str::parse::<Separators>(source)?;
+
+        for line in segment_lines(source, &delimiters) {
+            if line.split(delimiters.field).next() == Some(name) {
+                let segment = Segment::parse(line, &delimiters)?;
+                f(&segment);
+            }
+        }
+        Ok(())
+    }
+
+    /// Parses a message, additionally enforcing any strictness checks enabled in `options` (eg `require_uppercase_segment_ids`) and
+    /// applying the field level options (`trim_whitespace` and `lazy_fields`).
+    ///
+    /// Repairs may need to allocate a corrected copy of the source, so aren't applied here, call `ParseOptions::apply_repairs()` first if required.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::message::Message;
+    /// # use rusthl7::parse_options::ParseOptions;
+    /// let options = ParseOptions { require_uppercase_segment_ids: true, ..Default::default() };
+    /// let result = Message::parse_with_options("MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rObx|1", &options);
+    /// let err = result.unwrap_err();
+    /// assert!(matches!(err.root_cause(), Hl7ParseError::InvalidSegmentId(_)));
+    /// assert_eq!(err.to_string(), "Segment 1 (Obx): Invalid segment id 'Obx', segment ids must be uppercase");
+    /// ```
+    pub fn parse_with_options(
+        source: &'a str,
+        options: &ParseOptions,
+    ) -> Result<Message<'a>, Hl7ParseError> {
+        let separators = str::parse::<Separators>(source)?;
+        let layout = Layout {
+            exact_segment_separator: false,
+            trim_whitespace: options.trim_whitespace,
+            lazy_fields: options.lazy_fields,
+        };
+        let msg = Message::from_layout(source, separators, layout, &[]);
+        options.check(&msg)?;
+        Ok(msg)
+    }
+
+    /// Parses a message where the delimiters may vary from segment to segment, by asking `select` for the separators to use for each segment line.
+    ///
+    /// **This is an advanced escape hatch for pathological feeds that aren't spec compliant**, the encoding chars are meant to be fixed for the
+    /// whole message by MSH-2, and `Message::try_from()` is what you want in all normal cases.  `select` receives each segment line along with the
+    /// separators derived from the MSH, and returns the separators to parse that line with (returning the MSH set gives the normal behaviour).
+    /// Segments are always split as per `Message::try_from()`, and `get_separators()` still returns the MSH set.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::message::Message;
+    /// # use rusthl7::separators::Separators;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// // a legacy system appends a Z segment using `#` as the field separator
+    /// let source = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rZLG#LEGACY#42";
+    /// let m = Message::parse_with_segment_separators(source, |line, msh| {
+    ///     if line.starts_with("ZLG") {
+    ///         Separators { field: '#', ..msh }
+    ///     } else {
+    ///         msh
+    ///     }
+    /// })?;
+    /// assert_eq!(m.query("ZLG.F2"), "42");
+    /// assert_eq!(m.clone().query("ZLG.F2"), "42");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn parse_with_segment_separators<F>(
+        source: &'a str,
+        select: F,
+    ) -> Result<Message<'a>, Hl7ParseError>
+    where
+        F: Fn(&str, Separators) -> Separators,
+    {
+        let delimiters = str::parse::<Separators>(source)?;
+        let options = ParseOptions::default();
+
+        let segments = segment_lines(source, &delimiters)
+            .map(|line| Segment::split(line, &select(line, delimiters), &options))
             .collect();
-        Ok(found)
+
+        Ok(Message {
+            source,
+            segments,
+            separators: delimiters,
+            layout: Layout::default(),
+        })
+    }
+
+    /// Parses a message with explicitly supplied separators, rather than reading them from the MSH.  Segments are split on exactly
+    /// `separators.segment` (unlike `Message::try_from()` which accepts any line ending), so eg a `\n` separated message can hold
+    /// (non-conformant) carriage returns in its values.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::message::Message;
+    /// # use rusthl7::separators::Separators;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let source = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||MDM^T02|CNTRL-3456|P|2.4\nOBX|1|TX|||Line 1\rLine 2";
+    /// let separators = Separators { segment: '\n', ..Separators::default() };
+    /// let m = Message::parse_with_separators(source, separators)?;
+    /// assert_eq!(m.query("OBX-5"), "Line 1\rLine 2");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn parse_with_separators(
+        source: &'a str,
+        separators: Separators,
+    ) -> Result<Message<'a>, Hl7ParseError> {
+        let layout = Layout {
+            exact_segment_separator: true,
+            ..Layout::default()
+        };
+        Ok(Message::from_layout(source, separators, layout, &[]))
+    }
+
+    /// Parses a message directly from a byte slice, skipping the UTF-8 validation that converting to `&str` would normally perform.
+    ///
+    /// This is a performance escape hatch for hot-path gateways that have already validated their input upstream, `Message::try_from()` should be preferred in all other cases.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::message::Message;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let bytes = b"MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4";
+    /// // Safety: the source is a 7-bit ASCII literal
+    /// let m = unsafe { Message::parse_bytes_unchecked(bytes)? };
+    /// assert_eq!(m.query("MSH.F2"), "GHH LAB");
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Safety
+    /// The caller must guarantee that `bytes` is valid UTF-8 (which includes all 7-bit ASCII input).  Note that Latin-1 (8859/1)
+    /// encoded data with chars above 0x7F is **not** valid UTF-8, and passing it to this function is undefined behaviour.
+    pub unsafe fn parse_bytes_unchecked(bytes: &'a [u8]) -> Result<Message<'a>, Hl7ParseError> {
+        let source = std::str::from_utf8_unchecked(bytes);
+        Message::try_from(source)
+    }
+
+    /// Parses a message from raw bytes in the character set declared by MSH-18 (eg `8859/1`), transcoding it to UTF-8, requires the
+    /// `encoding_rs` feature.  Input without a MSH-18 is read as UTF-8 (which includes 7-bit ASCII).
+    ///
+    /// Only the first character set declared is used to decode the message, as code extension (switching character sets within the
+    /// message) isn't supported.  Unknown character sets, and bytes that aren't valid in the declared one, are errors.  The transcoded
+    /// source is a new buffer, so the result is always owned, use `as_message()` to query it.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::message::Message;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let bytes = b"MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ADT^A01|CNTRL-3456|P|2.4||||||8859/1\rPID|||555-44-4444||M\xdcLLER^EVE";
+    /// let m = Message::parse_bytes(bytes)?;
+    /// assert_eq!(m.as_message().query("PID-5.1"), "MÜLLER");
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "encoding_rs")]
+    pub fn parse_bytes(input: &[u8]) -> Result<OwnedMessage, Hl7ParseError> {
+        let encoding = declared_encoding(input)?;
+        let source = encoding
+            .decode_without_bom_handling_and_without_replacement(input)
+            .ok_or_else(|| {
+                Hl7ParseError::Generic(format!(
+                    "Message isn't valid {} as declared in MSH-18",
+                    encoding.name()
+                ))
+            })?;
+        OwnedMessage::try_from(source.into_owned())
+    }
+
+    /// Maps a byte offset in the source string back to the location of the (sub)component at that position, eg for cursor tracking in an editor.
+    ///
+    /// The delimiter _following_ an item is considered part of that item, so an offset immediately after a value (as a cursor often is) still maps to the value.
+    /// Returns `None` if the offset is beyond the end of the message source.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::message::{Location, Message};
+    /// # use std::convert::TryFrom;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let source = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rOBR|segment^sub&segment";
+    /// let m = Message::try_from(source)?;
+    /// let offset = source.find("sub").unwrap();
+    /// let expected = Location { segment: 1, field: 1, repeat: 0, component: 1, subcomponent: 0 };
+    /// assert_eq!(m.locate(offset), Some(expected));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn locate(&self, byte_offset: usize) -> Option<Location> {
+        if byte_offset > self.source.len() {
+            return None;
+        }
+        let contains = |item: &str| {
+            // every slice we hold points into `self.source`, so we can work out where it starts from the pointers
+            let start = item.as_ptr() as usize - self.source.as_ptr() as usize;
+            byte_offset >= start && byte_offset <= start + item.len()
+        };
+
+        let segment = self.segments.iter().position(|s| contains(s.source))?;
+        let fields = &self.segments[segment].fields;
+        let field = fields.iter().position(|f| contains(f.source))?;
+        let f = &fields[field];
+        let repeat = f.repeats().iter().position(|r| contains(r))?;
+        let component = f.components()[repeat].iter().position(|c| contains(c))?;
+        let subcomponent = f.subcomponents()[repeat][component]
+            .iter()
+            .position(|s| contains(s))?;
+
+        Some(Location {
+            segment,
+            field,
+            repeat,
+            component,
+            subcomponent,
+        })
+    }
+
+    /// Iterates over every segment with the given (case sensitive) name, eg all the OBX segments, in message order.  This doesn't allocate.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::message::Message;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let m = Message::parse("MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rOBX|1|NM|GLU||182\rOBX|2|NM|NA||140")?;
+    /// let values: Vec<&str> = m.segments_by_name("OBX").map(|obx| obx.query("F5")).collect();
+    /// assert_eq!(values, vec!["182", "140"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn segments_by_name<'b>(
+        &'b self,
+        name: &'b str,
+    ) -> impl Iterator<Item = &'b Segment<'a>> + 'b {
+        self.segments
+            .iter()
+            .filter(move |s| s.fields[0].source == name)
+    }
+
+    /// Iterates over the site specific `Z` segments (eg `ZPD` or `ZIN`), which carry local data outside the standard, in message order.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::message::Message;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let m = Message::parse("MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ADT^A01|CNTRL-3456|P|2.4\rPID|1\rZPD|1|custom")?;
+    /// let names: Vec<&str> = m.custom_segments().map(|s| s.segment_name()).collect();
+    /// assert_eq!(names, vec!["ZPD"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn custom_segments(&self) -> impl Iterator<Item = &Segment<'a>> {
+        self.segments
+            .iter()
+            .filter(|s| s.segment_name().starts_with('Z'))
+    }
+
+    /// Returns the first segment with the given (case sensitive) name, if there is one.
+    pub fn first_segment(&self, name: &str) -> Option<&Segment<'a>> {
+        self.segments.iter().find(|s| s.fields[0].source == name)
     }
 
     /// Present input vectors of &generics to vectors of &str
@@ -51,6 +688,161 @@ impl<'a> Message<'a> {
         Ok(vecs)
     }
 
+    /// Finds every field repeat anywhere in the message whose (raw) value matches the predicate, eg to locate a MRN without knowing the
+    /// message structure.  Segment names themselves aren't searched.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::message::Message;
+    /// # use std::convert::TryFrom;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let m = Message::try_from("MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rPID|||555-44-4444~123456789")?;
+    /// let found = m.find_fields(|value| value == "123456789");
+    /// assert_eq!((found[0].segment, found[0].field, found[0].repeat), ("PID", 3, 1));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn find_fields<F: Fn(&str) -> bool>(&self, pred: F) -> Vec<FieldLocation<'a>> {
+        let mut found = Vec::new();
+        let mut occurrences: Vec<(&str, usize)> = Vec::new();
+
+        for segment in &self.segments {
+            let name = segment.fields[0].source;
+            let occurrence = next_occurrence(&mut occurrences, name);
+
+            for (field, f) in segment.fields.iter().enumerate().skip(1) {
+                for (repeat, value) in f.repeats().iter().enumerate() {
+                    if pred(value) {
+                        found.push(FieldLocation {
+                            segment: name,
+                            occurrence,
+                            field,
+                            repeat,
+                            value,
+                        });
+                    }
+                }
+            }
+        }
+        found
+    }
+
+    /// Compares this message with another (eg the input and output of an interface transformation), returning every field that differs
+    /// along with any segments added or removed.  See `FieldDiff` for how segments are matched up.
+    ///
+    /// Fields are compared by structure, so messages using different separators only differ where the content does.
+    /// Absent and empty fields are treated as the same, so trailing empty fields don't count as a change.  Changes are listed in the order
+    /// of this message's segments, followed by the added segments.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::message::Message;
+    /// # use std::convert::TryFrom;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let input = Message::try_from("MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rOBX|1|NM|GLU||182")?;
+    /// let output = Message::try_from("MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rOBX|1|NM|GLU||10.1|mmol/L")?;
+    /// let changes = input.diff(&output);
+    /// assert_eq!(changes.len(), 2);
+    /// assert_eq!((changes[0].field, changes[0].old, changes[0].new), (Some(5), Some("182"), Some("10.1")));
+    /// assert_eq!((changes[1].field, changes[1].old, changes[1].new), (Some(6), None, Some("mmol/L")));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn diff<'b>(&self, other: &Message<'b>) -> Vec<FieldDiff<'a, 'b>> {
+        fn nth<'m, 's>(
+            msg: &'m Message<'s>,
+            name: &str,
+            occurrence: usize,
+        ) -> Option<&'m Segment<'s>> {
+            msg.segments
+                .iter()
+                .filter(|s| s.fields[0].source == name)
+                .nth(occurrence)
+        }
+        fn value<'s>(segment: &Segment<'s>, field: usize) -> Option<&'s str> {
+            segment
+                .fields
+                .get(field)
+                .map(|f| f.source)
+                .filter(|v| !v.is_empty())
+        }
+
+        let mut diffs = Vec::new();
+        let mut occurrences = Vec::new();
+
+        for old in self.segments.iter().filter(|s| !s.source.is_empty()) {
+            let name = old.fields[0].source;
+            let occurrence = next_occurrence(&mut occurrences, name);
+            let new = match nth(other, name, occurrence) {
+                Some(new) => new,
+                None => {
+                    diffs.push(FieldDiff {
+                        segment: name.to_string(),
+                        occurrence,
+                        field: None,
+                        old: Some(old.source),
+                        new: None,
+                    });
+                    continue;
+                }
+            };
+
+            for field in 1..old.fields.len().max(new.fields.len()) {
+                let unchanged = match (old.fields.get(field), new.fields.get(field)) {
+                    (Some(a), Some(b)) if a.delims == b.delims => a.source == b.source,
+                    (Some(a), Some(b)) => a.subcomponents() == b.subcomponents(),
+                    _ => value(old, field).is_none() && value(new, field).is_none(),
+                };
+                if !unchanged {
+                    diffs.push(FieldDiff {
+                        segment: name.to_string(),
+                        occurrence,
+                        field: Some(field),
+                        old: value(old, field),
+                        new: value(new, field),
+                    });
+                }
+            }
+        }
+
+        let mut occurrences = Vec::new();
+        for new in other.segments.iter().filter(|s| !s.source.is_empty()) {
+            let name = new.fields[0].source;
+            let occurrence = next_occurrence(&mut occurrences, name);
+            if nth(self, name, occurrence).is_none() {
+                diffs.push(FieldDiff {
+                    segment: name.to_string(),
+                    occurrence,
+                    field: None,
+                    old: None,
+                    new: Some(new.source),
+                });
+            }
+        }
+        diffs
+    }
+
+    /// Returns a quick overview of the message's shape as `(segment name, segment source)` pairs, in document order.
+    /// Handy for logging, as it's far more readable than the `Debug` output.  Only the outer `Vec` is allocated.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::message::Message;
+    /// # use std::convert::TryFrom;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let m = Message::try_from("MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rPID|||555-44-4444")?;
+    /// let outline = m.outline();
+    /// assert_eq!(outline[1], ("PID", "PID|||555-44-4444"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn outline(&self) -> Vec<(&'a str, &'a str)> {
+        self.segments
+            .iter()
+            .map(|s| (s.fields[0].source, s.source))
+            .collect()
+    }
+
     /// Returns the source string slice used to create this Message initially.  This method does not allocate.
     /// ## Example:
     /// ```
@@ -69,28 +861,577 @@ impl<'a> Message<'a> {
         self.source
     }
 
+    /// Copies the source into an `OwnedMessage`, which doesn't borrow the input buffer so can outlive it (eg to hand the message off to
+    /// another thread once it's been read from a socket).
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::message::Message;
+    /// # use rusthl7::owned_message::OwnedMessage;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let owned: OwnedMessage = {
+    ///     let buffer = String::from("MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4");
+    ///     Message::parse(&buffer)?.into_owned()
+    /// };
+    /// assert_eq!(owned.as_message().query("MSH-10"), "CNTRL-3456");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn into_owned(self) -> OwnedMessage {
+        OwnedMessage::from(self)
+    }
+
     /// Gets the delimiter information for this Message
     pub fn get_separators(&self) -> Separators {
         self.separators
     }
 
-    /// Access Segment, Field, or sub-field string references by string index
+    /// Re-assembles the message using different delimiters (including the segment separator), rewriting MSH-1 and MSH-2 to declare
+    /// them.  Values are re-escaped as needed, see `Segment::rebuild_with()`.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::message::Message;
+    /// # use rusthl7::separators::Separators;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let m = Message::parse("MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rPID|||555-44-4444")?;
+    /// let hashes = Separators { field: '#', ..m.get_separators() };
+    /// assert_eq!(
+    ///     m.rebuild_with(&hashes),
+    ///     "MSH#^~\\&#GHH LAB#ELAB-3#GHH OE#BLDG4#200202150930##ORU^R01#CNTRL-3456#P#2.4\rPID###555-44-4444"
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn rebuild_with(&self, delims: &Separators) -> String {
+        self.segments
+            .iter()
+            .map(|s| s.rebuild_with(delims))
+            .collect::<Vec<String>>()
+            .join(&delims.segment.to_string())
+    }
+
+    /// Returns the message type from MSH-9 (eg `ORU^R01`), split into its message code, trigger event and message structure, or `None`
+    /// if MSH-9 is empty.  This reads the MSH directly, so works even where the MSH is missing other fields `MshSegment` requires.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::message::Message;
+    /// # use std::convert::TryFrom;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let m = Message::try_from("MSH|^~\\&|CATH|StJohn|AcmeHIS|StJohn|20061019172719||ACK^O01|MSGID12349876|P|2.3\rMSA|AA|MSGID12349876")?;
+    /// let message_type = m.message_type().unwrap();
+    /// assert_eq!(message_type.code, "ACK");
+    /// assert_eq!(message_type.trigger_event, Some("O01"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn message_type(&self) -> Option<MessageType<'a>> {
+        self.segments[0]
+            .field(9)
+            .filter(|f| !f.source.is_empty())
+            .map(MessageType::from_field)
+    }
+
+    /// Returns true if this message is an acknowledgment, that is MSH-9 has a message code of `ACK`, or the message contains an `MSA` segment.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::message::Message;
+    /// # use std::convert::TryFrom;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let m = Message::try_from("MSH|^~\\&|CATH|StJohn|AcmeHIS|StJohn|20061019172719||ACK^O01|MSGID12349876|P|2.3\rMSA|AA|MSGID12349876")?;
+    /// assert!(m.is_acknowledgment());
+    /// assert_eq!(m.acknowledged_control_id(), Some("MSGID12349876"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn is_acknowledgment(&self) -> bool {
+        let msh_9_code = self.segments[0].field(9).map(|f| f[(0, 0)]);
+
+        msh_9_code == Some("ACK") || self.segments.iter().any(|s| s.fields[0].source == "MSA")
+    }
+
+    /// Returns the control id (MSA-2) of the message this message is acknowledging, if there is one.
+    pub fn acknowledged_control_id(&self) -> Option<&'a str> {
+        self.segments
+            .iter()
+            .find(|s| s.fields[0].source == "MSA")
+            .and_then(|msa| msa.fields.get(2))
+            .map(|f| f.value())
+            .filter(|v| !v.is_empty())
+    }
+
+    /// Builds an ACK response to this message with the given acknowledgment code, using a freshly generated control id and the current
+    /// time (which requires the `chrono` feature, otherwise MSH-7 is left blank).  See `build_ack_with()` to supply these explicitly.
+    pub fn build_ack(&self, code: AckCode) -> String {
+        let timestamp = timestamp::timestamp_or_now(None).unwrap_or_default();
+        self.build_ack_with(code, &generate_control_id(), &timestamp)
+    }
+
+    /// Builds an ACK response to this message with the given acknowledgment code, control id (MSH-10) and timestamp (MSH-7).
+    ///
+    /// The response uses the same separators, processing id (MSH-11) and version (MSH-12) as this message, with the sending and receiving
+    /// applications/facilities swapped.  Any fields missing from this message are left blank, and values are copied as-is so escape sequences
+    /// (eg in the original control id) are preserved.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::message::{AckCode, Message};
+    /// # use std::convert::TryFrom;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let m = Message::try_from("MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rPID|||555-44-4444")?;
+    /// assert_eq!(
+    ///     m.build_ack_with(AckCode::AA, "ACK-1", "200202150931"),
+    ///     "MSH|^~\\&|GHH OE|BLDG4|GHH LAB|ELAB-3|200202150931||ACK^R01|ACK-1|P|2.4\rMSA|AA|CNTRL-3456"
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn build_ack_with(&self, code: AckCode, control_id: &str, timestamp: &str) -> String {
+        let msh = &self.segments[0];
+        let msh_field = |n: usize| msh.field(n).map_or("", |f| f.source);
+
+        let delims = self.separators;
+        let message_type = match msh.field(9).map(|f| f[(0, 1)]) {
+            Some(trigger) if !trigger.is_empty() => {
+                format!("ACK{}{}", delims.component, trigger)
+            }
+            _ => "ACK".to_string(),
+        };
+
+        let msh_fields = [
+            "MSH",
+            &delims.to_string(),
+            msh_field(5),
+            msh_field(6),
+            msh_field(3),
+            msh_field(4),
+            timestamp,
+            "",
+            &message_type,
+            control_id,
+            msh_field(11),
+            msh_field(12),
+        ];
+        let msa_fields = ["MSA", &code.to_string(), msh_field(10)];
+
+        let field = delims.field.to_string();
+        format!(
+            "{}{}{}",
+            msh_fields.join(&field),
+            delims.segment,
+            msa_fields.join(&field)
+        )
+    }
+
+    /// Checks every field contains only chars representable in the character set declared in MSH-18, to catch encoding mismatches
+    /// before they turn into mojibake downstream.  Each field with a problem is reported once, against the first offending char.
+    ///
+    /// Only the first repeat of MSH-18 is considered, as any further repeats are alternate character sets that require escape sequences to be used.
+    /// - `ASCII` (which the spec says is the default when MSH-18 is empty) only allows 7-bit chars
+    /// - `8859/1` (Latin-1) allows chars up to `U+00FF`
+    /// - Unicode charsets (and any others we don't know how to check) are a no-op
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::message::Message;
+    /// # use std::convert::TryFrom;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let m = Message::try_from("MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ADT^A01|CNTRL-3456|P|2.4||||||ASCII\rPID|||555-44-4444||MÜLLER^EVE")?;
+    /// let violations = m.validate_charset();
+    /// assert_eq!(violations.len(), 1);
+    /// assert_eq!(violations[0].character, 'Ü');
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn validate_charset(&self) -> Vec<CharsetViolation<'a>> {
+        let charset = self.segments[0].field(18).map_or("", |f| f[0]);
+
+        let max_char = match charset {
+            "" | "ASCII" => '\u{7F}',
+            "8859/1" => '\u{FF}',
+            _ => return Vec::new(), // unicode, or something we don't know how to check
+        };
+
+        let mut violations = Vec::new();
+        for (segment, seg) in self.segments.iter().enumerate() {
+            for (field, f) in seg.fields.iter().enumerate() {
+                if let Some(character) = f.source.chars().find(|c| *c > max_char) {
+                    violations.push(CharsetViolation {
+                        segment,
+                        field,
+                        value: f.source,
+                        character,
+                    });
+                }
+            }
+        }
+        violations
+    }
+
+    /// Checks the separators declared in MSH-1 and MSH-2 are consistent with how the MSH itself is laid out, to catch messages that
+    /// have been corrupted or hand edited (eg a find and replace on `|`) before any values are read from them.
+    ///
+    /// The MSH must:
+    /// - Declare distinct separators, none of which are alphanumeric or whitespace
+    /// - Have nothing in MSH-2 beyond the encoding chars (allowing for the truncation char added in v2.7)
+    /// - Split on the declared field separator into at least the 12 fields the spec requires
+    /// - Use the declared component separator in MSH-9 (Message Type), which is made up of alphanumeric codes
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::message::Message;
+    /// # use std::convert::TryFrom;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let m = Message::try_from("MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4")?;
+    /// assert!(m.check_encoding_consistency().is_ok());
+    /// let m = Message::try_from("MSH|@~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4")?;
+    /// assert!(m.check_encoding_consistency().is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn check_encoding_consistency(&self) -> Result<(), Hl7ParseError> {
+        let inconsistent = |reason: String| Err(Hl7ParseError::Msh1Msh2(reason));
+        let msh = &self.segments[0];
+        let delims = &self.separators;
+
+        let declared = [
+            delims.field,
+            delims.component,
+            delims.repeat,
+            delims.escape_char,
+            delims.subcomponent,
+        ];
+        if let Some(c) = declared
+            .iter()
+            .find(|c| c.is_alphanumeric() || c.is_whitespace())
+        {
+            return inconsistent(format!("{:?} can't be used as a separator", c));
+        }
+        if let Some(c) = delims.duplicate() {
+            return inconsistent(format!("{:?} is declared as more than one separator", c));
+        }
+
+        let encoding_chars = msh.field(2).map_or("", |f| f.source);
+        if encoding_chars.chars().count() > 5 {
+            return inconsistent(format!(
+                "MSH-2 '{}' contains more than the encoding chars",
+                encoding_chars
+            ));
+        }
+        if msh.field(12).is_none() {
+            return inconsistent(format!(
+                "MSH splits into {} fields on {:?}, at least 12 are required",
+                msh.fields.len(),
+                delims.field
+            ));
+        }
+
+        let message_type = msh.field(9).map_or("", |f| f.source);
+        if message_type
+            .split(delims.component)
+            .any(|code| !code.chars().all(|c| c.is_alphanumeric() || c == '_'))
+        {
+            return inconsistent(format!(
+                "MSH-9 '{}' doesn't use the declared component separator {:?}",
+                message_type, delims.component
+            ));
+        }
+        Ok(())
+    }
+
+    /// Guesses whether this is a complete message, rather than one truncated part way through (eg when reading off a socket without MLLP framing).
+    /// This is necessarily heuristic, a `true` result means the message is plausible, not that it's guaranteed to be complete.
+    ///
+    /// The message is considered complete if:
+    /// - The MSH is complete at least up to MSH-12 (Version ID), the last field required by the spec
+    /// - The last segment has a well formed (3 char, uppercase alphanumeric) name, so we haven't stopped part way through a segment id
+    /// - For the message codes (MSH-9.1) listed in `EXPECTED_SEGMENTS`, each of the expected segments is present
+    ///
+    /// Use `looks_complete_with()` to supply your own expected segments.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::message::Message;
+    /// # use std::convert::TryFrom;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let m = Message::try_from("MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rPID|||555-44-4444\rOBR|1\rOBX|1|NM|2951-2^SODIUM||140")?;
+    /// assert!(m.looks_complete());
+    /// let m = Message::try_from("MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rPID|||555-44-4444")?;
+    /// assert!(!m.looks_complete());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn looks_complete(&self) -> bool {
+        self.looks_complete_with(EXPECTED_SEGMENTS)
+    }
+
+    /// As per `looks_complete()`, but using the given `(message code, required segments)` list in place of `EXPECTED_SEGMENTS`.
+    /// Message codes not in the list only get the structural checks.
+    pub fn looks_complete_with(&self, expected_segments: &[(&str, &[&str])]) -> bool {
+        let msh = &self.segments[0];
+        if msh.fields[0].source != "MSH" || msh.field(12).map_or("", |f| f.source).is_empty() {
+            return false;
+        }
+
+        let last = self
+            .segments
+            .iter()
+            .rev()
+            .map(|s| s.fields[0].source.trim_matches('\n'))
+            .find(|name| !name.is_empty())
+            .unwrap_or("");
+        let well_formed = last.len() == 3
+            && last
+                .chars()
+                .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit());
+        if !well_formed {
+            return false;
+        }
+
+        let message_code = msh.field(9).map_or("", |f| f[(0, 0)]);
+        match expected_segments
+            .iter()
+            .find(|(code, _)| *code == message_code)
+        {
+            Some((_, required)) => required.iter().all(|name| {
+                self.segments
+                    .iter()
+                    .any(|s| s.fields[0].source.trim_matches('\n') == *name)
+            }),
+            None => true,
+        }
+    }
+
+    /// Nests the message's segments into the groups defined by its abstract message syntax, as identified by MSH-9 (the message structure
+    /// in MSH-9.3, or the message code and trigger event).  Returns the top level groups, segments outside of any group (eg MSH, or EVN and
+    /// PID in an ADT) aren't included.
+    ///
+    /// Currently only `ORU^R01` and `ADT^A01` (along with the ADT events sharing its structure) are supported, other messages have no groups.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::message::Message;
+    /// # use std::convert::TryFrom;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let m = Message::try_from("MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rPID|||555-44-4444\rOBR|1\rOBX|1|NM|GLU||182\rOBX|2|NM|NA||140")?;
+    /// let groups = m.groups();
+    /// let order = groups[0].groups_by_name("ORDER_OBSERVATION").next().unwrap();
+    /// assert_eq!(order.groups_by_name("OBSERVATION").count(), 2);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn groups(&self) -> Vec<SegmentGroup<'_, 'a>> {
+        groups::group_segments(self)
+    }
+
+    /// Validates the number of times each segment in the profile occurs in this message, returning every violation found.  Use
+    /// `MessageProfile::for_message()` for the built in profile for the message's structure, or build your own.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::message::Message;
+    /// # use rusthl7::profile::MessageProfile;
+    /// # use std::convert::TryFrom;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let m = Message::try_from("MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rPID|||555-44-4444\rOBR|1")?;
+    /// let profile = MessageProfile::for_message(&m).unwrap();
+    /// assert!(m.validate(&profile).is_ok());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn validate(&self, profile: &MessageProfile) -> Result<(), Vec<ValidationError>> {
+        let names = self
+            .segments
+            .iter()
+            .map(|s| s.fields[0].source)
+            .filter(|name| !name.is_empty());
+
+        let errors = profile.check(names);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Serializes the message to the HL7 v2.xml encoding, eg `<ORU_R01><MSH><MSH.1>|</MSH.1>...</MSH>...</ORU_R01>`.
+    ///
+    /// - The root element is the message structure (see `MessageType::structure_or_inferred()`), or `MSG` if there's no MSH-9
+    /// - Each segment is an element containing its (non-empty) fields, with repeating fields emitted as repeated elements
+    /// - Components are named for their data type where it's known from the built in schema table (eg `<XPN.1>` in PID-5), otherwise for their
+    ///   position (eg `<ZPD.2.1>`).  Subcomponents are nested inside their component, with the component's name as a prefix (eg `<CX.4.1>`)
+    /// - HL7 escape sequences are decoded and XML special chars escaped in values
+    ///
+    /// Segment groups aren't emitted, and there's not (yet) any support for reading v2.xml back in.
+    pub fn to_v2xml(&self) -> String {
+        v2xml::to_v2xml(self)
+    }
+
+    /// Returns a canonical (minimal) form of this message, intended for hashing when de-duplicating or checking idempotency.
+    /// Two messages that produce the same canonical form are considered logically equivalent.
+    ///
+    /// The following canonicalization steps are applied:
+    /// - Delimiters are normalised to the default set (`|^~\&`), with MSH-1/MSH-2 rewritten to suit.  Where the message used custom separators
+    ///   the escape char is swapped for `\`, and any literal default-delimiter chars in the data are escaped (`\F\`, `\S\`, `\R\`, `\T\`, `\E\`)
+    /// - Trailing empty subcomponents are removed from each component, trailing empty components from each repeat,
+    ///   trailing empty repeats from each field, and trailing empty fields from each segment
+    /// - Stray `\n` chars (from `\r\n` line endings) are removed from the start and end of each segment, and empty segments are dropped
+    /// - Segments are joined with a single `\r`, with no trailing segment separator
+    ///
+    /// Escape sequences are otherwise left as-is, so `\T\` and a literal `&` in a message using other separators are _not_ considered equal.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::message::Message;
+    /// # use std::convert::TryFrom;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let m1 = Message::try_from("MSH|^~\\&|GHH LAB|ELAB-3||||ORU^R01^|CNTRL-3456||\rOBR|1||\r")?;
+    /// let m2 = Message::try_from("MSH#@~\\&#GHH LAB#ELAB-3####ORU@R01#CNTRL-3456\rOBR#1")?;
+    /// assert_eq!(m1.canonical(), m2.canonical());
+    /// assert_eq!(m1.canonical(), "MSH|^~\\&|GHH LAB|ELAB-3||||ORU^R01|CNTRL-3456\rOBR|1");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn canonical(&self) -> String {
+        self.canonical_excluding(&[])
+    }
+
+    /// Computes a fingerprint of the message content, for detecting duplicates (eg identical clinical content that has been re-sent).
+    ///
+    /// The hash is taken over the canonical form (see `canonical()`) with the `ignore`d fields blanked out, so volatile values that change on
+    /// every send don't affect the result.  Fields are identified by segment name and their HL7 field number (so `("MSH", 7)` is MSH-7),
+    /// and are blanked in every segment with that name. `VOLATILE_FIELDS` provides a sensible default.
+    ///
+    /// The hash algorithm (64-bit FNV-1a) is fixed, so fingerprints are stable across processes and can be persisted.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::message::{Message, VOLATILE_FIELDS};
+    /// # use std::convert::TryFrom;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let m1 = Message::try_from("MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rOBX|1|NM|2951-2^SODIUM||140")?;
+    /// let m2 = Message::try_from("MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202151015||ORU^R01|CNTRL-3457|P|2.4\rOBX|1|NM|2951-2^SODIUM||140")?;
+    /// assert_eq!(m1.content_fingerprint(VOLATILE_FIELDS), m2.content_fingerprint(VOLATILE_FIELDS));
+    /// assert_ne!(m1.content_fingerprint(&[]), m2.content_fingerprint(&[]));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn content_fingerprint(&self, ignore: &[(&str, usize)]) -> u64 {
+        fnv1a_64(&self.canonical_excluding(ignore))
+    }
+
+    /// Builds the canonical form, blanking any of the `ignore`d (segment name, HL7 field number) fields.
+    fn canonical_excluding(&self, ignore: &[(&str, usize)]) -> String {
+        let default = Separators::default();
+
+        let segments: Vec<String> = self
+            .segments
+            .iter()
+            .map(|s| s.source.trim_matches('\n'))
+            .filter(|s| !s.is_empty())
+            .map(|line| {
+                // re-split so any `\n` we trimmed above isn't included in the first/last field
+                let seg = Segment::split(line, &self.separators, &ParseOptions::default());
+                let name = seg.fields[0].source;
+                let is_msh = name == "MSH";
+
+                let fields: Vec<String> = seg
+                    .fields
+                    .iter()
+                    .enumerate()
+                    .map(|(i, f)| {
+                        if is_msh && i == 1 {
+                            // MSH-2 is the encoding chars, which are being normalised
+                            return default.to_string();
+                        }
+
+                        let field_number = seg.field_number(i);
+                        if i > 0 && ignore.contains(&(name, field_number)) {
+                            return String::new();
+                        }
+
+                        let repeats: Vec<String> = f
+                            .subcomponents()
+                            .iter()
+                            .map(|components| {
+                                let components: Vec<String> = components
+                                    .iter()
+                                    .map(|subs| {
+                                        let subs: Vec<String> = subs
+                                            .iter()
+                                            .map(|v| canonical_value(v, &self.separators))
+                                            .collect();
+                                        join_trimmed(subs, default.subcomponent)
+                                    })
+                                    .collect();
+                                join_trimmed(components, default.component)
+                            })
+                            .collect();
+                        join_trimmed(repeats, default.repeat)
+                    })
+                    .collect();
+                join_trimmed(fields, default.field)
+            })
+            .collect();
+
+        segments.join(&default.segment.to_string())
+    }
+
+    /// Access Segment, Field, or sub-field string references by string index.
+    ///
+    /// The query grammar is `SEG[.part]*`, where `SEG` is the segment name (the first segment with that name is used), and each `part` is either:
+    /// - a level prefix (`F`ield, `R`epeat, `C`omponent or `S`ubcomponent, case insensitive) followed by a 1-based index, eg `F5`, `R2`, `C1`, or
+    /// - a bare 1-based index, which fills the next unspecified level out of field, component, then subcomponent.  Repeats must always be prefixed.
+    ///
+    /// Any level skipped above the deepest one specified defaults to the first item, so `PID.5.2`, `PID.F5.C2` and `PID.F5.R1.C2` are equivalent,
+    /// and a bare segment name (`PID`) returns the whole segment.  Unknown segments and out-of-range or malformed indexes return `""`.
+    ///
+    /// Conventional HL7 paths are also accepted, in the form `SEG[n]-field.component.subcomponent`, eg `MSH-9`, `PID-5.1` or `OBX[2]-5`.
+    /// These are all 1-based, with the optional `[n]` selecting the nth segment with that name, and always use the first field repeat.
+    /// Unlike the grammar above, fields are numbered as per the spec so `MSH-9` is the message type (and `MSH-1` the field separator).
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::message::Message;
+    /// # use std::convert::TryFrom;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let m = Message::try_from("MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rPID|||555-44-4444||EVERYWOMAN^EVE^E^^^^L")?;
+    /// assert_eq!(m.query("PID"), "PID|||555-44-4444||EVERYWOMAN^EVE^E^^^^L");
+    /// assert_eq!(m.query("PID.5"), "EVERYWOMAN^EVE^E^^^^L");
+    /// assert_eq!(m.query("PID.5.2"), "EVE");
+    /// assert_eq!(m.query("PID.F5.R1.C2"), "EVE");
+    /// assert_eq!(m.query("ZZZ.F1"), "");
+    /// assert_eq!(m.query("MSH-9.2"), "R01");
+    /// assert_eq!(m.query("PID-5.1"), "EVERYWOMAN");
+    /// # Ok(())
+    /// # }
+    /// ```
     pub fn query<'b, S>(&self, idx: S) -> &'a str
     where
         S: Into<&'b str>,
     {
         let idx = idx.into();
+        if idx.contains(['-', '[']) {
+            return self.query_hl7_path(idx).unwrap_or("");
+        }
 
         // Parse index elements
         let indices = Self::parse_query_string(idx);
         let seg_name = indices[0];
-        // Find our first segment without offending the borow checker
-        let seg_index = self
+        if seg_name.is_empty() {
+            return "";
+        }
+
+        let seg = match self
             .segments
             .iter()
-            .position(|r| &r.as_str()[..seg_name.len()] == seg_name)
-            .expect("Segment not found");
-        let seg = &self.segments[seg_index];
+            .find(|s| s.fields[0].source == seg_name)
+        {
+            Some(seg) => seg,
+            None => return "",
+        };
         if indices.len() < 2 {
             seg.source
         } else {
@@ -99,176 +1440,913 @@ impl<'a> Message<'a> {
         }
     }
 
-    /// Parse query/index string to fill-in missing values.
-    /// Required when conumer requests "PID.F3.C1" to pass integers down
-    /// to the usize indexers at the appropriate positions
-    pub fn parse_query_string(query: &str) -> Vec<&str> {
-        fn query_idx_pos(indices: &[&str], idx: &str) -> Option<usize> {
-            indices[1..]
-                .iter()
-                .position(|r| r[0..1].to_uppercase() == idx)
-        }
-        let indices: Vec<&str> = query.split('.').collect();
-        // Leave segment name untouched - complex match
-        let mut res = vec![indices[0]];
-        // Get segment positions, if any
-        let sub_pos = query_idx_pos(&indices, "S");
-        let com_pos = query_idx_pos(&indices, "C");
-        let rep_pos = query_idx_pos(&indices, "R");
-        let fld_pos = query_idx_pos(&indices, "F");
-        // Push segment values to result, returning early if possible
-        match fld_pos {
-            Some(f) => res.push(indices[f + 1]),
-            None => {
-                // If empty but we have subsections, default to F1
-                if rep_pos.is_some() || com_pos.is_some() || sub_pos.is_some() {
-                    res.push("F1")
-                } else {
-                    return res;
-                }
-            }
-        };
-        match rep_pos {
-            Some(r) => res.push(indices[r + 1]),
-            None => {
-                // If empty but we have subsections, default to R1
-                if com_pos.is_some() || sub_pos.is_some() {
-                    res.push("R1")
-                } else {
-                    return res;
-                }
-            }
-        };
-        match com_pos {
-            Some(c) => res.push(indices[c + 1]),
-            None => {
-                // If empty but we have a subcomponent, default to C1
-                if sub_pos.is_some() {
-                    res.push("C1")
-                } else {
-                    return res;
-                }
+    /// Resolves a conventional HL7 path (`SEG[n]-field.component.subcomponent`) for `query()`, returning `None` if anything is malformed
+    /// or out of range.
+    fn query_hl7_path(&self, path: &str) -> Option<&'a str> {
+        let (segment, indexes) = self.hl7_path_segment(path)?;
+        let name = segment.fields[0].source;
+        if indexes.is_empty() {
+            return Some(segment.source);
+        }
+
+        let field = match (name, indexes[0]) {
+            // the field separator, which `field()` doesn't return as it isn't delimited like the other fields
+            ("MSH", 1) if indexes.len() == 1 => return segment.source.get(3..4),
+            (_, n) => segment.field(n)?,
+        };
+
+        match indexes[1..] {
+            [] => Some(field.source),
+            [component] => field.components()[0].get(component - 1).copied(),
+            [component, subcomponent] => field.subcomponents()[0]
+                .get(component - 1)?
+                .get(subcomponent - 1)
+                .copied(),
+            _ => None,
+        }
+    }
+
+    /// Finds the segment named in a conventional HL7 path (`SEG[n]-field.component.subcomponent`), returning it along with the (1 based)
+    /// numbers following the `-`, which are empty if the path names only the segment.
+    fn hl7_path_segment(&self, path: &str) -> Option<(&Segment<'a>, Vec<usize>)> {
+        let (head, tail) = match path.split_once('-') {
+            Some((head, tail)) => (head, Some(tail)),
+            None => (path, None),
+        };
+
+        let (name, occurrence) = match head.split_once('[') {
+            Some((name, occurrence)) => {
+                (name, occurrence.strip_suffix(']')?.parse::<usize>().ok()?)
+            }
+            None => (head, 1),
+        };
+        let segment = self
+            .segments
+            .iter()
+            .filter(|s| s.fields[0].source == name)
+            .nth(occurrence.checked_sub(1)?)?;
+
+        let indexes = match tail {
+            Some(tail) => tail
+                .split('.')
+                .map(|i| i.parse::<usize>().ok().filter(|i| *i > 0))
+                .collect::<Option<Vec<usize>>>()?,
+            None => Vec::new(),
+        };
+        Some((segment, indexes))
+    }
+
+    /// As per `query()`, but also returns where in the message the value came from, for tracing a (mis)mapped value back to its source.
+    /// Returns `None` where `query()` would return `""` because the segment or field wasn't found.
+    ///
+    /// This does a little more work than `query()`, so use that unless you need the provenance.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::message::Message;
+    /// # use std::convert::TryFrom;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let source = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rPID|||555-44-4444||EVERYWOMAN^EVE";
+    /// let m = Message::try_from(source)?;
+    /// let found = m.query_with_provenance("PID.F5.C1").unwrap();
+    /// assert_eq!(found.value, "EVERYWOMAN");
+    /// assert_eq!((found.segment_index, found.field_index), (1, Some(5)));
+    /// assert_eq!(&source[found.byte_span], "EVERYWOMAN");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn query_with_provenance(&self, path: &str) -> Option<ValueWithProvenance<'a>> {
+        let indices = Self::parse_query_string(path);
+        let seg_name = indices[0];
+        let segment_index = self
+            .segments
+            .iter()
+            .position(|s| !seg_name.is_empty() && s.fields[0].source == seg_name)?;
+        let seg = &self.segments[segment_index];
+
+        let (field_index, located, value) = if indices.len() < 2 {
+            (None, seg.source, seg.source)
+        } else {
+            let (field_index, value) = seg.find(&indices[1..].join("."))?;
+            (Some(field_index), seg.fields[field_index].source, value)
+        };
+
+        // the value is always a slice of the segment/field it was located in, which is in turn a slice of `self.source` (unless
+        // the caller swapped in a segment from elsewhere, in which case there's no span to report)
+        let start = offset_within(self.source, located)? + offset_within(located, value)?;
+        let byte_span = start..start + value.len();
+
+        Some(ValueWithProvenance {
+            value,
+            segment_index,
+            field_index,
+            byte_span,
+        })
+    }
+
+    /// Returns every value in the message matching the given query, for pulling out eg OBX-5 from every OBX segment.  See `Message::query()` for the grammar.
+    ///
+    /// The query is expanded as follows:
+    /// - Every segment with the given name is included, in message order
+    /// - If the query doesn't explicitly specify a repeat (with an `R` part), every repeat of the field is included, otherwise only that repeat is
+    /// - Components and subcomponents are never expanded, they default to the first item just like `query()`
+    ///
+    /// A bare segment name returns the source of each matching segment.  Empty values are included so results line up with their source segments/repeats,
+    /// but segments that don't have the requested field at all are skipped.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::message::Message;
+    /// # use std::convert::TryFrom;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let m = Message::try_from("MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rOBX|1|NM|GLU||182~190\rOBX|2|NM|NA||140")?;
+    /// assert_eq!(m.query_all("OBX.5"), vec!["182", "190", "140"]);
+    /// assert_eq!(m.query_all("OBX.F5.R1"), vec!["182", "140"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn query_all(&self, path: &str) -> Vec<&'a str> {
+        let indices = Self::parse_query_string(path);
+        let seg_name = indices[0];
+        let explicit_repeat = path
+            .split('.')
+            .skip(1)
+            .any(|p| p.starts_with('R') || p.starts_with('r'));
+
+        let mut values = Vec::new();
+        for seg in self
+            .segments
+            .iter()
+            .filter(|s| s.fields[0].source == seg_name)
+        {
+            if indices.len() < 2 {
+                values.push(seg.source);
+                continue;
+            }
+
+            let field = indices[1]
+                .chars()
+                .filter(|c| c.is_ascii_digit())
+                .collect::<String>()
+                .parse::<usize>()
+                .ok()
+                .and_then(|idx| seg.fields.get(idx));
+            if let Some(field) = field {
+                if explicit_repeat {
+                    values.push(field.query(&*indices[2..].join(".")));
+                    continue;
+                }
+
+                for repeat in 1..=field.repeats().len() {
+                    let mut query = vec![format!("R{}", repeat)];
+                    query.extend(indices.iter().skip(3).map(|i| i.to_string())); // anything below the repeat level
+                    values.push(field.query(&*query.join(".")));
+                }
+            }
+        }
+        values
+    }
+
+    /// Parse query/index string to fill-in missing values.
+    /// Required when conumer requests "PID.F3.C1" to pass integers down
+    /// to the usize indexers at the appropriate positions.
+    ///
+    /// The result is the segment name followed by the field, repeat, component and subcomponent parts in that order, down to the deepest level
+    /// requested.  See `Message::query()` for the grammar.
+    pub fn parse_query_string(query: &str) -> Vec<&str> {
+        const FIELD: usize = 0;
+        const REPEAT: usize = 1;
+        const COMPONENT: usize = 2;
+        const SUBCOMPONENT: usize = 3;
+        const DEFAULTS: [&str; 4] = ["F1", "R1", "C1", "S1"];
+
+        let mut parts = query.split('.');
+        // Leave segment name untouched - complex match
+        let mut res = vec![parts.next().unwrap_or_default()];
+
+        let mut levels: [Option<&str>; 4] = [None; 4];
+        for part in parts {
+            let level = match part.chars().next().map(|c| c.to_ascii_uppercase()) {
+                Some('F') => FIELD,
+                Some('R') => REPEAT,
+                Some('C') => COMPONENT,
+                Some('S') => SUBCOMPONENT,
+                _ => {
+                    // bare index, fills the next free level (repeats must be explicit)
+                    match [FIELD, COMPONENT, SUBCOMPONENT]
+                        .iter()
+                        .find(|&&l| levels[l].is_none())
+                    {
+                        Some(&l) => l,
+                        None => continue, // nowhere left to put it
+                    }
+                }
+            };
+            levels[level] = Some(part);
+        }
+
+        // Push values to result down to the deepest requested level, defaulting any we skipped to the first item
+        let depth = levels
+            .iter()
+            .rposition(|l| l.is_some())
+            .map_or(0, |d| d + 1);
+        for (level, default) in levels.iter().zip(DEFAULTS.iter()).take(depth) {
+            res.push(level.unwrap_or(default));
+        }
+        res
+    }
+}
+
+/// Drops any trailing empty parts, then joins what's left with the separator
+fn join_trimmed(mut parts: Vec<String>, separator: char) -> String {
+    while parts.last().is_some_and(|p| p.is_empty()) {
+        parts.pop();
+    }
+    parts.join(&separator.to_string())
+}
+
+/// Converts a single (sub)component value from the message's separators to the default set, as used by `Message::canonical()`
+fn canonical_value(value: &str, delims: &Separators) -> String {
+    let default = Separators::default();
+    if *delims == default {
+        return value.to_string(); // nothing to translate
+    }
+
+    let mut output = String::with_capacity(value.len());
+    for c in value.chars() {
+        let sequence = match c {
+            c if c == delims.escape_char => {
+                output.push(default.escape_char); // still the start/end of an escape sequence, just in the default char
+                continue;
+            }
+            c if c == default.field => 'F',
+            c if c == default.component => 'S',
+            c if c == default.repeat => 'R',
+            c if c == default.subcomponent => 'T',
+            c if c == default.escape_char => 'E',
+            c => {
+                output.push(c);
+                continue;
+            }
+        };
+        // a literal char that would be a delimiter under the default separators, it needs escaping
+        output.push(default.escape_char);
+        output.push(sequence);
+        output.push(default.escape_char);
+    }
+    output
+}
+
+impl<'a> TryFrom<&'a str> for Message<'a> {
+    type Error = Hl7ParseError;
+
+    /// Takes the source HL7 string and parses it into this message.  Segments
+    /// and other data are slices (`&str`) into the source HL7
+    ///
+    /// Segments may be terminated by `\r` (as the spec requires), `\n` or `\r\n`, as files saved on other systems often have their line
+    /// endings converted.  Use `Message::parse_with_separators()` to split on exactly one segment separator instead.  A separator
+    /// immediately preceded by the escape char (eg `\\<CR>`) is part of the value rather than the end of the segment.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::message::Message;
+    /// # use std::convert::TryInto;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let source = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rPID|||555-44-4444";
+    /// let msg: Message = source.try_into()?;
+    /// assert_eq!(msg.query("PID-3"), "555-44-4444");
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn try_from(source: &'a str) -> Result<Self, Self::Error> {
+        let delimiters = str::parse::<Separators>(source)?;
+        Ok(Message::from_layout(
+            source,
+            delimiters,
+            Layout::default(),
+            &[],
+        ))
+    }
+}
+
+impl<'a> Display for Message<'a> {
+    /// Writes the message exactly as it was parsed, so `Message::parse(s)?.to_string() == s` for any input that parses (including a trailing
+    /// segment separator, and non-default encoding characters).
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl<'a> Clone for Message<'a> {
+    /// Creates a new cloned Message object referencing the same source slice as the original.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::message::Message;
+    /// # use std::convert::TryFrom;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let m = Message::try_from("MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4")?;
+    /// let cloned = m.clone(); // this object is looking at the same string slice as m
+    /// assert_eq!(cloned, m);
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn clone(&self) -> Self {
+        Message {
+            source: self.source,
+            segments: self.segments.clone(),
+            separators: self.separators,
+            layout: self.layout,
+        }
+    }
+}
+
+impl<'a> Index<usize> for Message<'a> {
+    type Output = &'a str;
+
+    /// Access Segment string reference by numeric index
+    fn index(&self, idx: usize) -> &Self::Output {
+        if idx > self.segments.len() {
+            return &"";
+        }
+        &self.segments[idx].source
+    }
+}
+#[cfg(feature = "string_index")]
+impl<'a> Index<String> for Message<'a> {
+    type Output = &'a str;
+
+    /// Access Segment, Field, or sub-field string references by string index, either as `SEG.F1.R1.C1` or a conventional HL7 path
+    /// such as `PID-5.1` or `OBX[2]-5` (as per `query()`).  Returns `""` if the segment or value isn't present.
+    #[cfg(feature = "string_index")]
+    fn index(&self, idx: String) -> &Self::Output {
+        if idx.contains(['-', '[']) {
+            let (seg, indexes) = match self.hl7_path_segment(&idx) {
+                Some(found) => found,
+                None => return &"",
+            };
+            // translate to the segment's own string index, which numbers MSH fields the same way
+            return match indexes[..] {
+                [] => &seg.source,
+                [field] => &seg[format!("F{}", field)],
+                [field, component] => &seg[format!("F{}.R1.C{}", field, component)],
+                [field, component, subcomponent] => {
+                    &seg[format!("F{}.R1.C{}.S{}", field, component, subcomponent)]
+                }
+                _ => &"",
+            };
+        }
+
+        // Parse index elements
+        let indices = Self::parse_query_string(&idx);
+        let seg_name = indices[0];
+        let seg = match self
+            .segments
+            .iter()
+            .find(|s| s.fields[0].source == seg_name)
+        {
+            Some(seg) => seg,
+            None => return &"",
+        };
+        if indices.len() < 2 {
+            &seg.source
+        } else {
+            &seg[indices[1..].join(".")]
+        }
+    }
+}
+
+#[cfg(feature = "string_index")]
+impl<'a> Index<&str> for Message<'a> {
+    type Output = &'a str;
+
+    #[cfg(feature = "string_index")]
+    fn index(&self, idx: &str) -> &Self::Output {
+        &self[String::from(idx)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ensure_segments_are_returned() -> Result<(), Hl7ParseError> {
+        let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rOBR|segment";
+        let msg = Message::try_from(hl7)?;
+
+        assert_eq!(msg.segments.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_parse_matches_try_from() -> Result<(), Hl7ParseError> {
+        let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rOBR|segment\rOBX|1";
+        let msg = Message::parse(hl7)?;
+
+        assert_eq!(msg, Message::try_from(hl7)?);
+        assert_eq!(msg.to_string(), hl7);
+        assert!(Message::parse("not hl7").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_obr_owns_its_obx() -> Result<(), Hl7ParseError> {
+        let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rPID|||555-44-4444||EVERYWOMAN^EVE^E^^^^L|JONES|19620320|F|||153 FERNWOOD DR.^^STATESVILLE^OH^35292||(206)3345232|(206)752-121||||AC555444444||67-A4335^OH^20030520\rOBR|1|845439^GHH OE|1045813^GHH LAB|15545^GLUCOSE|||200202150730\rOBX|1|SN|1554-5^GLUCOSE^POST 12H CFST:MCNC:PT:SER/PLAS:QN||^182|mg/dl|70_105|H|||F";
+        let msg = Message::try_from(hl7)?;
+        let groups = msg.groups();
+
+        let orders: Vec<&SegmentGroup> = groups[0].groups_by_name("ORDER_OBSERVATION").collect();
+        assert_eq!(orders.len(), 1);
+        assert_eq!(orders[0].segments_by_name("OBR").count(), 1);
+        let observations: Vec<&SegmentGroup> = orders[0].groups_by_name("OBSERVATION").collect();
+        assert_eq!(observations.len(), 1);
+        assert_eq!(observations[0].segments[0].query("F5"), "^182");
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_messages_round_trip() -> Result<(), Hl7ParseError> {
+        let samples = [
+            "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rPID|||555-44-4444||EVERYWOMAN^EVE^E^^^^L|JONES|19620320|F|||153 FERNWOOD DR.^^STATESVILLE^OH^35292||(206)3345232|(206)752-121||||AC555444444||67-A4335^OH^20030520\rOBR|1|845439^GHH OE|1045813^GHH LAB|15545^GLUCOSE|||200202150730|||||||||555-55-5555^PRIMARY^PATRICIA P^^^^MD^^|||||||Joes Obs \\T\\ Gynae||F||||||444-44-4444^HIPPOCRATES^HOWARD H^^^^MD\rOBX|1|SN|1554-5^GLUCOSE^POST 12H CFST:MCNC:PT:SER/PLAS:QN||^182|mg/dl|70_105|H|||F",
+            "MSH|^~\\&|ADT1|GOOD HEALTH HOSPITAL|GHH LAB, INC.|GOOD HEALTH HOSPITAL|198808181126|SECURITY|ADT^A01^ADT_A01|MSG00001|P|2.8||\rEVN|A01|200708181123||\rPID|1||PATID1234^^^GOOD HEALTH HOSPITAL^MR~123456789^^^USSSA^SS||EVERYMAN^ADAM^A^III||19610615|M||C|2222 HOME STREET^^GREENSBORO^NC^27401-1020|GL|(555) 555-2004|(555)555-2004||S||PATID12345001^2^M10^ADT1^AN^A|444333333|987654^NC|\rNK1|1|NUCLEAR^NELDA^W|SPO^SPOUSE||||NK^NEXT OF KIN\rPV1|1|I|2000^2012^01||||004777^ATTEND^AARON^A|||SUR||||ADM|A0|\r",
+            "MSH|^~\\&|CATH|StJohn|AcmeHIS|StJohn|20061019172719||ACK^O01|MSGID12349876|P|2.3\rMSA|AA|MSGID12349876\r\r",
+            "MSH#@~\\$#GHH LAB#ELAB-3#GHH OE#BLDG4#200202150930##ORU@R01#CNTRL-3456#P#2.4\rPID###555-44-4444@@@GHH$MR~X||Y##EVERYWOMAN@EVE\rOBX#1#NM#1554-5@GLUCOSE##182#mg/dl",
+            "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||MDM^T02|CNTRL-3456|P|2.4\rTXA|1|CN||200202150930\rOBX|1|TX|||Line one\\.br\\Line two \\E\\ escaped \\F\\ chars||||||F\rZZZ|custom^segment&with~repeats",
+        ];
+
+        for hl7 in samples.iter() {
+            let msg = Message::parse(hl7)?;
+            assert_eq!(&msg.to_string(), hl7);
+
+            // each level of the parsed structure re-joins to its source with the message's own separators
+            let delims = msg.get_separators();
+            let segments: Vec<String> = msg.segments.iter().map(|s| s.to_string()).collect();
+            assert_eq!(&segments.join(&delims.segment.to_string()), hl7);
+
+            for segment in &msg.segments {
+                let fields: Vec<String> = segment.fields.iter().map(|f| f.to_string()).collect();
+                assert_eq!(fields.join(&delims.field.to_string()), segment.source);
+
+                if segment.fields[0].source == "MSH" {
+                    continue; // MSH-2 is the encoding chars, so isn't split further
+                }
+                for field in &segment.fields {
+                    let repeats: Vec<String> = field
+                        .components()
+                        .iter()
+                        .map(|c| c.join(&delims.component.to_string()))
+                        .collect();
+                    assert_eq!(repeats.join(&delims.repeat.to_string()), field.source);
+                }
+                if let Ok(typed) = segment.typed() {
+                    assert_eq!(typed.source(), segment.source);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_missing_pid_fails_validation() -> Result<(), Hl7ParseError> {
+        let hl7 = "MSH|^~\\&|ADT1|GOOD HEALTH HOSPITAL|GHH LAB|GHH|200202150930||ADT^A01|CNTRL-3456|P|2.4\rEVN|A01|200202150930\rPV1|1|I\r";
+        let msg = Message::try_from(hl7)?;
+        let profile = MessageProfile::for_message(&msg).unwrap();
+
+        let errors = msg.validate(&profile).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].segment, "PID");
+        assert_eq!(errors[0].found, 0);
+        assert_eq!(
+            errors[0].to_string(),
+            "PID occurs 0 times, expected exactly 1"
+        );
+
+        // custom profiles
+        let profile = MessageProfile::new().with("PV1", 1, None);
+        assert!(msg.validate(&profile).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_line_endings_are_interchangeable() -> Result<(), Hl7ParseError> {
+        let lines = [
+            "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4",
+            "PID|||555-44-4444||EVERYWOMAN^EVE",
+            "OBX|1|NM|1554-5^GLUCOSE||182",
+        ];
+        let canonical = lines.join("\r");
+        let expected = Message::try_from(&*canonical)?;
+
+        for ending in ["\r", "\n", "\r\n"] {
+            let hl7 = lines.join(ending);
+            let msg = Message::try_from(&*hl7)?;
+            assert_eq!(msg.segments.len(), 3, "{:?}", ending);
+            assert_eq!(msg.get_separators().segment, '\r');
+            for (actual, expected) in msg.segments.iter().zip(expected.segments.iter()) {
+                assert_eq!(actual.source, expected.source);
+            }
+            assert_eq!(msg.query("OBX-5"), "182");
+            assert_eq!(msg.to_string(), hl7);
+
+            // a trailing separator still gives a single empty segment, as it always has
+            let hl7 = format!("{}{}", hl7, ending);
+            assert_eq!(Message::try_from(&*hl7)?.segments.len(), 4);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_errors_report_the_failing_segment() {
+        let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rPID|||555-44-4444\rObx|1|NM|||42\rNTE|1";
+        let options = ParseOptions {
+            require_uppercase_segment_ids: true,
+            ..Default::default()
+        };
+
+        let err = Message::parse_with_options(hl7, &options).unwrap_err();
+        match &err {
+            Hl7ParseError::AtSegment { index, segment, .. } => {
+                assert_eq!((*index, segment.as_str()), (2, "Obx"))
+            }
+            _ => panic!("Expected the error to carry the segment, got {:?}", err),
+        }
+        assert!(matches!(
+            err.root_cause(),
+            Hl7ParseError::InvalidSegmentId(_)
+        ));
+        assert_eq!(
+            err.to_string(),
+            "Segment 2 (Obx): Invalid segment id 'Obx', segment ids must be uppercase"
+        );
+    }
+
+    #[test]
+    fn ensure_custom_segments_are_found() -> Result<(), Hl7ParseError> {
+        let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ADT^A01|CNTRL-3456|P|2.4\rZPD|1|custom\rPID|1\rZIN|1|PLAN\r";
+        let msg = Message::try_from(hl7)?;
+
+        let custom: Vec<&str> = msg.custom_segments().map(|s| s.source).collect();
+        assert_eq!(custom, vec!["ZPD|1|custom", "ZIN|1|PLAN"]);
+        assert_eq!(msg.custom_segments().next().unwrap()[2], "custom");
+
+        let msg = Message::try_from(
+            "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ADT^A01|CNTRL-3456|P|2.4\rPID|1",
+        )?;
+        assert_eq!(msg.custom_segments().count(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_messages_can_be_redelimited() -> Result<(), Hl7ParseError> {
+        let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rPID|||555-44-4444||EVERYWOMAN^EVE~SMITH^EVE||||||APT #4&UNIT 2\rNTE|1||Fish \\T\\ Chips\r";
+        let msg = Message::try_from(hl7)?;
+        let hashes = Separators {
+            field: '#',
+            component: '@',
+            ..Separators::default()
+        };
+
+        let rebuilt = msg.rebuild_with(&hashes);
+        assert_eq!(
+            rebuilt,
+            "MSH#@~\\&#GHH LAB#ELAB-3#GHH OE#BLDG4#200202150930##ORU@R01#CNTRL-3456#P#2.4\rPID###555-44-4444##EVERYWOMAN@EVE~SMITH@EVE######APT \\F\\4&UNIT 2\rNTE#1##Fish \\T\\ Chips\r"
+        );
+
+        // the result declares its own delimiters, and has the same content
+        let redelimited = Message::try_from(&*rebuilt)?;
+        assert_eq!(redelimited.get_separators(), hashes);
+        assert_eq!(redelimited.query("NTE-3"), msg.query("NTE-3"));
+        assert_eq!(redelimited.query("PID.F5.R2.C1"), "SMITH");
+
+        // unchanged delimiters rebuild the source exactly
+        assert_eq!(msg.rebuild_with(&msg.get_separators()), hl7);
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_escaped_segment_separators_dont_split() -> Result<(), Hl7ParseError> {
+        let msh = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4";
+
+        // an escape encoded CR never contains the separator
+        let hl7 = format!("{}\rOBX|1|TX|||Line 1\\X0D\\Line 2\rNTE|1", msh);
+        let msg = Message::try_from(&*hl7)?;
+        assert_eq!(msg.segments.len(), 3);
+        assert_eq!(msg.query("OBX-5"), "Line 1\\X0D\\Line 2");
+
+        // a literal separator straight after an (opening) escape char is kept in the value, whatever the line ending
+        for ending in &["\r", "\n", "\r\n"] {
+            let hl7 = format!("{}\rOBX|1|TX|||Line 1\\{}Line 2\rNTE|1", msh, ending);
+            let msg = Message::try_from(&*hl7)?;
+            assert_eq!(msg.segments.len(), 3);
+            assert_eq!(msg.query("OBX-5"), format!("Line 1\\{}Line 2", ending));
+            assert_eq!(crate::raw::RawMessage::new(&hl7)?.segments().count(), 3);
+        }
+
+        // but not after the escape char closing a sequence
+        let hl7 = format!("{}\rOBX|1|TX|||Tom\\T\\\rNTE|1", msh);
+        let msg = Message::try_from(&*hl7)?;
+        assert_eq!(msg.segments.len(), 3);
+        assert_eq!(msg.query("OBX-5"), "Tom\\T\\");
+
+        let separators = Separators {
+            segment: '\n',
+            ..Separators::default()
+        };
+        let hl7 = format!("{}\nOBX|1|TX|||a\\\nb", msh);
+        let msg = Message::parse_with_separators(&hl7, separators)?;
+        assert_eq!(msg.segments.len(), 2);
+        assert_eq!(msg.query("OBX-5"), "a\\\nb");
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_explicit_separators_are_used() -> Result<(), Hl7ParseError> {
+        let hl7 = "MSH#@~\\$#GHH LAB#ELAB-3#GHH OE#BLDG4#200202150930##ORU@R01#CNTRL-3456#P#2.4\nOBX#1#TX###a\rb";
+        let separators = Separators {
+            segment: '\n',
+            field: '#',
+            component: '@',
+            subcomponent: '$',
+            ..Separators::default()
+        };
+        let msg = Message::parse_with_separators(hl7, separators)?;
+
+        assert_eq!(msg.segments.len(), 2);
+        assert_eq!(msg.get_separators(), separators);
+        assert_eq!(msg.query("OBX-5"), "a\rb");
+        assert_eq!(msg.query("MSH-9.2"), "R01");
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_message_type_delegates_to_msh() -> Result<(), Hl7ParseError> {
+        let msg = Message::try_from("MSH|^~\\&|ADT1|GOOD HEALTH HOSPITAL|GHH LAB|GHH|198808181126||ADT^A01^ADT_A01|MSG00001|P|2.8")?;
+        let message_type = msg.message_type().unwrap();
+        assert_eq!(message_type.trigger_event, Some("A01"));
+        assert_eq!(message_type.structure, Some("ADT_A01"));
+
+        // a truncated MSH has no message type, rather than panicking
+        assert_eq!(Message::try_from("MSH|^~\\&|ADT1")?.message_type(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_fields_are_found_by_predicate() -> Result<(), Hl7ParseError> {
+        let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rPID|||555-44-4444^^^GHH~123^^^OTHER||EVERYWOMAN^EVE\rOBR|1|845439^GHH OE|1045813^GHH LAB\rOBX|1|NM|1554-5^GLUCOSE||182\rOBX|2|NM|2951-2^SODIUM||140^GHH";
+        let msg = Message::try_from(hl7)?;
+
+        let found = msg.find_fields(|value| value.contains("GHH"));
+        let summary: Vec<(&str, usize, usize, usize)> = found
+            .iter()
+            .map(|f| (f.segment, f.occurrence, f.field, f.repeat))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("MSH", 0, 2, 0),
+                ("MSH", 0, 4, 0),
+                ("PID", 0, 3, 0),
+                ("OBR", 0, 2, 0),
+                ("OBR", 0, 3, 0),
+                ("OBX", 1, 5, 0),
+            ]
+        );
+        assert_eq!(found[2].value, "555-44-4444^^^GHH");
+        assert_eq!(found[5].value, "140^GHH");
+
+        assert!(msg.find_fields(|value| value == "nothing").is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_messages_are_diffed_by_field() -> Result<(), Hl7ParseError> {
+        let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rPID|||555-44-4444||EVERYWOMAN^EVE^E^^^^L\rOBR|1|845439^GHH OE|1045813^GHH LAB|15545^GLUCOSE\rOBX|1|SN|1554-5^GLUCOSE^POST 12H CFST:MCNC:PT:SER/PLAS:QN||^182|mg/dl|70_105|H|||F";
+        let msg = Message::try_from(hl7)?;
+        assert!(msg.diff(&msg).is_empty());
+
+        let modified = hl7.replace("||^182|", "||^190|");
+        let changes = msg.diff(&Message::try_from(&*modified)?);
+        assert_eq!(
+            changes,
+            vec![FieldDiff {
+                segment: "OBX".to_string(),
+                occurrence: 0,
+                field: Some(5),
+                old: Some("^182"),
+                new: Some("^190"),
+            }]
+        );
+
+        // re-spaced delimiters and trailing empty fields aren't changes, but added/removed segments are
+        let respaced = "MSH#@~\\&#GHH LAB#ELAB-3#GHH OE#BLDG4#200202150930##ORU@R01#CNTRL-3456#P#2.4\rPID###555-44-4444##EVERYWOMAN@EVE@E@@@@L###\rOBR#1#845439@GHH OE#1045813@GHH LAB#15545@GLUCOSE\rNTE#1##Fasting";
+        let changes = msg.diff(&Message::try_from(respaced)?);
+        let summary: Vec<(&str, usize, Option<usize>)> = changes
+            .iter()
+            .map(|d| (d.segment.as_str(), d.occurrence, d.field))
+            .collect();
+        assert_eq!(summary, vec![("OBX", 0, None), ("NTE", 0, None)]);
+        assert_eq!(changes[0].new, None);
+        assert_eq!(changes[1].new, Some("NTE#1##Fasting"));
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_segments_are_found() -> Result<(), Hl7ParseError> {
+        let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rOBR|segment";
+        let msg = Message::try_from(hl7)?;
+
+        assert_eq!(msg.segments_by_name("OBR").count(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_separators_can_vary_per_segment() -> Result<(), Hl7ParseError> {
+        // a batch-ish feed where a second header switches to different encoding chars part way through
+        let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rPID|||555-44-4444^^^GHH\rMSH#@~\\$#OTHER LAB#ELAB-4\rPID#x#y#555-44-4444@@@OTHER";
+        // track the most recently seen header, as any segments after it use its encoding chars
+        let current = std::cell::Cell::new(Separators::default());
+        let redetect = |line: &str, _msh: Separators| {
+            if line.starts_with("MSH") {
+                current.set(line.parse().unwrap_or_else(|_| current.get()));
             }
+            current.get()
         };
-        if let Some(s) = sub_pos {
-            res.push(indices[s + 1])
+        let msg = Message::parse_with_segment_separators(hl7, redetect)?;
+
+        assert_eq!(msg.get_separators(), Separators::default());
+        assert_eq!(msg.segments[1].fields[3][(0, 3)], "GHH");
+        assert_eq!(msg.segments[2].fields[2].value(), "OTHER LAB");
+        assert_eq!(msg.segments[3].fields[3][(0, 3)], "OTHER");
+
+        // clones keep each segment's own separators
+        let dolly = msg.clone();
+        assert_eq!(dolly.segments[3].fields[3][(0, 3)], "OTHER");
+        assert_eq!(dolly, msg);
+
+        // the default behaviour matches try_from()
+        let default = Message::parse_with_segment_separators(hl7, |_, msh| msh)?;
+        assert_eq!(default, Message::try_from(hl7)?);
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_segments_are_streamed() -> Result<(), Hl7ParseError> {
+        let mut hl7 = String::from("MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rPID|||555-44-4444\rOBR|1");
+        for i in 1..=1000 {
+            hl7.push_str(&format!("\rOBX|{}|NM|HR^Heart rate||{}", i, i % 100));
         }
-        res
+
+        let mut count = 0;
+        let mut sum = 0;
+        Message::for_each_segment(&hl7, "OBX", |obx| {
+            count += 1;
+            sum += obx.query("F5").parse::<usize>().unwrap();
+        })?;
+        assert_eq!(count, 1000);
+        assert_eq!(sum, 49500);
+
+        let mut pids = Vec::new();
+        Message::for_each_segment(&hl7, "PID", |pid| pids.push(pid.source))?;
+        assert_eq!(pids, vec!["PID|||555-44-4444"]);
+
+        assert!(Message::for_each_segment("not hl7", "OBX", |_| ()).is_err());
+        Ok(())
     }
-}
 
-impl<'a> TryFrom<&'a str> for Message<'a> {
-    type Error = Hl7ParseError;
+    #[test]
+    fn ensure_truncated_messages_are_detected() -> Result<(), Hl7ParseError> {
+        let msh = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ADT^A01|CNTRL-3456|P|2.4";
+        let complete = format!(
+            "{}\rEVN|A01|200202150930\rPID|||555-44-4444\rPV1|1|I\r",
+            msh
+        );
+        assert!(Message::try_from(&*complete)?.looks_complete());
 
-    /// Takes the source HL7 string and parses it into this message.  Segments
-    /// and other data are slices (`&str`) into the source HL7
-    fn try_from(source: &'a str) -> Result<Self, Self::Error> {
-        let delimiters = str::parse::<Separators>(source)?;
+        // cut off before the PID
+        let truncated = format!("{}\rEVN|A01|200202150930", msh);
+        assert!(!Message::try_from(&*truncated)?.looks_complete());
 
-        let segments: Result<Vec<Segment<'a>>, Hl7ParseError> = source
-            .split(delimiters.segment)
-            .map(|line| Segment::parse(line, &delimiters))
-            .collect();
+        // cut off part way through a segment id
+        let truncated = format!("{}\rEVN|A01|200202150930\rPID|||555-44-4444\rPV", msh);
+        assert!(!Message::try_from(&*truncated)?.looks_complete());
 
-        let msg = Message {
-            source,
-            segments: segments?,
-            separators: delimiters,
-        };
+        // cut off inside the MSH
+        let truncated = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|2002021509";
+        assert!(!Message::try_from(truncated)?.looks_complete());
 
-        Ok(msg)
+        // unknown message codes only get the structural checks
+        let other =
+            "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ZZZ^Z01|CNTRL-3456|P|2.4\rZZ1|1";
+        assert!(Message::try_from(other)?.looks_complete());
+        Ok(())
     }
-}
 
-impl<'a> Display for Message<'a> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.source)
+    #[test]
+    fn ensure_expected_segments_can_be_overridden() -> Result<(), Hl7ParseError> {
+        let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ADT^A20|CNTRL-3456|P|2.4\rEVN|A20|200202150930\rNPU|4N^401";
+        let msg = Message::try_from(hl7)?;
+        assert!(!msg.looks_complete());
+        assert!(msg.looks_complete_with(&[("ADT", &["EVN", "NPU"])]));
+        assert!(!msg.looks_complete_with(&[("ADT", &["EVN", "PV1"])]));
+        Ok(())
     }
-}
 
-impl<'a> Clone for Message<'a> {
-    /// Creates a new cloned Message object referencing the same source slice as the original.
-    /// ## Example:
-    /// ```
-    /// # use rusthl7::Hl7ParseError;
-    /// # use rusthl7::message::Message;
-    /// # use std::convert::TryFrom;
-    /// # fn main() -> Result<(), Hl7ParseError> {
-    /// let m = Message::try_from("MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4")?;
-    /// let cloned = m.clone(); // this object is looking at the same string slice as m
-    /// # Ok(())
-    /// # }
-    /// ```
-    fn clone(&self) -> Self {
-        Message::try_from(self.source).unwrap()
-    }
-}
+    #[test]
+    fn ensure_provenance_is_reported() -> Result<(), Hl7ParseError> {
+        let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rPID|||555-44-4444||EVERYWOMAN^EVE||19620320\rOBX|1|NM|GLU||182";
+        let msg = Message::try_from(hl7)?;
 
-impl<'a> Index<usize> for Message<'a> {
-    type Output = &'a str;
+        let found = msg.query_with_provenance("PID.F5.C2").unwrap();
+        assert_eq!(found.value, "EVE");
+        assert_eq!(found.segment_index, 1);
+        assert_eq!(found.field_index, Some(5));
+        assert_eq!(&hl7[found.byte_span.clone()], "EVE");
+        assert_eq!(
+            found.to_string(),
+            format!(
+                "'EVE' from segments[1].fields[5] at bytes {}..{}",
+                found.byte_span.start, found.byte_span.end
+            )
+        );
 
-    /// Access Segment string reference by numeric index
-    fn index(&self, idx: usize) -> &Self::Output {
-        if idx > self.segments.len() {
-            return &"";
-        }
-        &self.segments[idx].source
-    }
-}
-#[cfg(feature = "string_index")]
-impl<'a> Index<String> for Message<'a> {
-    type Output = &'a str;
+        // empty, but present
+        let found = msg.query_with_provenance("PID.F6").unwrap();
+        assert_eq!((found.value, found.field_index), ("", Some(6)));
 
-    /// Access Segment, Field, or sub-field string references by string index
-    #[cfg(feature = "string_index")]
-    fn index(&self, idx: String) -> &Self::Output {
-        // Parse index elements
-        let indices = Self::parse_query_string(&idx);
-        let seg_name = indices[0];
-        // Find our first segment without offending the borow checker
-        let seg_index = self
-            .segments
-            .iter()
-            .position(|r| &r.as_str()[..seg_name.len()] == seg_name)
-            .expect("Segment not found");
-        let seg = &self.segments[seg_index];
-        if indices.len() < 2 {
-            &seg.source
-        } else {
-            &seg[indices[1..].join(".")]
-        }
-    }
-}
+        let found = msg.query_with_provenance("OBX").unwrap();
+        assert_eq!(found.value, "OBX|1|NM|GLU||182");
+        assert_eq!(found.field_index, None);
+        assert_eq!(found.byte_span.end, hl7.len());
 
-#[cfg(feature = "string_index")]
-impl<'a> Index<&str> for Message<'a> {
-    type Output = &'a str;
+        // agrees with query() for the lookup
+        assert_eq!(
+            msg.query_with_provenance("OBX.F5").unwrap().value,
+            msg.query("OBX.F5")
+        );
 
-    #[cfg(feature = "string_index")]
-    fn index(&self, idx: &str) -> &Self::Output {
-        &self[String::from(idx)]
+        assert_eq!(msg.query_with_provenance("PID.F99"), None);
+        assert_eq!(msg.query_with_provenance("PID.F5.C9"), None);
+        assert_eq!(msg.query_with_provenance("PID.F5.R2"), None);
+        assert_eq!(msg.query_with_provenance("ZZZ.F1"), None);
+        assert_eq!(msg.query_with_provenance(""), None);
+        Ok(())
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn ensure_multi_byte_values_have_correct_boundaries() -> Result<(), Hl7ParseError> {
+        let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ADT^A01|CNTRL-3456|P|2.4\rPID|||555-44-4444||MÜLLER^JOSÉ^Ōno||19620320|F\rNK1|1|ÅSTRÖM^BJÖRN";
+        let msg = Message::try_from(hl7)?;
+
+        assert_eq!(msg.query("PID.F5.C1"), "MÜLLER");
+        assert_eq!(msg.query("PID.F5.C2"), "JOSÉ");
+        assert_eq!(msg.query("PID.F5.C3"), "Ōno");
+        assert_eq!(msg.query("PID.F7"), "19620320");
+        assert_eq!(msg.query("NK1.F2.C2"), "BJÖRN");
+
+        // byte offsets line up with the source
+        let found = msg.query_with_provenance("PID.F5.C2").unwrap();
+        assert_eq!(&hl7[found.byte_span], "JOSÉ");
+        let offset = hl7.find("BJÖRN").unwrap();
+        assert_eq!(
+            msg.locate(offset),
+            Some(Location {
+                segment: 2,
+                field: 2,
+                repeat: 0,
+                component: 1,
+                subcomponent: 0
+            })
+        );
+        Ok(())
+    }
 
     #[test]
-    fn ensure_segments_are_returned() -> Result<(), Hl7ParseError> {
-        let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rOBR|segment";
+    fn ensure_outline_is_in_document_order() -> Result<(), Hl7ParseError> {
+        let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rOBR|segment\rOBX|1|NM|2951-2^SODIUM||140\rOBX|2";
         let msg = Message::try_from(hl7)?;
 
-        assert_eq!(msg.segments.len(), 2);
+        let names: Vec<&str> = msg.outline().iter().map(|(name, _)| *name).collect();
+        assert_eq!(names, vec!["MSH", "OBR", "OBX", "OBX"]);
+        assert_eq!(msg.outline()[2].1, "OBX|1|NM|2951-2^SODIUM||140");
         Ok(())
     }
 
     #[test]
-    fn ensure_segments_are_found() -> Result<(), Hl7ParseError> {
-        let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rOBR|segment";
+    fn ensure_segments_are_found_in_sample_message() -> Result<(), Hl7ParseError> {
+        let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rPID|||555-44-4444||EVERYWOMAN^EVE^E^^^^L|JONES|19620320|F|||153 FERNWOOD DR.^^STATESVILLE^OH^35292||(206)3345232|(206)752-121||||AC555444444||67-A4335^OH^20030520\rOBR|1|845439^GHH OE|1045813^GHH LAB|15545^GLUCOSE|||200202150730|||||||||555-55-5555^PRIMARY^PATRICIA P^^^^MD^^|||||||Joes Obs \\T\\ Gynae||F||||||444-44-4444^HIPPOCRATES^HOWARD H^^^^MD\rOBX|1|SN|1554-5^GLUCOSE^POST 12H CFST:MCNC:PT:SER/PLAS:QN||^182|mg/dl|70_105|H|||F";
         let msg = Message::try_from(hl7)?;
 
-        assert_eq!(msg.segments_by_name("OBR").unwrap().len(), 1);
+        assert_eq!(msg.segments_by_name("OBR").count(), 1);
+        assert_eq!(msg.segments_by_name("OBX").count(), 1);
+        assert_eq!(msg.segments_by_name("obx").count(), 0); // case sensitive
+        assert_eq!(msg.segments_by_name("NTE").count(), 0);
+
+        assert_eq!(
+            msg.first_segment("OBR").unwrap().query("F7"),
+            "200202150730"
+        );
+        assert!(msg.first_segment("ZZZ").is_none());
         Ok(())
     }
 
@@ -276,7 +2354,7 @@ mod tests {
     fn ensure_segments_convert_to_vectors() -> Result<(), Hl7ParseError> {
         let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rOBR|segment";
         let msg = Message::try_from(hl7)?;
-        let segs = msg.segments_by_name("OBR")?;
+        let segs: Vec<&Segment> = msg.segments_by_name("OBR").collect();
         let sval = segs.first().unwrap().fields.first().unwrap().value();
         let vecs = Message::segments_to_str_vecs(segs).unwrap();
         let vval = vecs.first().unwrap().first().unwrap();
@@ -295,6 +2373,34 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn ensure_clones_equal_the_original_for_each_entry_point() -> Result<(), Hl7ParseError> {
+        let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rOBR|segment";
+        let msg = Message::try_from(hl7)?;
+        assert_eq!(msg.clone(), msg);
+        let msg = Message::parse(hl7)?;
+        assert_eq!(msg.clone(), msg);
+        let msg = Message::parse_with_options(hl7, &ParseOptions::default())?;
+        assert_eq!(msg.clone(), msg);
+
+        // a carriage return in a value isn't a segment separator here, and mustn't become one in the clone
+        let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||MDM^T02|CNTRL-3456|P|2.4\nOBX|1|TX|||Line 1\rLine 2";
+        let separators = Separators {
+            segment: '\n',
+            ..Separators::default()
+        };
+        let msg = Message::parse_with_separators(hl7, separators)?;
+        let dolly = msg.clone();
+        assert_eq!(dolly.segments.len(), 2);
+        assert_eq!(dolly.query("OBX-5"), "Line 1\rLine 2");
+        assert_eq!(dolly, msg);
+
+        // explicit separators don't need an MSH to read them from, so cloning mustn't require one either
+        let msg = Message::parse_with_separators("PID|1|2", Separators::default())?;
+        assert_eq!(msg.clone(), msg);
+        Ok(())
+    }
+
     #[test]
     fn ensure_to_string() -> Result<(), Hl7ParseError> {
         let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rOBR|segment";
@@ -324,6 +2430,407 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn ensure_query_grammar() -> Result<(), Hl7ParseError> {
+        let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rOBR|segment^sub&segment~repeat";
+        let msg = Message::try_from(hl7)?;
+        assert_eq!(msg.query("OBR"), "OBR|segment^sub&segment~repeat");
+        assert_eq!(msg.query("OBR.1"), "segment^sub&segment~repeat");
+        assert_eq!(msg.query("OBR.1.2"), "sub&segment");
+        assert_eq!(msg.query("OBR.1.2.2"), "segment");
+        assert_eq!(msg.query("OBR.F1.C2.S1"), "sub");
+        assert_eq!(msg.query("OBR.1.R2"), "repeat");
+        assert_eq!(msg.query("obr.r2.f1"), ""); // segment names are case sensitive
+        assert_eq!(msg.query("OBR.f1.r2"), "repeat");
+
+        // degenerate cases
+        assert_eq!(msg.query(""), "");
+        assert_eq!(msg.query("PID.F3"), "");
+        assert_eq!(msg.query("OBR.F9"), "");
+        assert_eq!(msg.query("OBR.Fx"), "");
+        assert_eq!(msg.query("OBR.1.0"), "");
+        assert_eq!(msg.query("OBR."), "");
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_query_all_expands_segments_and_repeats() -> Result<(), Hl7ParseError> {
+        let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rOBX|1|SN|1554-5^GLUCOSE||^182~^190\rOBX|2|NM|2951-2^SODIUM||140\rOBX|3\rNTE|1";
+        let msg = Message::try_from(hl7)?;
+
+        assert_eq!(msg.query_all("OBX.F1"), vec!["1", "2", "3"]);
+        assert_eq!(msg.query_all("OBX.5"), vec!["^182", "^190", "140"]);
+        assert_eq!(msg.query_all("OBX.5.2"), vec!["182", "190", ""]);
+        assert_eq!(msg.query_all("OBX.F5.R2"), vec!["^190", ""]);
+        assert_eq!(msg.query_all("OBX.3.2"), vec!["GLUCOSE", "SODIUM"]);
+        assert_eq!(msg.query_all("NTE"), vec!["NTE|1"]);
+        assert!(msg.query_all("PID.F3").is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_query_strings_are_expanded() {
+        assert_eq!(Message::parse_query_string("PID"), vec!["PID"]);
+        assert_eq!(Message::parse_query_string("PID.5"), vec!["PID", "5"]);
+        assert_eq!(
+            Message::parse_query_string("PID.5.1"),
+            vec!["PID", "5", "R1", "1"]
+        );
+        assert_eq!(
+            Message::parse_query_string("PID.C2"),
+            vec!["PID", "F1", "R1", "C2"]
+        );
+        assert_eq!(
+            Message::parse_query_string("PID.F5.1.1"),
+            vec!["PID", "F5", "R1", "1", "1"]
+        );
+    }
+
+    #[test]
+    fn ensure_acknowledgments_are_detected() -> Result<(), Hl7ParseError> {
+        let oru = Message::try_from("MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rOBR|segment")?;
+        assert!(!oru.is_acknowledgment());
+        assert_eq!(oru.acknowledged_control_id(), None);
+
+        // no MSA, but still an ACK
+        let ack = Message::try_from(
+            "MSH|^~\\&|GHH OE|BLDG4|GHH LAB|ELAB-3|200202150930||ACK|ACK-1|P|2.4",
+        )?;
+        assert!(ack.is_acknowledgment());
+        assert_eq!(ack.acknowledged_control_id(), None);
+
+        // application level ack with a non-ACK message type
+        let ack = Message::try_from("MSH|^~\\&|GHH OE|BLDG4|GHH LAB|ELAB-3|200202150930||ORR^O02|ORR-1|P|2.4\rMSA|AA|CNTRL-3456")?;
+        assert!(ack.is_acknowledgment());
+        assert_eq!(ack.acknowledged_control_id(), Some("CNTRL-3456"));
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_offsets_are_located() -> Result<(), Hl7ParseError> {
+        let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rOBR|segment~a^sub&segment";
+        let msg = Message::try_from(hl7)?;
+        let location = |segment, field, repeat, component, subcomponent| {
+            Some(Location {
+                segment,
+                field,
+                repeat,
+                component,
+                subcomponent,
+            })
+        };
+
+        assert_eq!(msg.locate(0), location(0, 0, 0, 0, 0));
+        assert_eq!(
+            msg.locate(hl7.find("ORU").unwrap()),
+            location(0, 8, 0, 0, 0)
+        );
+        assert_eq!(
+            msg.locate(hl7.find("R01").unwrap()),
+            location(0, 8, 0, 1, 0)
+        );
+        assert_eq!(
+            msg.locate(hl7.find("OBR").unwrap()),
+            location(1, 0, 0, 0, 0)
+        );
+        assert_eq!(msg.locate(hl7.find("a^").unwrap()), location(1, 1, 1, 0, 0));
+        assert_eq!(msg.locate(hl7.len() - 1), location(1, 1, 1, 1, 1));
+        assert_eq!(msg.locate(hl7.len()), location(1, 1, 1, 1, 1)); // cursor after the last char
+        assert_eq!(msg.locate(hl7.len() + 1), None);
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_inconsistent_encoding_chars_are_detected() -> Result<(), Hl7ParseError> {
+        let good = [
+            "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01^ORU_R01|CNTRL-3456|P|2.4",
+            "MSH#@~\\&#GHH LAB#ELAB-3#GHH OE#BLDG4#200202150930##ORU@R01#CNTRL-3456#P#2.4",
+            "MSH|^~\\&#|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ACK|CNTRL-3456|P|2.7",
+        ];
+        for hl7 in good.iter() {
+            assert!(
+                Message::try_from(*hl7)?
+                    .check_encoding_consistency()
+                    .is_ok(),
+                "{}",
+                hl7
+            );
+        }
+
+        let bad = [
+            // declares '@' as the component separator, but MSH-9 uses '^'
+            "MSH|@~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4",
+            // fields separated by '#', which isn't what MSH-1 declares
+            "MSH|^~\\&#GHH LAB#ELAB-3#GHH OE#BLDG4#200202150930##ORU^R01#CNTRL-3456#P#2.4",
+            // truncated MSH
+            "MSH|^~\\&|GHH LAB|ELAB-3",
+        ];
+        for hl7 in bad.iter() {
+            assert!(
+                matches!(
+                    Message::try_from(*hl7)?.check_encoding_consistency(),
+                    Err(Hl7ParseError::Msh1Msh2(_))
+                ),
+                "{}",
+                hl7
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_charset_is_validated() -> Result<(), Hl7ParseError> {
+        let msh = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ADT^A01|CNTRL-3456|P|2.4";
+        let pid = "PID|||555-44-4444||MÜLLER^EVE||19620320|F|||Straße 1";
+
+        // no MSH-18, defaults to ASCII
+        let source = format!("{}\r{}", msh, pid);
+        let violations = Message::try_from(&*source)?.validate_charset();
+        assert_eq!(
+            violations,
+            vec![
+                CharsetViolation {
+                    segment: 1,
+                    field: 5,
+                    value: "MÜLLER^EVE",
+                    character: 'Ü'
+                },
+                CharsetViolation {
+                    segment: 1,
+                    field: 11,
+                    value: "Straße 1",
+                    character: 'ß'
+                }
+            ]
+        );
+
+        // Latin-1 can represent these, but not the euro sign
+        let source = format!("{}||||||8859/1~ISO IR87\r{}\rNTE|1||€5", msh, pid);
+        let violations = Message::try_from(&*source)?.validate_charset();
+        assert_eq!(violations.len(), 1);
+        assert_eq!((violations[0].segment, violations[0].field), (2, 3));
+
+        // unicode is a no-op
+        let source = format!("{}||||||UNICODE UTF-8\r{}\rNTE|1||€5", msh, pid);
+        assert!(Message::try_from(&*source)?.validate_charset().is_empty());
+        Ok(())
+    }
+
+    #[cfg(feature = "encoding_rs")]
+    #[test]
+    fn ensure_bytes_are_decoded_per_msh_18() -> Result<(), Hl7ParseError> {
+        let msh = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ADT^A01|CNTRL-3456|P|2.4";
+
+        // MÜLLER and Straße in Latin-1, which isn't valid UTF-8
+        let mut latin1 = format!("{}||||||8859/1~ISO IR87\rPID|||555-44-4444||M", msh).into_bytes();
+        latin1.extend_from_slice(b"\xdcLLER^EVE||19620320|F|||Stra\xdfe 1");
+        assert!(std::str::from_utf8(&latin1).is_err());
+
+        let owned = Message::parse_bytes(&latin1)?;
+        let msg = owned.as_message();
+        assert_eq!(msg.query("PID-5"), "MÜLLER^EVE");
+        assert_eq!(msg.query("PID-11"), "Straße 1");
+        assert!(msg.validate_charset().is_empty());
+
+        // no MSH-18 is UTF-8
+        let utf8 = format!("{}\rPID|||555-44-4444||MÜLLER^EVE", msh);
+        let owned = Message::parse_bytes(utf8.as_bytes())?;
+        assert_eq!(owned.as_message().query("PID-5.1"), "MÜLLER");
+
+        // Latin-1 that isn't declared isn't valid UTF-8
+        let mut undeclared = format!("{}\rPID|||555-44-4444||M", msh).into_bytes();
+        undeclared.extend_from_slice(b"\xdcLLER^EVE");
+        assert!(Message::parse_bytes(&undeclared).is_err());
+
+        let unknown = format!("{}||||||EBCDIC\rPID|1", msh);
+        assert!(Message::parse_bytes(unknown.as_bytes()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_fingerprint_ignores_volatile_fields() -> Result<(), Hl7ParseError> {
+        let original = Message::try_from("MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rPID|||555-44-4444\rOBX|1|NM|2951-2^SODIUM||140")?;
+        let resent = Message::try_from("MSH#@~\\&#GHH LAB#ELAB-3#GHH OE#BLDG4#200202151015##ORU@R01#CNTRL-9999#P#2.4\rPID###555-44-4444\rOBX#1#NM#2951-2@SODIUM##140#")?;
+        let changed = Message::try_from("MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rPID|||555-44-4444\rOBX|1|NM|2951-2^SODIUM||141")?;
+
+        let fingerprint = original.content_fingerprint(VOLATILE_FIELDS);
+        assert_eq!(fingerprint, resent.content_fingerprint(VOLATILE_FIELDS));
+        assert_ne!(fingerprint, changed.content_fingerprint(VOLATILE_FIELDS));
+        assert_ne!(fingerprint, original.content_fingerprint(&[]));
+
+        // blanking the changed value makes them match again
+        let ignore = [("MSH", 7), ("MSH", 10), ("OBX", 5)];
+        assert_eq!(
+            original.content_fingerprint(&ignore),
+            changed.content_fingerprint(&ignore)
+        );
+
+        // stable across runs
+        assert_eq!(
+            original.content_fingerprint(&[]),
+            original.content_fingerprint(&[])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_canonical_form() -> Result<(), Hl7ParseError> {
+        let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01^|CNTRL-3456|P|2.4||\r\nOBR|segment^sub&&~|\r";
+        let custom = "MSH#@~\\&#GHH LAB#ELAB-3#GHH OE#BLDG4#200202150930##ORU@R01#CNTRL-3456#P#2.4\rOBR#segment@sub&";
+        let expected = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rOBR|segment^sub";
+
+        assert_eq!(Message::try_from(hl7)?.canonical(), expected);
+        assert_eq!(Message::try_from(custom)?.canonical(), expected);
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_canonical_form_escapes_default_delimiters() -> Result<(), Hl7ParseError> {
+        let custom = "MSH#@~$&#GHH|LAB#ELAB^3#a$F$value";
+        let msg = Message::try_from(custom)?;
+        assert_eq!(
+            msg.canonical(),
+            "MSH|^~\\&|GHH\\F\\LAB|ELAB\\S\\3|a\\F\\value"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_custom_separators_apply_to_every_segment() -> Result<(), Hl7ParseError> {
+        let hl7 = "MSH#@~\\&#GHH LAB#ELAB-3#GHH OE#BLDG4#200202150930##ORU@R01#CNTRL-3456#P#2.4\rPID###555-44-4444##EVERYWOMAN@EVE|JANE";
+        let msg = Message::parse(hl7)?;
+
+        assert_eq!(msg.get_separators().field, '#');
+        assert_eq!(msg.get_separators().component, '@');
+        assert_eq!(msg.query("MSH.F8.C2"), "R01");
+        assert_eq!(msg.query("PID.F3"), "555-44-4444");
+        assert_eq!(msg.query("PID.F5.C1"), "EVERYWOMAN");
+        assert_eq!(msg.query("PID.F5.C2"), "EVE|JANE"); // the default field separator is just data here
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_hl7_paths_are_queried() -> Result<(), Hl7ParseError> {
+        let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rPID|||555-44-4444||EVERYWOMAN^EVE^E^^^^L|JONES\rOBX|1|NM|1554-5^GLUCOSE||182|mg/dl\rOBX|2|NM|2951-2^SODIUM^LN&LOINC||140|mmol/l";
+        let msg = Message::try_from(hl7)?;
+
+        // segment
+        assert_eq!(
+            msg.query("OBX[2]"),
+            "OBX|2|NM|2951-2^SODIUM^LN&LOINC||140|mmol/l"
+        );
+
+        // field, with MSH numbered as per the spec
+        assert_eq!(msg.query("MSH-1"), "|");
+        assert_eq!(msg.query("MSH-2"), "^~\\&");
+        assert_eq!(msg.query("MSH-9"), "ORU^R01");
+        assert_eq!(msg.query("PID-3"), "555-44-4444");
+        assert_eq!(msg.query("OBX-5"), "182");
+        assert_eq!(msg.query("OBX[1]-5"), "182");
+        assert_eq!(msg.query("OBX[2]-5"), "140");
+
+        // component
+        assert_eq!(msg.query("MSH-9.2"), "R01");
+        assert_eq!(msg.query("PID-5.1"), "EVERYWOMAN");
+        assert_eq!(msg.query("OBX[2]-3.2"), "SODIUM");
+
+        // subcomponent
+        assert_eq!(msg.query("OBX[2]-3.3.2"), "LOINC");
+        assert_eq!(msg.query("PID-5.2.1"), "EVE");
+
+        // unknown segments and out of range/malformed paths
+        for path in [
+            "ZZZ-1",
+            "OBX[3]-5",
+            "OBX[0]-5",
+            "OBX[x]-5",
+            "OBX[2-5",
+            "PID-99",
+            "PID-0",
+            "PID-5.99",
+            "PID-5.1.99",
+            "PID-5.1.1.1",
+            "PID-",
+            "PID-a",
+            "MSH-1.1",
+        ] {
+            assert_eq!(msg.query(path), "", "{} should be empty", path);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_ack_is_built() -> Result<(), Hl7ParseError> {
+        let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rPID|||555-44-4444";
+        let msg = Message::try_from(hl7)?;
+
+        let ack = msg.build_ack_with(AckCode::AE, "ACK-1", "200202150931");
+        let ack = Message::try_from(ack.as_str())?;
+        assert!(ack.is_acknowledgment());
+        assert_eq!(ack.acknowledged_control_id(), Some("CNTRL-3456"));
+        // query() indexes MSH fields from MSH-2, so MSH.F2 is MSH-3
+        assert_eq!(ack.query("MSH.F2"), "GHH OE");
+        assert_eq!(ack.query("MSH.F3"), "BLDG4");
+        assert_eq!(ack.query("MSH.F4"), "GHH LAB");
+        assert_eq!(ack.query("MSH.F5"), "ELAB-3");
+        assert_eq!(ack.query("MSH.F8"), "ACK^R01");
+        assert_eq!(ack.query("MSH.F9"), "ACK-1");
+        assert_eq!(ack.query("MSH.F11"), "2.4");
+        assert_eq!(ack.query("MSA.F1"), "AE");
+
+        // generated control ids differ
+        let first = Message::try_from(msg.build_ack(AckCode::AA).as_str())?
+            .query("MSH.F9")
+            .to_string();
+        let second = Message::try_from(msg.build_ack(AckCode::AA).as_str())?
+            .query("MSH.F9")
+            .to_string();
+        assert!(!first.is_empty());
+        assert_ne!(first, second);
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_ack_handles_sparse_msh_and_custom_separators() -> Result<(), Hl7ParseError> {
+        // no MSH-3/4, escaped delimiters in the control id, and no trigger event
+        let hl7 = "MSH#@~$&###GHH OE#BLDG4#200202150930##ORU#CNTRL$F$3456#P#2.5";
+        let msg = Message::try_from(hl7)?;
+
+        assert_eq!(
+            msg.build_ack_with(AckCode::AR, "ACK-2", "200202150931"),
+            "MSH#@~$&#GHH OE#BLDG4###200202150931##ACK#ACK-2#P#2.5\rMSA#AR#CNTRL$F$3456"
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn ensure_message_serializes_to_json() -> Result<(), Hl7ParseError> {
+        let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rOBR|segment^sub&segment~repeat";
+        let msg = Message::try_from(hl7)?;
+        let json = serde_json::to_value(&msg).unwrap();
+
+        assert_eq!(json["separators"]["field"], "|");
+        assert_eq!(json["separators"]["subcomponent"], "&");
+
+        // MSH-1 and MSH-2 are the separators as plain strings, then MSH-n is fields[n - 1] as for any other segment
+        let msh = &json["segments"][0];
+        assert_eq!(msh["name"], "MSH");
+        assert_eq!(msh["fields"][0], "|");
+        assert_eq!(msh["fields"][1], "^~\\&");
+        assert_eq!(msh["fields"][2], serde_json::json!([[["GHH LAB"]]]));
+        assert_eq!(msh["fields"][8], serde_json::json!([[["ORU"], ["R01"]]]));
+        assert_eq!(msh["fields"].as_array().unwrap().len(), 12);
+
+        let obr = &json["segments"][1];
+        assert_eq!(obr["name"], "OBR");
+        assert_eq!(
+            obr["fields"][0],
+            serde_json::json!([[["segment"], ["sub", "segment"]], [["repeat"]]])
+        );
+        Ok(())
+    }
+
     #[cfg(feature = "string_index")]
     mod string_index_tests {
         use super::*;
@@ -340,5 +2847,43 @@ mod tests {
             assert_eq!(msg["MSH.F2"], "^~\\&");
             Ok(())
         }
+
+        #[test]
+        fn ensure_hl7_path_index() -> Result<(), Hl7ParseError> {
+            let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rPID|||555-44-4444||EVERYWOMAN^EVE\rOBX|1|NM|1554-5^GLUCOSE||182\rOBX|2|NM|2951-2^SODIUM&NA||140";
+            let msg = Message::try_from(hl7)?;
+            assert_eq!(msg["PID-5.1"], "EVERYWOMAN");
+            assert_eq!(msg["PID-5"], "EVERYWOMAN^EVE");
+            assert_eq!(msg["MSH-9.2"], "R01");
+            assert_eq!(msg["MSH-10"], "CNTRL-3456");
+            assert_eq!(msg["OBX[2]-5"], "140");
+            assert_eq!(msg["OBX[2]-3.2.2"], "NA");
+            assert_eq!(msg["OBX[2]"], "OBX|2|NM|2951-2^SODIUM&NA||140");
+            for path in ["PID-5", "MSH-9.2", "OBX[2]-5", "OBX[2]-3.2.2"] {
+                assert_eq!(msg[path], msg.query(path));
+            }
+            Ok(())
+        }
+
+        #[test]
+        fn ensure_out_of_range_index_is_empty() -> Result<(), Hl7ParseError> {
+            let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rOBR|segment^sub&segment";
+            let msg = Message::try_from(hl7)?;
+            for idx in [
+                "ZZZ.F1",
+                "ZZZ",
+                "",
+                "OBR.F5",
+                "OBR-5.1",
+                "OBR[2]-1",
+                "OBR[0]-1",
+                "OBR-0",
+                "OBR-x",
+                "OBR-1.1.1.1",
+            ] {
+                assert_eq!(msg[idx], "", "{:?} should be out of range", idx);
+            }
+            Ok(())
+        }
     }
 }
@@ -0,0 +1,230 @@
+/*!
+Minimal Lower Layer Protocol (MLLP) framing, as used to exchange HL7 messages over TCP.
+
+Each message is wrapped in a frame: a start block (`0x0B`), the message itself, then an end block (`0x1C`) followed by a carriage return (`0x0D`).
+This module only deals with the framing, the socket handling (and sending ACKs etc) is left to the caller.
+*/
+
+use super::*;
+use log::warn;
+
+/// Start of block, marks the beginning of a frame
+pub const START_BLOCK: u8 = 0x0B;
+/// End of block, followed by `CARRIAGE_RETURN` to end a frame
+pub const END_BLOCK: u8 = 0x1C;
+/// Final byte of a frame, after the `END_BLOCK`
+pub const CARRIAGE_RETURN: u8 = 0x0D;
+
+/// Wraps the message in an MLLP frame, ready to be written to a socket.
+/// ## Example:
+/// ```
+/// # use rusthl7::mllp::wrap;
+/// assert_eq!(wrap("MSH|^~\\&"), b"\x0bMSH|^~\\&\x1c\x0d");
+/// ```
+pub fn wrap(message: &str) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(message.len() + 3);
+    frame.push(START_BLOCK);
+    frame.extend_from_slice(message.as_bytes());
+    frame.push(END_BLOCK);
+    frame.push(CARRIAGE_RETURN);
+    frame
+}
+
+/// Validates a single, complete MLLP frame, returning the message inside it.
+/// ## Example:
+/// ```
+/// # use rusthl7::Hl7ParseError;
+/// # use rusthl7::mllp::unwrap;
+/// # fn main() -> Result<(), Hl7ParseError> {
+/// assert_eq!(unwrap(b"\x0bMSH|^~\\&\x1c\x0d")?, "MSH|^~\\&");
+/// assert!(unwrap(b"\x0bMSH|^~\\&").is_err());
+/// # Ok(())
+/// # }
+/// ```
+pub fn unwrap(frame: &[u8]) -> Result<&str, Hl7ParseError> {
+    let payload = frame
+        .strip_prefix(&[START_BLOCK])
+        .ok_or_else(|| Hl7ParseError::Generic("MLLP frame doesn't start with 0x0B".to_string()))?
+        .strip_suffix(&[END_BLOCK, CARRIAGE_RETURN])
+        .ok_or_else(|| {
+            Hl7ParseError::Generic("MLLP frame doesn't end with 0x1C 0x0D".to_string())
+        })?;
+
+    std::str::from_utf8(payload)
+        .map_err(|e| Hl7ParseError::Generic(format!("MLLP payload isn't valid UTF-8: {}", e)))
+}
+
+/// Reassembles messages from a stream of MLLP framed bytes, eg as read from a socket.
+///
+/// Chunks can be pushed in as they arrive, regardless of where they split frames, and each complete message is returned from
+/// `next_message()` once its trailer has been received.  Any bytes between frames (ie before a start block) are discarded, as is a
+/// frame interrupted by another start block (eg after the sender dropped a connection part way through a message and retried), decoding
+/// restarts from the latest start block.
+///
+/// Only newly pushed bytes are scanned for the trailer, so a large frame arriving in many small chunks is decoded in linear time.
+/// ## Example:
+/// ```
+/// # use rusthl7::mllp::MllpDecoder;
+/// let mut decoder = MllpDecoder::new();
+/// decoder.push(b"\x0bMSH|^~\\&|FIRST\x1c");
+/// assert!(decoder.next_message().is_none()); // waiting on the rest of the trailer
+///
+/// decoder.push(b"\x0d\x0bMSH|^~\\&|SECOND\x1c\x0d");
+/// assert_eq!(decoder.next_message().unwrap().unwrap(), "MSH|^~\\&|FIRST");
+/// assert_eq!(decoder.next_message().unwrap().unwrap(), "MSH|^~\\&|SECOND");
+/// assert!(decoder.next_message().is_none());
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct MllpDecoder {
+    buffer: Vec<u8>,
+    /// How much of the frame at the start of `buffer` has already been searched for its trailer
+    scanned: usize,
+}
+
+impl MllpDecoder {
+    /// Creates a decoder with an empty buffer.
+    pub fn new() -> MllpDecoder {
+        MllpDecoder::default()
+    }
+
+    /// Appends a chunk of received bytes to the buffer.
+    pub fn push(&mut self, chunk: &[u8]) {
+        self.buffer.extend_from_slice(chunk);
+    }
+
+    /// Returns the next complete message from the buffer, or `None` if no complete frame has been received yet.
+    /// A frame whose payload isn't valid UTF-8 is consumed, and returned as an error.
+    pub fn next_message(&mut self) -> Option<Result<String, Hl7ParseError>> {
+        // skip anything before the start of the next frame
+        if self.buffer.first() != Some(&START_BLOCK) {
+            self.scanned = 0;
+            match self.buffer.iter().position(|b| *b == START_BLOCK) {
+                Some(start) => {
+                    self.buffer.drain(..start);
+                }
+                None => {
+                    self.buffer.clear();
+                    return None;
+                }
+            }
+        }
+
+        let mut idx = self.scanned.max(1);
+        while idx < self.buffer.len() {
+            match self.buffer[idx] {
+                START_BLOCK => {
+                    warn!("Discarding {} bytes of an unfinished MLLP frame", idx);
+                    self.buffer.drain(..idx);
+                    idx = 1;
+                }
+                END_BLOCK if idx + 1 == self.buffer.len() => break, // waiting on the rest of the trailer
+                END_BLOCK if self.buffer[idx + 1] == CARRIAGE_RETURN => {
+                    self.scanned = 0;
+                    let frame: Vec<u8> = self.buffer.drain(..idx + 2).collect();
+                    return Some(unwrap(&frame).map(|message| message.to_string()));
+                }
+                _ => idx += 1,
+            }
+        }
+
+        self.scanned = idx;
+        None
+    }
+
+    /// Returns the number of buffered bytes that don't (yet) form a complete frame.
+    pub fn pending(&self) -> usize {
+        self.buffer.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ensure_well_formed_frame_round_trips() -> Result<(), Hl7ParseError> {
+        let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rPID|||555-44-4444";
+        let frame = wrap(hl7);
+
+        assert_eq!(frame[0], START_BLOCK);
+        assert_eq!(&frame[frame.len() - 2..], &[END_BLOCK, CARRIAGE_RETURN]);
+        assert_eq!(unwrap(&frame)?, hl7);
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_frame_missing_trailer_is_rejected() {
+        assert!(unwrap(b"\x0bMSH|^~\\&|GHH LAB").is_err());
+        assert!(unwrap(b"\x0bMSH|^~\\&|GHH LAB\x1c").is_err());
+        assert!(unwrap(b"MSH|^~\\&|GHH LAB\x1c\x0d").is_err());
+        assert!(unwrap(b"\x0b\xff\x1c\x0d").is_err());
+
+        let mut decoder = MllpDecoder::new();
+        decoder.push(b"\x0bMSH|^~\\&|GHH LAB");
+        assert!(decoder.next_message().is_none());
+        assert_eq!(decoder.pending(), 17);
+    }
+
+    #[test]
+    fn ensure_back_to_back_frames_are_split() {
+        let mut buffer = wrap("MSH|^~\\&|FIRST");
+        buffer.extend(wrap("MSH|^~\\&|SECOND"));
+
+        let mut decoder = MllpDecoder::new();
+        decoder.push(&buffer);
+        assert_eq!(decoder.next_message().unwrap().unwrap(), "MSH|^~\\&|FIRST");
+        assert_eq!(decoder.next_message().unwrap().unwrap(), "MSH|^~\\&|SECOND");
+        assert!(decoder.next_message().is_none());
+        assert_eq!(decoder.pending(), 0);
+    }
+
+    #[test]
+    fn ensure_frames_are_reassembled_from_chunks() {
+        let mut buffer = b"noise".to_vec();
+        buffer.extend(wrap("MSH|^~\\&|FIRST"));
+        buffer.extend(wrap("MSH|^~\\&|SECOND"));
+
+        // feed a byte at a time, as a slow socket might
+        let mut decoder = MllpDecoder::new();
+        let mut messages = Vec::new();
+        for byte in buffer.chunks(1) {
+            decoder.push(byte);
+            while let Some(message) = decoder.next_message() {
+                messages.push(message.unwrap());
+            }
+        }
+        assert_eq!(messages, vec!["MSH|^~\\&|FIRST", "MSH|^~\\&|SECOND"]);
+    }
+
+    #[test]
+    fn ensure_interrupted_frame_is_resynced() {
+        let mut decoder = MllpDecoder::new();
+        decoder.push(b"\x0bAAA\x0bMSH|^~\\&|SECOND\x1c\x0d");
+        assert_eq!(decoder.next_message().unwrap().unwrap(), "MSH|^~\\&|SECOND");
+        assert!(decoder.next_message().is_none());
+
+        // the new start block arrives after the partial frame has been scanned
+        decoder.push(b"\x0bAAA");
+        assert!(decoder.next_message().is_none());
+        decoder.push(b"\x1c\x0bMSH|^~\\&|THIRD\x1c");
+        assert!(decoder.next_message().is_none());
+        decoder.push(b"\x0d");
+        assert_eq!(decoder.next_message().unwrap().unwrap(), "MSH|^~\\&|THIRD");
+        assert_eq!(decoder.pending(), 0);
+    }
+
+    #[test]
+    fn ensure_large_frame_is_decoded_from_small_chunks() {
+        let hl7 = format!("MSH|^~\\&|GHH LAB\rOBX|1|ED|||{}", "A".repeat(512 * 1024));
+        let frame = wrap(&hl7);
+
+        // each push only scans the new bytes, rescanning the whole buffer would take minutes
+        let mut decoder = MllpDecoder::new();
+        for chunk in frame.chunks(16) {
+            assert!(decoder.next_message().is_none());
+            decoder.push(chunk);
+        }
+        assert_eq!(decoder.next_message().unwrap().unwrap(), hl7);
+        assert_eq!(decoder.pending(), 0);
+    }
+}
@@ -0,0 +1,419 @@
+use super::message::{Layout, Message};
+use super::redact::RedactPolicy;
+use super::separators::Separators;
+use super::*;
+use std::convert::TryFrom;
+use std::fmt::Display;
+
+/// An OwnedMessage holds its own copy of the HL7 source, rather than borrowing it like `Message` does.
+/// This allows it to outlive the buffer it was read from, and to be edited.
+///
+/// The parsed content is available via `as_message()`, which parses the owned source on demand, splitting it up exactly as the
+/// original message was (eg keeping the segment separator given to `Message::parse_with_separators()`).
+#[derive(Debug, PartialEq, Clone)]
+pub struct OwnedMessage {
+    source: String,
+    separators: Separators,
+    layout: Layout,
+    /// The separators for each segment where they vary (see `Message::parse_with_segment_separators()`), otherwise empty
+    segment_separators: Vec<Separators>,
+}
+
+impl OwnedMessage {
+    /// Returns the (possibly edited) HL7 source of this message.
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// Gets the delimiter information for this Message
+    pub fn get_separators(&self) -> Separators {
+        self.separators
+    }
+
+    /// Parses the owned source into a `Message` borrowing from this struct.
+    pub fn as_message(&self) -> Message<'_> {
+        Message::from_layout(
+            &self.source,
+            self.separators,
+            self.layout,
+            &self.segment_separators,
+        )
+    }
+
+    /// Decompresses and parses a gzipped HL7 message (eg from an archive), requires the `flate2` feature.
+    ///
+    /// The decompressed content must be valid UTF-8.  As the source is decompressed into a new buffer the result is always
+    /// owned, use `as_message()` to query it.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::owned_message::OwnedMessage;
+    /// # use std::io::Write;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// # let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    /// # encoder.write_all(b"MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4").unwrap();
+    /// # let gzipped = encoder.finish().unwrap();
+    /// let m = OwnedMessage::parse_gz(&gzipped[..])?;
+    /// assert_eq!(m.as_message().query("MSH.F2"), "GHH LAB");
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "flate2")]
+    pub fn parse_gz<R: std::io::Read>(reader: R) -> Result<OwnedMessage, Hl7ParseError> {
+        let mut source = String::new();
+        std::io::Read::read_to_string(&mut flate2::read::GzDecoder::new(reader), &mut source)
+            .map_err(|e| Hl7ParseError::Generic(format!("Failed to decompress message: {}", e)))?;
+        OwnedMessage::try_from(source)
+    }
+
+    /// Removes every segment with the given name (eg local `Z` segments before forwarding a message externally), returning the number removed.
+    /// The order and content of the remaining segments is preserved exactly.
+    ///
+    /// The MSH segment is required for the message to be valid, so is never removed.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::owned_message::OwnedMessage;
+    /// # use std::convert::TryFrom;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let mut m = OwnedMessage::try_from("MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ADT^A01|CNTRL-3456|P|2.4\rZPD|secret\rPID|1")?;
+    /// assert_eq!(m.remove_segments("ZPD"), 1);
+    /// assert_eq!(m.to_string(), "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ADT^A01|CNTRL-3456|P|2.4\rPID|1");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn remove_segments(&mut self, name: &str) -> usize {
+        if name == "MSH" {
+            return 0;
+        }
+
+        let message = self.as_message();
+        let (kept, removed): (Vec<_>, Vec<_>) = message
+            .segments
+            .iter()
+            .partition(|segment| segment.fields[0].source != name);
+        if removed.is_empty() {
+            return 0;
+        }
+
+        let source = kept
+            .iter()
+            .map(|segment| segment.source)
+            .collect::<Vec<&str>>()
+            .join(&self.separators.segment.to_string());
+        let segment_separators = if self.segment_separators.is_empty() {
+            Vec::new()
+        } else {
+            kept.iter()
+                .map(|segment| segment.fields[0].delims)
+                .collect()
+        };
+        let removed = removed.len();
+
+        self.source = source;
+        self.segment_separators = segment_separators;
+        removed
+    }
+
+    /// Blanks or hashes the fields covered by the policy in every segment (eg for sharing sample messages), returning the number of fields
+    /// changed.  Empty fields are left as they are, and the segment structure of the message is unchanged.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::owned_message::OwnedMessage;
+    /// # use rusthl7::redact::RedactPolicy;
+    /// # use std::convert::TryFrom;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let mut m = OwnedMessage::try_from("MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ADT^A01|CNTRL-3456|P|2.4\rPID|||555-44-4444||EVERYWOMAN^EVE")?;
+    /// assert_eq!(m.redact(&RedactPolicy::linkable_phi(b"keep this secret")), 2);
+    /// assert_eq!(m.as_message().query("PID-5"), "");
+    /// assert_ne!(m.as_message().query("PID-3"), "555-44-4444");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn redact(&mut self, policy: &RedactPolicy) -> usize {
+        let delims = self.separators;
+        let mut redacted = 0;
+
+        let segments: Vec<String> = self
+            .as_message()
+            .segments
+            .iter()
+            .map(|segment| {
+                let name = segment.fields[0].source;
+                let fields: Vec<String> = segment
+                    .fields
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, field)| {
+                        let number = segment.field_number(idx);
+                        match policy.action_for(name, number) {
+                            Some(_) if idx == 0 || field.source.is_empty() => {
+                                field.source.to_string()
+                            }
+                            Some(action) => {
+                                redacted += 1;
+                                policy
+                                    .redacted_value(action, field.source)
+                                    .unwrap_or_default()
+                            }
+                            None => field.source.to_string(),
+                        }
+                    })
+                    .collect();
+                fields.join(&segment.fields[0].delims.field.to_string())
+            })
+            .collect();
+
+        if redacted > 0 {
+            self.source = segments.join(&delims.segment.to_string());
+        }
+        redacted
+    }
+}
+
+impl TryFrom<String> for OwnedMessage {
+    type Error = Hl7ParseError;
+
+    /// Takes ownership of the source HL7 string, validating that it can be parsed.
+    fn try_from(source: String) -> Result<Self, Self::Error> {
+        let separators = Message::try_from(source.as_str())?.get_separators();
+        Ok(OwnedMessage {
+            source,
+            separators,
+            layout: Layout::default(),
+            segment_separators: Vec::new(),
+        })
+    }
+}
+
+impl<'a> From<Message<'a>> for OwnedMessage {
+    /// Copies the source of an already parsed message, along with how it was split up so `as_message()` gives back the same segments
+    /// and fields.  No re-validation is needed.
+    fn from(message: Message<'a>) -> Self {
+        let separators = message.get_separators();
+        let delims: Vec<Separators> = message
+            .segments
+            .iter()
+            .map(|segment| segment.fields.first().map_or(separators, |f| f.delims))
+            .collect();
+        let segment_separators = if delims.iter().all(|d| *d == separators) {
+            Vec::new()
+        } else {
+            delims
+        };
+
+        OwnedMessage {
+            source: message.source.to_string(),
+            separators,
+            layout: message.layout(),
+            segment_separators,
+        }
+    }
+}
+
+impl TryFrom<&str> for OwnedMessage {
+    type Error = Hl7ParseError;
+
+    /// Copies the source HL7 string, validating that it can be parsed.
+    fn try_from(source: &str) -> Result<Self, Self::Error> {
+        OwnedMessage::try_from(source.to_string())
+    }
+}
+
+impl Display for OwnedMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_options::ParseOptions;
+    use crate::redact::RedactAction;
+
+    #[test]
+    fn ensure_owned_message_outlives_its_input() -> Result<(), Hl7ParseError> {
+        let buffer = String::from("MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rPID|||555-44-4444||EVERYWOMAN^EVE");
+        let owned = Message::try_from(buffer.as_str())?.into_owned();
+        drop(buffer);
+
+        // owned messages are `'static` and `Send`, so can be handed off to another thread
+        let name = std::thread::spawn(move || owned.as_message().query("PID-5").to_string())
+            .join()
+            .unwrap();
+        assert_eq!(name, "EVERYWOMAN^EVE");
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_segments_are_removed() -> Result<(), Hl7ParseError> {
+        let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rPID|||555-44-4444\rOBR|1|845439^GHH OE\rOBX|1|SN|1554-5^GLUCOSE||^182\rNTE|1||Fasting\rOBX|2|NM|2951-2^SODIUM||140\r";
+        let mut msg = OwnedMessage::try_from(hl7)?;
+
+        assert_eq!(msg.remove_segments("OBX"), 2);
+        assert_eq!(
+            msg.to_string(),
+            "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rPID|||555-44-4444\rOBR|1|845439^GHH OE\rNTE|1||Fasting\r"
+        );
+        assert_eq!(msg.as_message().segments_by_name("OBX").count(), 0);
+        assert_eq!(msg.as_message().query("NTE.F3"), "Fasting");
+
+        // nothing left to remove
+        assert_eq!(msg.remove_segments("OBX"), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_phi_is_redacted() -> Result<(), Hl7ParseError> {
+        let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rPID|||555-44-4444||EVERYWOMAN^EVE^E^^^^L|JONES|19620320|F|||153 FERNWOOD DR.^^STATESVILLE^OH^35292||(206)3345232|(206)752-121||||AC555444444||67-A4335^OH^20030520\rOBR|1|845439^GHH OE|1045813^GHH LAB|15545^GLUCOSE\rOBX|1|SN|1554-5^GLUCOSE||^182|mg/dl\r";
+        let mut msg = OwnedMessage::try_from(hl7)?;
+        let other = OwnedMessage::try_from(hl7)?;
+
+        assert_eq!(msg.redact(&RedactPolicy::default_phi()), 9);
+        assert!(!msg.as_str().contains("EVERYWOMAN"));
+        assert!(!msg.as_str().contains("19620320"));
+        assert!(!msg.as_str().contains("FERNWOOD"));
+
+        let redacted = msg.as_message();
+        let original = other.as_message();
+        assert_eq!(redacted.outline().len(), original.outline().len());
+        for (r, o) in redacted.segments.iter().zip(original.segments.iter()) {
+            assert_eq!(r.fields.len(), o.fields.len());
+        }
+
+        // untouched segments are identical
+        assert_eq!(redacted.query("MSH"), original.query("MSH"));
+        assert_eq!(redacted.query("OBR"), original.query("OBR"));
+        assert_eq!(redacted.query("OBX-5"), "^182");
+
+        // identifiers are blanked by default
+        assert_eq!(redacted.query("PID-5"), "");
+        assert_eq!(redacted.query("PID-3"), "");
+        assert_eq!(redacted.query("PID-18"), "");
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_identifiers_are_hashed_with_the_secret() -> Result<(), Hl7ParseError> {
+        let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rPID|||555-44-4444||EVERYWOMAN^EVE";
+        let redact = |policy: &RedactPolicy| -> Result<String, Hl7ParseError> {
+            let mut msg = OwnedMessage::try_from(hl7)?;
+            msg.redact(policy);
+            Ok(msg.as_message().query("PID-3").to_string())
+        };
+
+        // hashed consistently, so can still be correlated
+        let hashed = redact(&RedactPolicy::linkable_phi(b"secret"))?;
+        assert_eq!(hashed.len(), 32);
+        assert_ne!(hashed, "555-44-4444");
+        assert_eq!(redact(&RedactPolicy::linkable_phi(b"secret"))?, hashed);
+
+        // but can't be reproduced without the secret
+        assert_ne!(redact(&RedactPolicy::linkable_phi(b"other"))?, hashed);
+
+        // and hashing without a secret fails closed
+        let policy = RedactPolicy::default().with("PID", 3, RedactAction::Hash);
+        assert_eq!(redact(&policy)?, "");
+        assert!(format!("{:?}", RedactPolicy::linkable_phi(b"secret")).contains("<redacted>"));
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_msh_fields_are_numbered_as_per_the_spec() -> Result<(), Hl7ParseError> {
+        let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4";
+        let mut msg = OwnedMessage::try_from(hl7)?;
+
+        let policy = RedactPolicy::default().with("MSH", 4, RedactAction::Blank);
+        assert_eq!(msg.redact(&policy), 1);
+        assert_eq!(
+            msg.as_str(),
+            "MSH|^~\\&|GHH LAB||GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4"
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "flate2")]
+    #[test]
+    fn ensure_gzipped_message_is_parsed() -> Result<(), Hl7ParseError> {
+        use flate2::{write::GzEncoder, Compression};
+        use std::io::Write;
+
+        let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rPID|||555-44-4444";
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(hl7.as_bytes()).unwrap();
+        let gzipped = encoder.finish().unwrap();
+
+        let msg = OwnedMessage::parse_gz(&gzipped[..])?;
+        assert_eq!(msg.as_str(), hl7);
+        assert_eq!(msg.as_message().query("PID.F3"), "555-44-4444");
+
+        // not gzipped at all
+        assert!(matches!(
+            OwnedMessage::parse_gz(hl7.as_bytes()),
+            Err(Hl7ParseError::Generic(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_msh_is_never_removed() -> Result<(), Hl7ParseError> {
+        let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rPID|||555-44-4444";
+        let mut msg = OwnedMessage::try_from(hl7)?;
+
+        assert_eq!(msg.remove_segments("MSH"), 0);
+        assert_eq!(msg.as_str(), hl7);
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_owned_copy_keeps_the_original_layout() -> Result<(), Hl7ParseError> {
+        // no MSH to read separators from
+        let separators = Separators::default();
+        let owned = Message::parse_with_separators("PID|||555-44-4444", separators)?.into_owned();
+        assert_eq!(owned.as_message().query("PID-3"), "555-44-4444");
+
+        // split on exactly '\n', so the '\r' stays in OBX-5
+        let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||MDM^T02|CNTRL-3456|P|2.4\nOBX|1|TX|||a\rb\nZPD|1";
+        let separators = Separators {
+            segment: '\n',
+            ..Separators::default()
+        };
+        let mut owned = Message::parse_with_separators(hl7, separators)?.into_owned();
+        assert_eq!(owned.as_message().segments.len(), 3);
+        assert_eq!(owned.as_message().query("OBX-5"), "a\rb");
+        assert_eq!(owned.remove_segments("ZPD"), 1);
+        assert_eq!(owned.as_message().segments.len(), 2);
+        assert_eq!(owned.as_message().query("OBX-5"), "a\rb");
+
+        let options = ParseOptions {
+            trim_whitespace: true,
+            ..Default::default()
+        };
+        let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ADT^A01|CNTRL-3456|P|2.4\rPID|||  555-44-4444  ";
+        let owned = Message::parse_with_options(hl7, &options)?.into_owned();
+        assert_eq!(owned.as_message().query("PID-3"), "555-44-4444");
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_owned_copy_keeps_segment_separators() -> Result<(), Hl7ParseError> {
+        let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rZLG#LEGACY#42\rZPD|1";
+        let m = Message::parse_with_segment_separators(hl7, |line, msh| {
+            if line.starts_with("ZLG") {
+                Separators { field: '#', ..msh }
+            } else {
+                msh
+            }
+        })?;
+
+        let mut owned = m.into_owned();
+        assert_eq!(owned.as_message().query("ZLG.F2"), "42");
+        assert_eq!(owned.remove_segments("MSH"), 0);
+        assert_eq!(owned.remove_segments("ZPD"), 1);
+        assert_eq!(owned.as_message().query("ZLG.F2"), "42");
+        Ok(())
+    }
+}
@@ -0,0 +1,280 @@
+use crate::{message::Message, separators::Separators, Hl7ParseError};
+use log::warn;
+use std::borrow::Cow;
+
+/// Options controlling how lenient parsing is with malformed input.
+/// The default is strict and leaves values untouched, matching the behaviour of `Message::try_from()`.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct ParseOptions {
+    /// Attempt to correct known classes of malformed messages before parsing, logging a warning for each repair made.
+    /// Guessing what a sender meant is risky, so this is off by default.  Repairs are made by `apply_repairs()`, or
+    /// `MshSegment::parse_with_options()` when parsing a MSH on its own.
+    ///
+    /// Currently handles:
+    /// - A MSH segment missing the field separator (MSH-1) before the encoding chars (MSH-2), eg `MSH^~\&|...`
+    pub repair: bool,
+
+    /// Reject any segment id that isn't uppercase (eg `pid` or `Pid`) with `Hl7ParseError::InvalidSegmentId`, as the spec requires.
+    /// Intended for conformance testing a sender, rather than tolerating sloppy input.
+    pub require_uppercase_segment_ids: bool,
+
+    /// Parse each segment that has a typed equivalent (eg MSH, PID or OBX) with `Segment::typed()`, rejecting the message if any fail
+    /// (eg a required field is missing).  Intended, like `require_uppercase_segment_ids`, for checking a sender's conformance.
+    pub validate_segments: bool,
+
+    /// Trim surrounding whitespace from field values and each of their repeats, components and subcomponents, for senders that pad
+    /// fields (eg `OBX|1 | SN |`).  Segment names and the segment source are left as-is.  See `Field::parse_with_options()`.
+    pub trim_whitespace: bool,
+
+    /// Only split each field into repeats, components and subcomponents when they're first needed (eg via `Field::components()` or
+    /// indexing), rather than as it's parsed.  Much cheaper where most fields are only read whole with `value()`, but the deprecated
+    /// public `Field::repeats`, `components` and `subcomponents` are left empty, so code still reading those needs the default.
+    pub lazy_fields: bool,
+}
+
+impl ParseOptions {
+    /// Applies any enabled repairs to the source message, ready for parsing.  If nothing needed fixing
+    /// the original slice is returned as-is without allocating.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::message::Message;
+    /// # use rusthl7::parse_options::ParseOptions;
+    /// # use std::convert::TryFrom;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let options = ParseOptions { repair: true, ..Default::default() };
+    /// let source = options.apply_repairs("MSH^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4");
+    /// let m = Message::try_from(&*source)?;
+    /// assert_eq!(m.get_separators().field, '|');
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn apply_repairs<'a>(&self, source: &'a str) -> Cow<'a, str> {
+        if !self.repair {
+            return Cow::Borrowed(source);
+        }
+
+        match repair_missing_field_separator(source) {
+            Some(repaired) => {
+                warn!("MSH segment is missing the field separator before the encoding characters, inserting it");
+                Cow::Owned(repaired)
+            }
+            None => Cow::Borrowed(source),
+        }
+    }
+
+    /// Checks a parsed message against the enabled strictness options, returning the first violation found (wrapped in
+    /// `Hl7ParseError::AtSegment`).
+    pub(crate) fn check(&self, message: &Message) -> Result<(), Hl7ParseError> {
+        if self.require_uppercase_segment_ids {
+            for (index, segment) in message.segments.iter().enumerate() {
+                let id = segment.fields[0].source;
+                if !id
+                    .chars()
+                    .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+                {
+                    return Err(
+                        Hl7ParseError::InvalidSegmentId(id.to_string()).at_segment(index, id)
+                    );
+                }
+            }
+        }
+        if self.validate_segments {
+            for (index, segment) in message.segments.iter().enumerate() {
+                if let Err(e) = segment.typed() {
+                    return Err(e.at_segment(index, segment.fields[0].source));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Detects a MSH with the field separator missing (`MSH^~\&|...`), returning the corrected message (`MSH|^~\&|...`) if so.
+fn repair_missing_field_separator(source: &str) -> Option<String> {
+    let delims = missing_field_separator(source)?;
+    Some(format!("MSH{}{}", delims.field, &source[3..]))
+}
+
+/// Detects a MSH with the field separator missing (`MSH^~\&|...`), returning the separators it should have declared if so.
+///
+/// A well formed MSH has the same char either side of the encoding chars, whether that's the usual four (`MSH|^~\&|`) or five with
+/// the v2.7 truncation char (`MSH|^~\&#|`).  If instead we find four distinct punctuation chars after `MSH` followed by a fifth
+/// distinct one, it's far more likely that fifth char is the field separator than that the encoding chars are in an odd order.
+pub(crate) fn missing_field_separator(source: &str) -> Option<Separators> {
+    let header: Vec<char> = source.strip_prefix("MSH")?.chars().take(7).collect();
+    if header.len() < 6 {
+        return None; // too short to tell
+    }
+    let wrapped = |len: usize| header.get(len + 1) == Some(&header[0]);
+    if wrapped(4) || (wrapped(5) && header[5].is_ascii_punctuation()) {
+        return None; // the encoding chars are correctly wrapped in field separators
+    }
+
+    let candidates = &header[..5];
+    let all_distinct_punctuation = candidates
+        .iter()
+        .enumerate()
+        .all(|(i, c)| c.is_ascii_punctuation() && !candidates[..i].contains(c));
+    if !all_distinct_punctuation {
+        return None;
+    }
+
+    Some(Separators {
+        segment: '\r',
+        field: header[4],
+        component: header[0],
+        repeat: header[1],
+        escape_char: header[2],
+        subcomponent: header[3],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn ensure_strict_by_default() {
+        let source = "MSH^~\\&|GHH LAB|ELAB-3";
+        let options = ParseOptions::default();
+        assert!(matches!(options.apply_repairs(source), Cow::Borrowed(s) if s == source));
+    }
+
+    #[test]
+    fn ensure_missing_field_separator_is_repaired() -> Result<(), Hl7ParseError> {
+        let options = ParseOptions {
+            repair: true,
+            ..Default::default()
+        };
+        let source = options.apply_repairs("MSH^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rOBR|segment^sub&segment");
+        assert_eq!(
+            source,
+            "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rOBR|segment^sub&segment"
+        );
+
+        let msg = Message::try_from(&*source)?;
+        assert_eq!(msg.get_separators(), Separators::default());
+        assert_eq!(msg.query("OBR.F1.C2.S1"), "sub");
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_well_formed_messages_are_not_repaired() {
+        let options = ParseOptions {
+            repair: true,
+            ..Default::default()
+        };
+        for source in [
+            "MSH|^~\\&|GHH LAB|ELAB-3",
+            "MSH#@~\\&#GHH LAB#ELAB-3",
+            "MSH|^~\\&",
+            "MSH|^~\\&#|GHH LAB|ELAB-3",
+            "MSH|^~\\&#||ELAB-3",
+            "MSH|^~",
+            "",
+        ] {
+            assert!(matches!(options.apply_repairs(source), Cow::Borrowed(_)));
+        }
+    }
+
+    #[test]
+    fn ensure_uppercase_segment_ids_are_accepted() -> Result<(), Hl7ParseError> {
+        let options = ParseOptions {
+            require_uppercase_segment_ids: true,
+            ..Default::default()
+        };
+        let msg = Message::parse_with_options(
+            "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rPV1|1\r",
+            &options,
+        )?;
+        assert_eq!(msg.query("PV1.F1"), "1");
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_whitespace_is_only_trimmed_when_asked() -> Result<(), Hl7ParseError> {
+        let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rOBX|1 | SN | 1554-5 ^ GLUCOSE ||  182 ";
+
+        let untrimmed = Message::parse_with_options(hl7, &ParseOptions::default())?;
+        assert_eq!(untrimmed.query("OBX-2"), " SN ");
+        assert_eq!(untrimmed.query("OBX-3.2"), " GLUCOSE ");
+        assert_eq!(untrimmed.query("OBX-5"), "  182 ");
+
+        let options = ParseOptions {
+            trim_whitespace: true,
+            ..Default::default()
+        };
+        let trimmed = Message::parse_with_options(hl7, &options)?;
+        assert_eq!(trimmed.query("OBX-1"), "1");
+        assert_eq!(trimmed.query("OBX-2"), "SN");
+        assert_eq!(trimmed.query("OBX-3"), "1554-5 ^ GLUCOSE");
+        assert_eq!(trimmed.query("OBX-3.1"), "1554-5");
+        assert_eq!(trimmed.query("OBX-3.2"), "GLUCOSE");
+        assert_eq!(trimmed.query("OBX-5"), "182");
+        assert_eq!(trimmed.segments[1].source, untrimmed.segments[1].source);
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_trimmed_messages_clone_trimmed() -> Result<(), Hl7ParseError> {
+        let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rOBX|1 | SN | 1554-5 ^ GLUCOSE ||  182 ";
+        let options = ParseOptions {
+            trim_whitespace: true,
+            ..Default::default()
+        };
+        let trimmed = Message::parse_with_options(hl7, &options)?;
+
+        let dolly = trimmed.clone();
+        assert_eq!(dolly, trimmed);
+        assert_eq!(dolly.query("OBX-3.2"), "GLUCOSE");
+        assert_eq!(dolly.query("OBX-5"), "182");
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_typed_segments_are_only_validated_when_asked() {
+        // the OBX is missing OBX-11, which is required
+        let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rPID|||555-44-4444||EVERYWOMAN^EVE\rOBX|1|NM|1554-5^GLUCOSE||182\rNTE|1";
+        assert!(Message::parse_with_options(hl7, &ParseOptions::default()).is_ok());
+
+        let options = ParseOptions {
+            validate_segments: true,
+            ..Default::default()
+        };
+        let err = Message::parse_with_options(hl7, &options).unwrap_err();
+        assert!(matches!(
+            &err,
+            Hl7ParseError::AtSegment { index: 2, segment, .. } if segment == "OBX"
+        ));
+        assert!(matches!(
+            err.root_cause(),
+            Hl7ParseError::MissingRequiredValue { field: Some(f) } if f == "OBX-11"
+        ));
+    }
+
+    #[test]
+    fn ensure_lowercase_segment_ids_are_rejected() {
+        let hl7 =
+            "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rpid|1";
+        let options = ParseOptions {
+            require_uppercase_segment_ids: true,
+            ..Default::default()
+        };
+        match Message::parse_with_options(hl7, &options) {
+            Err(Hl7ParseError::AtSegment {
+                index,
+                segment,
+                source,
+            }) => {
+                assert_eq!((index, segment.as_str()), (1, "pid"));
+                assert!(matches!(*source, Hl7ParseError::InvalidSegmentId(id) if id == "pid"));
+            }
+            _ => panic!("Expected InvalidSegmentId at segment 1"),
+        }
+
+        // allowed when not enforced
+        assert!(Message::parse_with_options(hl7, &ParseOptions::default()).is_ok());
+    }
+}
@@ -0,0 +1,176 @@
+/*!
+Minimal validation of a message's segment cardinality (eg exactly one MSH, at least one OBR), see `Message::validate()`.
+
+Only segment counts are checked, the order of segments and their contents are out of scope here.
+*/
+
+use crate::groups;
+use crate::message::Message;
+use std::fmt::Display;
+
+/// The number of times a segment must occur in a message, `max` of `None` meaning unbounded.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SegmentCardinality {
+    pub segment: String,
+    pub min: usize,
+    pub max: Option<usize>,
+}
+
+/// The segments required in a message, and how often they may occur.  Segments not listed may occur any number of times.
+/// ## Example:
+/// ```
+/// # use rusthl7::profile::MessageProfile;
+/// let profile = MessageProfile::new()
+///     .with("MSH", 1, Some(1))
+///     .with("ZPD", 1, None); // at least one
+/// assert_eq!(profile.segments.len(), 2);
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct MessageProfile {
+    pub segments: Vec<SegmentCardinality>,
+}
+
+impl MessageProfile {
+    /// Creates an empty profile, which any message satisfies.
+    pub fn new() -> MessageProfile {
+        MessageProfile::default()
+    }
+
+    /// Adds a segment to the profile, which must occur at least `min` and at most `max` (if given) times.
+    pub fn with(mut self, segment: &str, min: usize, max: Option<usize>) -> MessageProfile {
+        self.segments.push(SegmentCardinality {
+            segment: segment.to_string(),
+            min,
+            max,
+        });
+        self
+    }
+
+    /// Returns the built in profile for the given message structure (eg `ADT_A01`, or a trigger event sharing its structure such as
+    /// `ADT_A04`), if there is one.  Profiles are derived from the structures `Message::groups()` knows about, currently `ACK`, `ADT_A01`
+    /// and `ORU_R01`, and list every segment in the structure.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::profile::MessageProfile;
+    /// let profile = MessageProfile::for_structure("ADT_A01").unwrap();
+    /// assert!(profile.segments.iter().any(|s| s.segment == "PV1" && s.min == 1 && s.max == Some(1)));
+    /// assert!(MessageProfile::for_structure("ADT_A17").is_none()); // two PID/PV1 pairs, so not held to the A01 profile
+    /// ```
+    pub fn for_structure(structure: &str) -> Option<MessageProfile> {
+        let bounds = groups::segment_bounds(structure)?;
+        Some(
+            bounds
+                .iter()
+                .fold(MessageProfile::new(), |profile, (segment, min, max)| {
+                    profile.with(segment, *min, *max)
+                }),
+        )
+    }
+
+    /// Returns the built in profile for the message's structure, from MSH-9.3 where it's given, otherwise inferred from MSH-9.1/9.2 (see
+    /// `MessageType::structure_or_inferred()`).  See `for_structure()`.
+    pub fn for_message(message: &Message) -> Option<MessageProfile> {
+        MessageProfile::for_structure(&message.message_type()?.structure_or_inferred())
+    }
+
+    /// Checks the given segment names (in any order) against the profile, returning every bound that was violated.
+    pub(crate) fn check<'a>(
+        &self,
+        names: impl Iterator<Item = &'a str> + Clone,
+    ) -> Vec<ValidationError> {
+        self.segments
+            .iter()
+            .filter_map(|rule| {
+                let found = names.clone().filter(|n| *n == rule.segment).count();
+                let too_many = matches!(rule.max, Some(max) if found > max);
+                if found >= rule.min && !too_many {
+                    None
+                } else {
+                    Some(ValidationError {
+                        segment: rule.segment.clone(),
+                        found,
+                        min: rule.min,
+                        max: rule.max,
+                    })
+                }
+            })
+            .collect()
+    }
+}
+
+/// A segment that occurred more or fewer times than a `MessageProfile` allows, as returned from `Message::validate()`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ValidationError {
+    pub segment: String,
+    /// The number of times the segment occurred in the message
+    pub found: usize,
+    pub min: usize,
+    pub max: Option<usize>,
+}
+
+impl Display for ValidationError {
+    /// Formats as eg `PID occurs 0 times, expected at least 1`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} occurs {} times, expected ", self.segment, self.found)?;
+        match self.max {
+            Some(max) if max == self.min => write!(f, "exactly {}", max),
+            Some(max) if self.found > max => write!(f, "at most {}", max),
+            _ => write!(f, "at least {}", self.min),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Hl7ParseError;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn ensure_bounds_are_checked() {
+        let profile = MessageProfile::new()
+            .with("MSH", 1, Some(1))
+            .with("OBX", 1, Some(2));
+
+        assert!(profile
+            .check(["MSH", "OBX", "OBX"].iter().copied())
+            .is_empty());
+
+        let errors = profile.check(["MSH", "MSH", "OBX", "OBX", "OBX"].iter().copied());
+        assert_eq!(errors.len(), 2);
+        assert_eq!(
+            errors[0].to_string(),
+            "MSH occurs 2 times, expected exactly 1"
+        );
+        assert_eq!(
+            errors[1].to_string(),
+            "OBX occurs 3 times, expected at most 2"
+        );
+
+        let errors = profile.check(["MSH"].iter().copied());
+        assert_eq!(
+            errors[0].to_string(),
+            "OBX occurs 0 times, expected at least 1"
+        );
+    }
+
+    #[test]
+    fn ensure_built_in_profiles_are_found() -> Result<(), Hl7ParseError> {
+        let adt = MessageProfile::for_structure("ADT_A01").unwrap();
+        assert!(adt
+            .segments
+            .iter()
+            .any(|s| s.segment == "PID" && s.min == 1));
+        assert_eq!(MessageProfile::for_structure("ADT_A04"), Some(adt));
+        assert_eq!(MessageProfile::for_structure("ZZZ"), None);
+
+        // ADT^A17 (swap patients) carries two PID/PV1 pairs, so mustn't be held to the A01 profile
+        let msg = Message::try_from("MSH|^~\\&|ADT1|GOOD HEALTH HOSPITAL|GHH LAB|GHH|200202150930||ADT^A17|CNTRL-3456|P|2.4\rEVN|A17|200202150930\rPID|||111\rPV1|1|I|W1\rPID|||222\rPV1|1|I|W2")?;
+        assert_eq!(MessageProfile::for_message(&msg), None);
+
+        let msg = Message::try_from("MSH|^~\\&|CATH|StJohn|AcmeHIS|StJohn|20061019172719||ACK^A01|MSGID12349876|P|2.3\rMSA|AA|MSGID12349876")?;
+        let ack = MessageProfile::for_message(&msg).unwrap();
+        assert!(msg.validate(&ack).is_ok());
+        Ok(())
+    }
+}
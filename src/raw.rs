@@ -0,0 +1,126 @@
+/*!
+A lightweight, zero allocation view over a HL7 message, for when only a handful of values are needed (eg routing on MSH-9/MSH-10).
+
+Unlike `Message`, nothing is parsed up front.  Each lookup scans the source, so for anything beyond a few values parsing a full `Message` will be faster.
+*/
+
+use super::separators::Separators;
+use super::*;
+
+/// A HL7 message that is only split into segments and fields as they're requested, returning slices of the source.
+/// ## Example:
+/// ```
+/// # use rusthl7::Hl7ParseError;
+/// # use rusthl7::raw::RawMessage;
+/// # fn main() -> Result<(), Hl7ParseError> {
+/// let m = RawMessage::new("MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rPID|||555-44-4444")?;
+/// assert_eq!(m.field("MSH", 9), Some("ORU^R01"));
+/// assert_eq!(m.field("MSH", 10), Some("CNTRL-3456"));
+/// assert_eq!(m.field("PID", 3), Some("555-44-4444"));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct RawMessage<'a> {
+    pub source: &'a str,
+    separators: Separators,
+}
+
+impl<'a> RawMessage<'a> {
+    /// Wraps the source, reading the separators from the MSH.  No other validation is done.
+    pub fn new(source: &'a str) -> Result<RawMessage<'a>, Hl7ParseError> {
+        let separators = Separators::from_msh(source)?;
+        Ok(RawMessage { source, separators })
+    }
+
+    /// Gets the delimiter information for this Message
+    pub fn get_separators(&self) -> Separators {
+        self.separators
+    }
+
+    /// Iterates the source of each segment in the message, accepting the same line endings as `Message::try_from()`.
+    pub fn segments(&self) -> impl Iterator<Item = &'a str> {
+        message::segment_lines(self.source, &self.separators)
+    }
+
+    /// Returns the source of the first segment with the given name.
+    pub fn segment(&self, name: &str) -> Option<&'a str> {
+        let field = self.separators.field;
+        self.segments()
+            .find(|s| s.split(field).next() == Some(name))
+    }
+
+    /// Returns the raw value of the given field in the first segment with the given name, or `None` if there's no such segment or field.
+    ///
+    /// Fields are numbered as per the spec, so `MSH-1` is the field separator itself and `MSH-9` the message type.  Field 0 is the segment name.
+    pub fn field(&self, segment: &str, index: usize) -> Option<&'a str> {
+        let source = self.segment(segment)?;
+        let mut fields = source.split(self.separators.field);
+
+        if segment == "MSH" {
+            // MSH-1 is the field separator, so isn't delimited like the other fields
+            match index {
+                0 => fields.next(),
+                1 => source.get(3..3 + self.separators.field.len_utf8()),
+                n => fields.nth(n - 1),
+            }
+        } else {
+            fields.nth(index)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HL7: &str = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rPID|||555-44-4444||EVERYWOMAN^EVE^E^^^^L\rOBX|1|NM|1554-5^GLUCOSE||182\rOBX|2|NM|2951-2^SODIUM||140";
+
+    #[test]
+    fn ensure_segments_are_iterated() -> Result<(), Hl7ParseError> {
+        let m = RawMessage::new(HL7)?;
+        let names: Vec<&str> = m.segments().map(|s| &s[..3]).collect();
+        assert_eq!(names, vec!["MSH", "PID", "OBX", "OBX"]);
+        assert_eq!(m.segment("OBX"), Some("OBX|1|NM|1554-5^GLUCOSE||182"));
+        assert_eq!(m.segment("ZZZ"), None);
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_fields_are_numbered_as_per_the_spec() -> Result<(), Hl7ParseError> {
+        let m = RawMessage::new(HL7)?;
+
+        assert_eq!(m.field("MSH", 0), Some("MSH"));
+        assert_eq!(m.field("MSH", 1), Some("|"));
+        assert_eq!(m.field("MSH", 2), Some("^~\\&"));
+        assert_eq!(m.field("MSH", 9), Some("ORU^R01"));
+        assert_eq!(m.field("MSH", 12), Some("2.4"));
+        assert_eq!(m.field("MSH", 13), None);
+
+        assert_eq!(m.field("PID", 0), Some("PID"));
+        assert_eq!(m.field("PID", 5), Some("EVERYWOMAN^EVE^E^^^^L"));
+        assert_eq!(m.field("OBX", 5), Some("182")); // first OBX
+        assert_eq!(m.field("ZZZ", 1), None);
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_values_match_the_full_parser() -> Result<(), Hl7ParseError> {
+        let raw = RawMessage::new(HL7)?;
+        let msg = crate::message::Message::parse(HL7)?;
+
+        for path in ["MSH-3", "MSH-9", "MSH-10", "PID-3", "PID-5", "OBX-3"] {
+            let (segment, field) = path.split_once('-').unwrap();
+            assert_eq!(
+                raw.field(segment, field.parse().unwrap()),
+                Some(msg.query(path))
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_missing_msh_is_rejected() {
+        assert!(RawMessage::new("PID|||555-44-4444").is_err());
+    }
+}
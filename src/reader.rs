@@ -0,0 +1,162 @@
+/*!
+Reads MLLP framed messages from a stream (eg a large capture file, or a socket), one message at a time.
+*/
+
+use super::mllp::MllpDecoder;
+use super::*;
+use std::io::{ErrorKind, Read};
+
+const READ_SIZE: usize = 8 * 1024;
+
+/// Iterates the messages in a stream of MLLP framed messages, reading from the underlying source only as needed, so input of any size
+/// can be processed in bounded memory.  See `mllp::MllpDecoder` for the framing rules.
+///
+/// Errors reading the source are returned as `Hl7ParseError::Io`, after which iteration stops.  Input ending part way through a frame
+/// is reported as an error, rather than silently dropped.
+/// ## Example:
+/// ```
+/// # use rusthl7::Hl7ParseError;
+/// # use rusthl7::reader::Hl7Reader;
+/// # fn main() -> Result<(), Hl7ParseError> {
+/// let capture: &[u8] = b"\x0bMSH|^~\\&|FIRST\x1c\x0d\x0bMSH|^~\\&|SECOND\x1c\x0d";
+/// let messages = Hl7Reader::new(capture).collect::<Result<Vec<String>, Hl7ParseError>>()?;
+/// assert_eq!(messages, vec!["MSH|^~\\&|FIRST", "MSH|^~\\&|SECOND"]);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Hl7Reader<R: Read> {
+    source: R,
+    decoder: MllpDecoder,
+    done: bool,
+}
+
+impl<R: Read> Hl7Reader<R> {
+    /// Creates a reader over the given source.  Reads are made in large blocks, so there's no need to wrap the source in a `BufReader`.
+    pub fn new(source: R) -> Hl7Reader<R> {
+        Hl7Reader {
+            source,
+            decoder: MllpDecoder::new(),
+            done: false,
+        }
+    }
+}
+
+impl<R: Read> Iterator for Hl7Reader<R> {
+    type Item = Result<String, Hl7ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut buffer = [0; READ_SIZE];
+
+        loop {
+            if let Some(message) = self.decoder.next_message() {
+                return Some(message);
+            }
+            if self.done {
+                return None;
+            }
+
+            match self.source.read(&mut buffer) {
+                Ok(0) => {
+                    self.done = true;
+                    if self.decoder.pending() > 0 {
+                        return Some(Err(Hl7ParseError::Generic(format!(
+                            "Input ended part way through a MLLP frame ({} bytes unread)",
+                            self.decoder.pending()
+                        ))));
+                    }
+                }
+                Ok(read) => self.decoder.push(&buffer[..read]),
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(Hl7ParseError::Io(e)));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mllp::wrap;
+
+    /// Returns at most `chunk` bytes per read, to split frames across reads
+    struct ChunkedReader<'a> {
+        data: &'a [u8],
+        chunk: usize,
+    }
+
+    impl<'a> Read for ChunkedReader<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let len = self.chunk.min(buf.len()).min(self.data.len());
+            buf[..len].copy_from_slice(&self.data[..len]);
+            self.data = &self.data[len..];
+            Ok(len)
+        }
+    }
+
+    #[test]
+    fn ensure_frames_split_across_reads_are_reassembled() {
+        let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rPID|||555-44-4444";
+        let mut data = Vec::new();
+        for _ in 0..3 {
+            data.extend(wrap(hl7));
+        }
+
+        for chunk in [1, 7, 64, READ_SIZE] {
+            let reader = Hl7Reader::new(ChunkedReader { data: &data, chunk });
+            let messages: Vec<String> = reader.map(|m| m.unwrap()).collect();
+            assert_eq!(messages, vec![hl7; 3], "chunk size {}", chunk);
+        }
+    }
+
+    #[test]
+    fn ensure_large_frames_are_read() {
+        let hl7 = format!(
+            "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rOBX|1|ED|||{}",
+            "A".repeat(4 * 1024 * 1024)
+        );
+        let mut data = wrap(&hl7);
+        data.extend(wrap("MSH|^~\\&|SECOND"));
+
+        let reader = Hl7Reader::new(ChunkedReader {
+            data: &data,
+            chunk: 1024,
+        });
+        let messages: Vec<String> = reader.map(|m| m.unwrap()).collect();
+        assert_eq!(messages, vec![hl7.as_str(), "MSH|^~\\&|SECOND"]);
+    }
+
+    #[test]
+    fn ensure_truncated_input_is_reported() {
+        let mut data = wrap("MSH|^~\\&|FIRST");
+        data.extend(b"\x0bMSH|^~\\&|SEC");
+
+        let mut reader = Hl7Reader::new(&data[..]);
+        assert_eq!(reader.next().unwrap().unwrap(), "MSH|^~\\&|FIRST");
+        assert!(matches!(
+            reader.next(),
+            Some(Err(Hl7ParseError::Generic(_)))
+        ));
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn ensure_io_errors_are_surfaced() {
+        struct FailingReader;
+        impl Read for FailingReader {
+            fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::new(ErrorKind::BrokenPipe, "boom"))
+            }
+        }
+
+        let mut reader = Hl7Reader::new(FailingReader);
+        match reader.next() {
+            Some(Err(Hl7ParseError::Io(e))) => assert_eq!(e.kind(), ErrorKind::BrokenPipe),
+            _ => panic!("Expected an IO error"),
+        }
+        assert!(reader.next().is_none());
+    }
+}
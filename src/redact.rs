@@ -0,0 +1,121 @@
+/*!
+Policies for de-identifying messages (eg before sharing samples with a vendor), see `OwnedMessage::redact()`.
+*/
+
+/// What to do with the content of a redacted field.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum RedactAction {
+    /// Remove the content entirely, leaving an empty field
+    Blank,
+    /// Replace the content with a keyed hash (HMAC-SHA256) of it, so the same value (eg a MRN) can still be correlated across messages
+    /// without revealing it.  The key is the policy's secret (see `RedactPolicy::with_secret()`), which must be kept private as anyone
+    /// holding it can recover guessable values by brute force.  Without a secret the field is blanked instead.
+    Hash,
+}
+
+/// The fields to redact, as `(segment name, HL7 field number, action)`.  Fields are numbered as per the spec, so `("PID", 5, ..)` is PID-5
+/// and `("MSH", 9, ..)` is the message type.
+/// ## Example:
+/// ```
+/// # use rusthl7::redact::{RedactAction, RedactPolicy};
+/// let policy = RedactPolicy::default_phi().with("ZPD", 1, RedactAction::Blank);
+/// assert!(policy.fields.contains(&("ZPD".to_string(), 1, RedactAction::Blank)));
+/// ```
+#[derive(PartialEq, Clone, Default)]
+pub struct RedactPolicy {
+    pub fields: Vec<(String, usize, RedactAction)>,
+    secret: Option<Vec<u8>>,
+}
+
+impl std::fmt::Debug for RedactPolicy {
+    /// Never writes out the secret, so policies can be logged safely.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RedactPolicy")
+            .field("fields", &self.fields)
+            .field("secret", &self.secret.as_ref().map(|_| "<redacted>"))
+            .finish()
+    }
+}
+
+impl RedactPolicy {
+    /// Adds a field to the policy.
+    pub fn with(mut self, segment: &str, field: usize, action: RedactAction) -> RedactPolicy {
+        self.fields.push((segment.to_string(), field, action));
+        self
+    }
+
+    /// Sets the secret key used for `RedactAction::Hash`.  Use a long random value, and the same one wherever hashed values need to be
+    /// correlated.
+    pub fn with_secret(mut self, secret: &[u8]) -> RedactPolicy {
+        self.secret = Some(secret.to_vec());
+        self
+    }
+
+    /// A starting point covering the common patient identifiers, names, dates of birth, addresses and contact details in the PID and NK1
+    /// segments, all of which are blanked.  Use `linkable_phi()` to hash the identifiers instead.
+    ///
+    /// This is **not** a guarantee that a message is de-identified: free text (eg NTE and OBX values) and local Z segments can contain
+    /// anything, so review what else your feeds carry and extend the policy to suit.
+    pub fn default_phi() -> RedactPolicy {
+        use RedactAction::Blank;
+        let fields: &[(&str, usize, RedactAction)] = &[
+            ("PID", 2, Blank),  // patient id
+            ("PID", 3, Blank),  // patient identifier list
+            ("PID", 4, Blank),  // alternate patient id
+            ("PID", 5, Blank),  // patient name
+            ("PID", 6, Blank),  // mother's maiden name
+            ("PID", 7, Blank),  // date of birth
+            ("PID", 9, Blank),  // patient alias
+            ("PID", 11, Blank), // address
+            ("PID", 13, Blank), // home phone
+            ("PID", 14, Blank), // business phone
+            ("PID", 18, Blank), // account number
+            ("PID", 19, Blank), // SSN
+            ("PID", 20, Blank), // driver's license
+            ("PID", 21, Blank), // mother's identifier
+            ("NK1", 2, Blank),  // name
+            ("NK1", 4, Blank),  // address
+            ("NK1", 5, Blank),  // phone
+            ("NK1", 6, Blank),  // business phone
+        ];
+
+        fields.iter().fold(
+            RedactPolicy::default(),
+            |policy, (segment, field, action)| policy.with(segment, *field, *action),
+        )
+    }
+
+    /// As per `default_phi()`, but hashing the patient identifiers (PID-2, 3, 4, 18 and 21) with the given secret rather than blanking
+    /// them, so records can still be linked.  See `RedactAction::Hash`.
+    pub fn linkable_phi(secret: &[u8]) -> RedactPolicy {
+        let mut policy = RedactPolicy::default_phi().with_secret(secret);
+        for (segment, field, action) in policy.fields.iter_mut() {
+            if segment == "PID" && IDENTIFIERS.contains(field) {
+                *action = RedactAction::Hash;
+            }
+        }
+        policy
+    }
+
+    /// Returns the action for the given field, if it's covered by this policy.
+    pub(crate) fn action_for(&self, segment: &str, field: usize) -> Option<RedactAction> {
+        self.fields
+            .iter()
+            .find(|(s, f, _)| s == segment && *f == field)
+            .map(|(_, _, action)| *action)
+    }
+
+    /// Returns the replacement for a field's content under the given action, or `None` to blank it.
+    pub(crate) fn redacted_value(&self, action: RedactAction, value: &str) -> Option<String> {
+        match (action, &self.secret) {
+            (RedactAction::Hash, Some(secret)) => {
+                let mac = hmac_sha256::HMAC::mac(value.as_bytes(), secret);
+                Some(hex::encode_upper(&mac[..16]))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The PID fields holding patient identifiers, which `linkable_phi()` hashes.
+const IDENTIFIERS: &[usize] = &[2, 3, 4, 18, 21];
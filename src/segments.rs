@@ -1,7 +1,31 @@
-use super::{fields::Field, separators::Separators, Hl7ParseError};
+use super::datatypes::{self, DataType};
+use super::{fields::Field, parse_options::ParseOptions, separators::Separators, Hl7ParseError};
+use std::convert::TryFrom;
 use std::fmt::Display;
 use std::ops::Index;
 
+pub mod al1;
+pub mod evn;
+pub mod msa;
+pub mod msh;
+pub mod obx;
+pub mod pid;
+pub mod pv1;
+pub mod qrd;
+pub mod rol;
+pub mod txa;
+
+use al1::Al1Segment;
+use evn::EvnSegment;
+use msa::MsaSegment;
+use msh::MshSegment;
+use obx::ObxSegment;
+use pid::PidSegment;
+use pv1::Pv1Segment;
+use qrd::QrdSegment;
+use rol::RolSegment;
+use txa::TxaSegment;
+
 /// A generic bag o' fields, representing an arbitrary segment.
 #[derive(Debug, PartialEq, Clone)]
 pub struct Segment<'a> {
@@ -12,24 +36,45 @@ pub struct Segment<'a> {
 
 impl<'a> Segment<'a> {
     /// Convert the given line of text into a Segment.
+    ///
+    /// This never panics on short input.  An empty (or whitespace only) line becomes a segment with a single empty (or whitespace) name
+    /// field, as that's what a trailing segment separator produces and messages need to tolerate those.
     pub fn parse<S: Into<&'a str>>(
         input: S,
         delims: &Separators,
     ) -> Result<Segment<'a>, Hl7ParseError> {
-        let input = input.into();
+        Ok(Segment::split(
+            input.into(),
+            delims,
+            &ParseOptions::default(),
+        ))
+    }
 
-        let fields: Result<Vec<Field<'a>>, Hl7ParseError> = input
+    /// Splits the line into fields as per the field level `options`, though the segment name is never trimmed.  `parse()` wraps this,
+    /// it's used directly where a `Result` would only need unwrapping.
+    pub(crate) fn split(
+        input: &'a str,
+        delims: &Separators,
+        options: &ParseOptions,
+    ) -> Segment<'a> {
+        let name_options = ParseOptions {
+            trim_whitespace: false,
+            ..*options
+        };
+        let fields = input
             .split(delims.field)
-            .map(|line| Field::parse(line, delims))
+            .enumerate()
+            .map(|(i, field)| {
+                let options = if i == 0 { &name_options } else { options };
+                Field::from_source(field, delims, options)
+            })
             .collect();
 
-        let fields = fields?;
-        let seg = Segment {
+        Segment {
             source: input,
             delim: delims.segment,
             fields,
-        };
-        Ok(seg)
+        }
     }
 
     /// Export source to str
@@ -38,41 +83,579 @@ impl<'a> Segment<'a> {
         self.source
     }
 
+    /// Returns the exact source line this segment was parsed from (without the segment separator).
+    #[inline]
+    pub fn source(&self) -> &'a str {
+        self.source
+    }
+
+    /// Returns the segment's name (eg `PID`), ie the value before the first field separator.
+    #[inline]
+    pub fn segment_name(&self) -> &'a str {
+        self.fields[0].value()
+    }
+
+    /// Returns the field with the given HL7 field number (so 3 is PID-3 on a PID), or `None` if the segment doesn't have that many fields.
+    ///
+    /// MSH fields are numbered as per the spec, so 9 is MSH-9 (the message type).  MSH-1 is the field separator, which isn't a field
+    /// in its own right so is always `None` here, use `Message::query("MSH-1")` for it.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::segments::Segment;
+    /// # use rusthl7::separators::Separators;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let pid = Segment::parse("PID|||555-44-4444||EVERYWOMAN^EVE", &Separators::default())?;
+    /// assert_eq!(pid.field(3).map(|f| f.value()), Some("555-44-4444"));
+    /// assert_eq!(pid.field(30), None);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn field(&self, one_based: usize) -> Option<&Field<'a>> {
+        match (self.fields[0].source, one_based) {
+            (_, 0) | ("MSH", 1) => None,
+            ("MSH", n) => self.fields.get(n - 1),
+            (_, n) => self.fields.get(n),
+        }
+    }
+
+    /// Returns the HL7 field number of `fields[index]`, the inverse of `field()`.
+    pub(crate) fn field_number(&self, index: usize) -> usize {
+        if self.fields[0].source == "MSH" {
+            index + 1
+        } else {
+            index
+        }
+    }
+
+    /// Reassembles the segment text from its fields (see `Field::rebuild()`), so a segment that's been modified can be re-emitted.
+    /// Present but empty fields are kept (including trailing ones), so for an unmodified segment this is always equal to `source`.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::segments::Segment;
+    /// # use rusthl7::separators::Separators;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let mut pid = Segment::parse("PID|||555-44-4444||", &Separators::default())?;
+    /// pid.fields[3].subcomponents_mut()[0][0][0] = "555-55-5555";
+    /// assert_eq!(pid.rebuild(), "PID|||555-55-5555||");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn rebuild(&self) -> String {
+        let separator = self.fields[0].delims.field.to_string();
+        self.fields
+            .iter()
+            .map(|f| f.rebuild())
+            .collect::<Vec<String>>()
+            .join(&separator)
+    }
+
+    /// As per `rebuild()`, but assembling the segment with different delimiters (see `Field::rebuild_with()`).  For a MSH the field
+    /// separator (MSH-1) and encoding chars (MSH-2) are rewritten to declare the new delimiters, so a whole message can be re-delimited
+    /// uniformly.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::segments::Segment;
+    /// # use rusthl7::separators::Separators;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let msh = Segment::parse("MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01", &Separators::default())?;
+    /// let hashes = Separators { field: '#', ..Separators::default() };
+    /// assert_eq!(msh.rebuild_with(&hashes), "MSH#^~\\&#GHH LAB#ELAB-3#GHH OE#BLDG4#200202150930##ORU^R01");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn rebuild_with(&self, delims: &Separators) -> String {
+        let separator = delims.field.to_string();
+        let fields = self.fields.iter().map(|f| f.rebuild_with(delims));
+        if self.segment_name() == "MSH" {
+            let encoding_chars = std::iter::once(delims.to_string());
+            std::iter::once("MSH".to_string())
+                .chain(encoding_chars)
+                .chain(fields.skip(2))
+                .collect::<Vec<String>>()
+                .join(&separator)
+        } else {
+            fields.collect::<Vec<String>>().join(&separator)
+        }
+    }
+
+    /// Returns the data type of the given field (by HL7 field number, so 5 is PID-5 on a PID) from the built in schema table, see `datatypes::field_datatype()`.
+    pub fn field_datatype(&self, field_number: usize) -> Option<DataType> {
+        datatypes::field_datatype(self.fields[0].source, field_number)
+    }
+
+    /// Returns the human readable name of a component (both 1-based HL7 numbers), eg PID-5.1 is "family name".
+    /// Returns `None` where the field isn't in the schema table, or its data type doesn't have that component.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::segments::Segment;
+    /// # use rusthl7::separators::Separators;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let pid = Segment::parse("PID|||555-44-4444||EVERYWOMAN^EVE", &Separators::default())?;
+    /// assert_eq!(pid.component_name(5, 2), Some("given name"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn component_name(
+        &self,
+        field_number: usize,
+        component_number: usize,
+    ) -> Option<&'static str> {
+        self.field_datatype(field_number)?
+            .component_name(component_number)
+    }
+
+    /// Interprets this segment as one of the strongly typed segments where we know how (based on the segment name),
+    /// otherwise it's returned as-is wrapped in `TypedSegment::Generic`.
+    ///
+    /// The segment name is matched leniently, ignoring surrounding whitespace (including a stray MLLP start block) and case, as some feeds
+    /// pad or lowercase their segment ids.  The source of the typed segment is unchanged.
+    pub fn typed(&self) -> Result<TypedSegment<'a>, Hl7ParseError> {
+        const TYPED_IDS: [&str; 10] = [
+            "AL1", "EVN", "MSA", "MSH", "OBX", "PID", "PV1", "QRD", "ROL", "TXA",
+        ];
+        let delims = &self.fields[0].delims;
+        let id = TYPED_IDS
+            .iter()
+            .find(|id| has_segment_id(Some(self.fields[0].source), id));
+
+        match id.copied().unwrap_or_default() {
+            "AL1" => Ok(TypedSegment::AL1(Al1Segment::parse(self.source, delims)?)),
+            "EVN" => Ok(TypedSegment::EVN(EvnSegment::parse(self.source, delims)?)),
+            "MSA" => Ok(TypedSegment::MSA(MsaSegment::parse(self.source, delims)?)),
+            "MSH" => Ok(TypedSegment::MSH(MshSegment::parse(self.source, delims)?)),
+            "OBX" => Ok(TypedSegment::OBX(ObxSegment::parse(self.source, delims)?)),
+            "PID" => Ok(TypedSegment::PID(PidSegment::parse(self.source, delims)?)),
+            "PV1" => Ok(TypedSegment::PV1(Pv1Segment::parse(self.source, delims)?)),
+            "QRD" => Ok(TypedSegment::QRD(QrdSegment::parse(self.source, delims)?)),
+            "ROL" => Ok(TypedSegment::ROL(RolSegment::parse(self.source, delims)?)),
+            "TXA" => Ok(TypedSegment::TXA(TxaSegment::parse(self.source, delims)?)),
+            _ => Ok(TypedSegment::Generic(self.clone())),
+        }
+    }
+
     /// Access Field as string reference
     pub fn query<'b, S>(&self, fidx: S) -> &'a str
     where
         S: Into<&'b str>,
     {
-        let fidx = fidx.into();
+        self.find(fidx.into()).map_or("", |(_, value)| value)
+    }
+
+    /// As per `query()`, but returns `None` rather than `""` for out-of-range or malformed indexes, and also returns the index of
+    /// the field the value came from.
+    pub(crate) fn find(&self, fidx: &str) -> Option<(usize, &'a str)> {
         let sections = fidx.split('.').collect::<Vec<&str>>();
+        let stringnum = sections[0]
+            .chars()
+            .filter(|c| c.is_ascii_digit())
+            .collect::<String>();
+        let idx: usize = stringnum.parse().ok()?;
+        let field = self.fields.get(idx)?;
 
         match sections.len() {
-            1 => {
-                let stringnum = sections[0]
-                    .chars()
-                    .filter(|c| c.is_digit(10))
-                    .collect::<String>();
-                let idx: usize = stringnum.parse().unwrap();
-                self[idx]
-            }
-            _ => {
-                let stringnum = sections[0]
-                    .chars()
-                    .filter(|c| c.is_digit(10))
-                    .collect::<String>();
-                let idx: usize = stringnum.parse().unwrap();
-                if idx > self.fields.len() - 1 {
-                    return "";
-                }
-                let field = &self.fields[idx];
-                let query = sections[1..].join(".");
+            1 => Some((idx, field.source)),
+            _ => Some((idx, field.find(&sections[1..].join("."))?)),
+        }
+    }
+}
 
-                field.query(&*query)
-            }
+/// Returns true if the (raw) segment id matches the expected one, ignoring surrounding whitespace (which includes the `0x0B` MLLP start
+/// block) and case, as used by `Segment::typed()` and the typed segment parsers.
+pub(crate) fn has_segment_id(raw: Option<&str>, id: &str) -> bool {
+    raw.is_some_and(|raw| raw.trim().eq_ignore_ascii_case(id))
+}
+
+/// Returns the human readable name of a segment id (eg `PID` is "Patient Identification"), for tooling such as editors and viewers.
+/// Covers the commonly used segments of v2.x, returning `None` for others (including local `Z` segments).
+/// ## Example:
+/// ```
+/// # use rusthl7::segments::segment_description;
+/// assert_eq!(segment_description("MSH"), Some("Message Header"));
+/// assert_eq!(segment_description("ZPD"), None);
+/// ```
+pub fn segment_description(id: &str) -> Option<&'static str> {
+    let description = match id {
+        "ACC" => "Accident",
+        "AL1" => "Patient Allergy Information",
+        "BHS" => "Batch Header",
+        "BTS" => "Batch Trailer",
+        "DG1" => "Diagnosis",
+        "DSC" => "Continuation Pointer",
+        "ERR" => "Error",
+        "EVN" => "Event Type",
+        "FHS" => "File Header",
+        "FT1" => "Financial Transaction",
+        "FTS" => "File Trailer",
+        "GT1" => "Guarantor",
+        "IN1" => "Insurance",
+        "IN2" => "Insurance Additional Information",
+        "MRG" => "Merge Patient Information",
+        "MSA" => "Message Acknowledgment",
+        "MSH" => "Message Header",
+        "NK1" => "Next of Kin / Associated Parties",
+        "NTE" => "Notes and Comments",
+        "OBR" => "Observation Request",
+        "OBX" => "Observation/Result",
+        "ORC" => "Common Order",
+        "PD1" => "Patient Additional Demographic",
+        "PID" => "Patient Identification",
+        "PR1" => "Procedures",
+        "PV1" => "Patient Visit",
+        "PV2" => "Patient Visit - Additional Information",
+        "QAK" => "Query Acknowledgment",
+        "QPD" => "Query Parameter Definition",
+        "QRD" => "Original-Style Query Definition",
+        "QRF" => "Original Style Query Filter",
+        "RCP" => "Response Control Parameter",
+        "RGS" => "Resource Group",
+        "ROL" => "Role",
+        "RXA" => "Pharmacy/Treatment Administration",
+        "RXE" => "Pharmacy/Treatment Encoded Order",
+        "RXO" => "Pharmacy/Treatment Order",
+        "RXR" => "Pharmacy/Treatment Route",
+        "SCH" => "Scheduling Activity Information",
+        "SFT" => "Software Segment",
+        "SPM" => "Specimen",
+        "TQ1" => "Timing/Quantity",
+        "TXA" => "Transcription Document Header",
+        _ => return None,
+    };
+    Some(description)
+}
+
+/// Returns the human readable name of a field (by HL7 field number, so `("PID", 5)` is PID-5, "Patient Name") of the segments we have
+/// typed representations for (see `TypedSegment`), returning `None` for other segments and fields.
+/// ## Example:
+/// ```
+/// # use rusthl7::segments::field_description;
+/// assert_eq!(field_description("PID", 5), Some("Patient Name"));
+/// assert_eq!(field_description("MSH", 9), Some("Message Type"));
+/// ```
+pub fn field_description(segment: &str, field_number: usize) -> Option<&'static str> {
+    let fields: &[&str] = match segment {
+        "AL1" => &[
+            "Set ID",
+            "Allergen Type Code",
+            "Allergen Code",
+            "Allergy Severity Code",
+            "Allergy Reaction Code",
+            "Identification Date",
+        ],
+        "EVN" => &[
+            "Event Type Code",
+            "Recorded Date/Time",
+            "Date/Time Planned Event",
+            "Event Reason Code",
+            "Operator ID",
+            "Event Occurred",
+            "Event Facility",
+        ],
+        "MSA" => &[
+            "Acknowledgment Code",
+            "Message Control ID",
+            "Text Message",
+            "Expected Sequence Number",
+            "Delayed Acknowledgment Type",
+            "Error Condition",
+        ],
+        "MSH" => &[
+            "Field Separator",
+            "Encoding Characters",
+            "Sending Application",
+            "Sending Facility",
+            "Receiving Application",
+            "Receiving Facility",
+            "Date/Time of Message",
+            "Security",
+            "Message Type",
+            "Message Control ID",
+            "Processing ID",
+            "Version ID",
+            "Sequence Number",
+            "Continuation Pointer",
+            "Accept Acknowledgment Type",
+            "Application Acknowledgment Type",
+            "Country Code",
+            "Character Set",
+            "Principal Language of Message",
+            "Alternate Character Set Handling Scheme",
+            "Message Profile Identifier",
+            "Sending Responsible Organization",
+            "Receiving Responsible Organization",
+            "Sending Network Address",
+            "Receiving Network Address",
+        ],
+        "OBX" => &[
+            "Set ID",
+            "Value Type",
+            "Observation Identifier",
+            "Observation Sub-ID",
+            "Observation Value",
+            "Units",
+            "References Range",
+            "Abnormal Flags",
+            "Probability",
+            "Nature of Abnormal Test",
+            "Observation Result Status",
+            "Effective Date of Reference Range",
+            "User Defined Access Checks",
+            "Date/Time of the Observation",
+            "Producer's ID",
+            "Responsible Observer",
+            "Observation Method",
+            "Equipment Instance Identifier",
+            "Date/Time of the Analysis",
+        ],
+        "PID" => &[
+            "Set ID",
+            "Patient ID",
+            "Patient Identifier List",
+            "Alternate Patient ID",
+            "Patient Name",
+            "Mother's Maiden Name",
+            "Date of Birth",
+            "Sex",
+            "Patient Alias",
+            "Race",
+            "Address",
+            "County Code",
+            "Phone Number Home",
+            "Phone Number Business",
+            "Primary Language",
+            "Marital Status",
+            "Religion",
+            "Patient Account Number",
+            "SSN Number",
+            "Driver's License Number",
+            "Mother's Identifier",
+            "Ethnic Group",
+            "Birth Place",
+            "Multiple Birth Indicator",
+            "Birth Order",
+            "Citizenship",
+            "Veterans Military Status",
+            "Nationality",
+            "Patient Death Date and Time",
+            "Patient Death Indicator",
+            "Identity Unknown Indicator",
+            "Identity Reliability Code",
+            "Last Update Date/Time",
+            "Last Update Facility",
+            "Species Code",
+            "Breed Code",
+            "Strain",
+            "Production Class Code",
+            "Tribal Citizenship",
+        ],
+        "PV1" => &[
+            "Set ID",
+            "Patient Class",
+            "Assigned Patient Location",
+            "Admission Type",
+            "Preadmit Number",
+            "Prior Patient Location",
+            "Attending Doctor",
+            "Referring Doctor",
+            "Consulting Doctor",
+            "Hospital Service",
+            "Temporary Location",
+            "Preadmit Test Indicator",
+            "Re-admission Indicator",
+            "Admit Source",
+            "Ambulatory Status",
+            "VIP Indicator",
+            "Admitting Doctor",
+            "Patient Type",
+            "Visit Number",
+            "Financial Class",
+            "Charge Price Indicator",
+            "Courtesy Code",
+            "Credit Rating",
+            "Contract Code",
+            "Contract Effective Date",
+            "Contract Amount",
+            "Contract Period",
+            "Interest Code",
+            "Transfer to Bad Debt Code",
+            "Transfer to Bad Debt Date",
+            "Bad Debt Agency Code",
+            "Bad Debt Transfer Amount",
+            "Bad Debt Recovery Amount",
+            "Delete Account Indicator",
+            "Delete Account Date",
+            "Discharge Disposition",
+            "Discharged to Location",
+            "Diet Type",
+            "Servicing Facility",
+            "Bed Status",
+            "Account Status",
+            "Pending Location",
+            "Prior Temporary Location",
+            "Admit Date/Time",
+            "Discharge Date/Time",
+            "Current Patient Balance",
+            "Total Charges",
+            "Total Adjustments",
+            "Total Payments",
+            "Alternate Visit ID",
+            "Visit Indicator",
+            "Other Healthcare Provider",
+        ],
+        "QRD" => &[
+            "Query Date/Time",
+            "Query Format Code",
+            "Query Priority",
+            "Query ID",
+            "Deferred Response Type",
+            "Deferred Response Date/Time",
+            "Quantity Limited Request",
+            "Who Subject Filter",
+            "What Subject Filter",
+            "What Department Data Code",
+            "What Data Code Value Qualifier",
+            "Query Results Level",
+        ],
+        "ROL" => &[
+            "Role Instance ID",
+            "Action Code",
+            "Role",
+            "Role Person",
+            "Role Begin Date/Time",
+            "Role End Date/Time",
+        ],
+        "TXA" => &[
+            "Set ID",
+            "Document Type",
+            "Document Content Presentation",
+            "Activity Date/Time",
+            "Primary Activity Provider",
+            "Origination Date/Time",
+            "Transcription Date/Time",
+            "Edit Date/Time",
+            "Originator",
+            "Assigned Document Authenticator",
+            "Transcriptionist",
+            "Unique Document Number",
+            "Parent Document Number",
+            "Placer Order Number",
+            "Filler Order Number",
+            "Unique Document File Name",
+            "Document Completion Status",
+            "Document Confidentiality Status",
+            "Document Availability Status",
+            "Document Storage Status",
+            "Document Change Reason",
+            "Authentication Person Time Stamp",
+            "Distributed Copies",
+        ],
+        _ => &[],
+    };
+
+    fields.get(field_number.checked_sub(1)?).copied()
+}
+
+/// Uniform access to a segment's fields by HL7 field number, including the individual repeats of repeating fields (eg the identifiers
+/// in PID-3).  Implemented by `Segment` and each of the typed segments, which only return the fields they model.
+/// ## Example:
+/// ```
+/// # use rusthl7::Hl7ParseError;
+/// # use rusthl7::segments::pid::PidSegment;
+/// # use rusthl7::segments::RepeatingSegment;
+/// # use rusthl7::separators::Separators;
+/// # fn main() -> Result<(), Hl7ParseError> {
+/// let pid = PidSegment::parse("PID|||555-44-4444^^^SSA~MRN-1^^^GHH||EVERYWOMAN^EVE", &Separators::default())?;
+/// assert_eq!(pid.field_repeats(3), vec!["555-44-4444^^^SSA", "MRN-1^^^GHH"]);
+/// # Ok(())
+/// # }
+/// ```
+pub trait RepeatingSegment<'a> {
+    /// Returns the field with the given HL7 field number (so 3 is PID-3), or `None` if it's absent.
+    fn field(&self, field_number: usize) -> Option<&Field<'a>>;
+
+    /// Returns the raw value of each repeat of the given field, or an empty vec if the field is absent.
+    fn field_repeats(&self, field_number: usize) -> Vec<&'a str> {
+        self.field(field_number)
+            .map_or_else(Vec::new, |f| f.repeats().to_vec())
+    }
+}
+
+impl<'a> RepeatingSegment<'a> for Segment<'a> {
+    fn field(&self, field_number: usize) -> Option<&Field<'a>> {
+        Segment::field(self, field_number)
+    }
+}
+
+/// A segment converted to the strongly typed representation for its segment name, if we have one.
+/// See `Segment::typed()`.
+#[allow(clippy::large_enum_variant)] // typed segments are all big bags of fields, boxing them buys us nothing
+#[derive(Debug, PartialEq, Clone)]
+pub enum TypedSegment<'a> {
+    AL1(Al1Segment<'a>),
+    EVN(EvnSegment<'a>),
+    MSA(MsaSegment<'a>),
+    MSH(MshSegment<'a>),
+    OBX(ObxSegment<'a>),
+    PID(PidSegment<'a>),
+    PV1(Pv1Segment<'a>),
+    QRD(QrdSegment<'a>),
+    ROL(RolSegment<'a>),
+    TXA(TxaSegment<'a>),
+    Generic(Segment<'a>),
+}
+
+impl<'a> TypedSegment<'a> {
+    /// Returns the exact source line the segment was parsed from, whichever variant it is.
+    /// Every segment type retains its source, so this is always available for logging, auditing and round-tripping.
+    pub fn source(&self) -> &'a str {
+        match self {
+            TypedSegment::AL1(s) => s.source,
+            TypedSegment::EVN(s) => s.source,
+            TypedSegment::MSA(s) => s.source,
+            TypedSegment::MSH(s) => s.source,
+            TypedSegment::OBX(s) => s.source,
+            TypedSegment::PID(s) => s.source,
+            TypedSegment::PV1(s) => s.source,
+            TypedSegment::QRD(s) => s.source,
+            TypedSegment::ROL(s) => s.source,
+            TypedSegment::TXA(s) => s.source,
+            TypedSegment::Generic(s) => s.source,
         }
     }
 }
 
+impl<'a> TryFrom<&'a str> for Segment<'a> {
+    type Error = Hl7ParseError;
+
+    /// Parses a single segment line.  An MSH line declares its own separators, which are used to parse it; any other segment is parsed
+    /// with the default separators, so use `Segment::parse()` for segments from a message with custom delimiters.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::segments::Segment;
+    /// # use std::convert::TryInto;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let segment: Segment = "PID|||555-44-4444||EVERYWOMAN^EVE".try_into()?;
+    /// assert_eq!(segment[5], "EVERYWOMAN^EVE");
+    ///
+    /// let msh: Segment = "MSH#@~\\$#GHH LAB#ELAB-3".try_into()?;
+    /// assert_eq!(msh.fields[2].value(), "GHH LAB");
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn try_from(source: &'a str) -> Result<Self, Self::Error> {
+        let delims = if source.starts_with("MSH") {
+            Separators::from_msh(source)?
+        } else {
+            Separators::default()
+        };
+        Segment::parse(source, &delims)
+    }
+}
+
 impl<'a> Display for Segment<'a> {
     /// Required for to_string() and other formatter consumers
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -80,6 +663,45 @@ impl<'a> Display for Segment<'a> {
     }
 }
 
+/// Serializes as `{"name": "PID", "fields": [...]}`, where `fields` excludes the segment name, so `fields[0]` is the first field of the
+/// segment (eg PID-1).  For MSH the field separator is included as MSH-1, and MSH-1/MSH-2 are emitted as plain strings (eg `"|"` and
+/// `"^~\\&"`) rather than being split on the separators they define.
+#[cfg(feature = "serde")]
+impl<'a> serde::Serialize for Segment<'a> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let mut state = serializer.serialize_struct("Segment", 2)?;
+        state.serialize_field("name", self.fields[0].source)?;
+        if self.fields[0].source == "MSH" {
+            state.serialize_field("fields", &MshFields(self))?;
+        } else {
+            state.serialize_field("fields", &self.fields[1..])?;
+        }
+        state.end()
+    }
+}
+
+/// The fields of an MSH segment as serialized, see `Segment`'s `Serialize` impl.
+#[cfg(feature = "serde")]
+struct MshFields<'s, 'a>(&'s Segment<'a>);
+
+#[cfg(feature = "serde")]
+impl<'s, 'a> serde::Serialize for MshFields<'s, 'a> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeSeq;
+        let fields = &self.0.fields;
+        let mut seq = serializer.serialize_seq(Some(fields.len()))?;
+        seq.serialize_element(&fields[0].delims.field.to_string())?;
+        if let Some(encoding_chars) = fields.get(1) {
+            seq.serialize_element(encoding_chars.source)?;
+        }
+        for field in fields.iter().skip(2) {
+            seq.serialize_element(field)?;
+        }
+        seq.end()
+    }
+}
+
 impl<'a> Index<usize> for Segment<'a> {
     type Output = &'a str;
     /// Access Field as string reference
@@ -95,7 +717,7 @@ impl<'a> Index<(usize, usize)> for Segment<'a> {
     type Output = &'a str;
     /// Access Field component as string reference
     fn index(&self, fidx: (usize, usize)) -> &Self::Output {
-        if fidx.0 > self.fields.len() - 1 || fidx.1 > self.fields[fidx.0].components.len() - 1 {
+        if fidx.0 > self.fields.len() - 1 || fidx.1 > self.fields[fidx.0].components().len() - 1 {
             return &"";
         }
         &self.fields[fidx.0][fidx.1]
@@ -107,8 +729,8 @@ impl<'a> Index<(usize, usize, usize)> for Segment<'a> {
     /// Access Field subcomponent as string reference
     fn index(&self, fidx: (usize, usize, usize)) -> &Self::Output {
         if fidx.0 > self.fields.len() - 1
-            || fidx.1 > self.fields[fidx.0].components.len() - 1
-            || fidx.2 > self.fields[fidx.0].subcomponents[fidx.1].len() - 1
+            || fidx.1 > self.fields[fidx.0].components().len() - 1
+            || fidx.2 > self.fields[fidx.0].subcomponents()[fidx.1].len() - 1
         {
             return &"";
         }
@@ -125,9 +747,12 @@ impl<'a> Index<&str> for Segment<'a> {
         let sections = fidx.split('.').collect::<Vec<&str>>();
         let stringnum = sections[0]
             .chars()
-            .filter(|c| c.is_digit(10))
+            .filter(|c| c.is_ascii_digit())
             .collect::<String>();
-        let mut idx: usize = stringnum.parse().unwrap();
+        let mut idx: usize = match stringnum.parse() {
+            Ok(idx) => idx,
+            Err(_) => return &"",
+        };
         // MSH segment has an off-by-one problem in that the first
         // field separator is considered to be a field in the spec
         // https://hl7-definition.caristix.com/v2/HL7v2.8/Segments/MSH
@@ -136,7 +761,7 @@ impl<'a> Index<&str> for Segment<'a> {
                 // return &&self.source[3..3]; //TODO figure out how to return a string ref safely
                 return &"|";
             } else {
-                idx = idx - 1
+                idx -= 1
             }
         }
         match sections.len() {
@@ -165,7 +790,7 @@ impl<'a> Index<String> for Segment<'a> {
 
 #[cfg(test)]
 mod tests {
-    use crate::{message::Message, segments::*, separators::Separators, Hl7ParseError};
+    use crate::{message::Message, segments::*};
     use std::convert::TryFrom;
 
     #[test]
@@ -179,6 +804,224 @@ mod tests {
         assert_eq!(s, "sub&segment");
     }
 
+    #[test]
+    fn ensure_fields_are_found_by_number() -> Result<(), Hl7ParseError> {
+        let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rOBR|1|845439^GHH OE|1045813^GHH LAB|15545^GLUCOSE|||200202150730";
+        let msg = Message::try_from(hl7)?;
+
+        let obr = &msg.segments[1];
+        assert_eq!(obr.segment_name(), "OBR");
+        assert_eq!(obr.field(4).unwrap().value(), "15545^GLUCOSE");
+        assert_eq!(obr.field(4).unwrap().query("R1.C2"), "GLUCOSE");
+        assert_eq!(obr.field(0), None);
+        assert_eq!(obr.field(8), None);
+
+        let msh = &msg.segments[0];
+        assert_eq!(msh.segment_name(), "MSH");
+        assert_eq!(msh.field(1), None);
+        assert_eq!(msh.field(2).unwrap().value(), "^~\\&");
+        assert_eq!(msh.field(9).unwrap().value(), msg.query("MSH-9"));
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_segments_convert_from_str() -> Result<(), Hl7ParseError> {
+        let obr = Segment::try_from("OBR|1|845439^GHH OE")?;
+        assert_eq!(
+            obr,
+            Segment::parse("OBR|1|845439^GHH OE", &Separators::default())?
+        );
+
+        // MSH uses the separators it declares
+        let msh = Segment::try_from("MSH#@~\\$#GHH LAB@ELAB-3")?;
+        assert_eq!(msh.fields[2].components()[0], vec!["GHH LAB", "ELAB-3"]);
+        assert!(Segment::try_from("MSH").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_descriptions_are_found() {
+        assert_eq!(segment_description("PID"), Some("Patient Identification"));
+        assert_eq!(segment_description("OBX"), Some("Observation/Result"));
+        assert_eq!(segment_description("ZPD"), None);
+        assert_eq!(segment_description("pid"), None);
+
+        assert_eq!(field_description("MSH", 1), Some("Field Separator"));
+        assert_eq!(field_description("MSH", 10), Some("Message Control ID"));
+        assert_eq!(
+            field_description("PV1", 3),
+            Some("Assigned Patient Location")
+        );
+        assert_eq!(field_description("PID", 7), Some("Date of Birth"));
+        assert_eq!(
+            field_description("OBX", 14),
+            Some("Date/Time of the Observation")
+        );
+        assert_eq!(field_description("PID", 0), None);
+        assert_eq!(field_description("PID", 40), None);
+        assert_eq!(field_description("NTE", 1), None);
+    }
+
+    #[test]
+    fn ensure_empty_fields_are_distinct_from_absent_ones() -> Result<(), Hl7ParseError> {
+        let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4|";
+        let msg = Message::try_from(hl7)?;
+        let msh = &msg.segments[0];
+
+        assert!(msh.field(8).unwrap().is_empty()); // MSH-8 is present, but empty
+        assert!(msh.field(13).unwrap().is_empty()); // as is the trailing MSH-13
+        assert!(msh.field(19).is_none()); // MSH-19 is absent
+        assert!(!msh.field(9).unwrap().is_empty());
+        assert_eq!(msh.rebuild(), hl7);
+
+        // the typed segment collapses both to `None`, but still echoes its source
+        match msh.typed()? {
+            TypedSegment::MSH(typed) => {
+                assert_eq!(typed.msh_8_security, None);
+                assert_eq!(typed.msh_19_principal_language_of_message, None);
+                assert_eq!(typed.to_string(), hl7);
+            }
+            _ => panic!("Expected an MSH"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_short_lines_dont_panic() -> Result<(), Hl7ParseError> {
+        let delims = Separators::default();
+
+        for line in ["", "M", "   "] {
+            let segment = Segment::parse(line, &delims)?;
+            assert_eq!(segment.fields.len(), 1);
+            assert_eq!(segment.fields[0].source, line);
+            assert_eq!(segment[1], "");
+            assert!(matches!(segment.typed()?, TypedSegment::Generic(_)));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_segment_ids_are_matched_leniently() -> Result<(), Hl7ParseError> {
+        let delims = Separators::default();
+
+        let pid = Segment::parse(" pid|||555-44-4444||EVERYWOMAN^EVE", &delims)?;
+        match pid.typed()? {
+            TypedSegment::PID(typed) => {
+                assert_eq!(typed.pid_3_patient_identifier_list.value(), "555-44-4444");
+                assert_eq!(typed.to_string(), " pid|||555-44-4444||EVERYWOMAN^EVE");
+            }
+            _ => panic!("Expected a PID"),
+        }
+
+        let msh = Segment::parse(
+            "\x0bmsh|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4",
+            &delims,
+        )?;
+        assert!(matches!(msh.typed()?, TypedSegment::MSH(_)));
+
+        // unknown segments still fall back to generic, untouched
+        let zzz = Segment::parse("zzz |1", &delims)?;
+        match zzz.typed()? {
+            TypedSegment::Generic(s) => assert_eq!(s.source, "zzz |1"),
+            _ => panic!("Expected a generic segment"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_typed_segments_are_dispatched() -> Result<(), Hl7ParseError> {
+        let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ADT^A01|CNTRL-3456|P|2.4\rROL||AD|AT|1234^HIPPOCRATES";
+        let msg = Message::try_from(hl7)?;
+
+        match msg.segments[0].typed()? {
+            TypedSegment::MSH(msh) => assert_eq!(msh.msh_9_message_type.value(), "ADT^A01"),
+            _ => panic!(),
+        }
+        match msg.segments[1].typed()? {
+            TypedSegment::ROL(rol) => assert_eq!(rol.rol_3_role.value(), "AT"),
+            _ => panic!(),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_source_is_retained_by_all_segment_types() -> Result<(), Hl7ParseError> {
+        let lines = [
+            "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|MDM|200202150930||MDM^T02|CNTRL-3456|P|2.4",
+            "PID|||555-44-4444||EVERYWOMAN^EVE^E^^^^L|JONES|19620320|F",
+            "ROL||AD|AT|1234^HIPPOCRATES",
+            "AL1|1|DA|70618^PENICILLIN",
+            "TXA|1|DS|TX|200202150930||||||||DOC-0001||||||AU",
+            "OBX|1|TX|11490-0^DISCHARGE SUMMARY^LN||Discharged home||||||F",
+            "ZPD|custom^segment",
+        ];
+        let hl7 = lines.join("\r");
+        let msg = Message::try_from(&*hl7)?;
+
+        for (segment, line) in msg.segments.iter().zip(lines.iter()) {
+            assert_eq!(segment.source(), *line);
+            assert_eq!(segment.typed()?.source(), *line);
+        }
+        assert!(matches!(msg.segments[0].typed()?, TypedSegment::MSH(_)));
+        assert!(matches!(msg.segments[3].typed()?, TypedSegment::AL1(_)));
+        assert!(matches!(msg.segments[4].typed()?, TypedSegment::TXA(_)));
+        assert!(matches!(msg.segments[5].typed()?, TypedSegment::OBX(_)));
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_typed_segments_agree_with_generic_field_access() -> Result<(), Hl7ParseError> {
+        let lines = [
+            "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ADT^A01|CNTRL-3456|P|2.4||||AL|||EN",
+            "EVN|A01|200202150930|||OPER^SMITH",
+            "PID|||555-44-4444~MRN-1||EVERYWOMAN^EVE",
+            "PV1|1|I|W^389^1||||1234^HIPPOCRATES~5678^GALEN",
+            "AL1|1|DA|70618^PENICILLIN||HIVES~RASH",
+            "OBX|1|NM|GLU||182|mg/dl|70-105|H|||F",
+            "ROL||AD|AT|1234^HIPPOCRATES",
+            "TXA|1|DS|TX|200202150930||||||||DOC-0001||||||AU",
+            "MSA|AA|CNTRL-3456|done",
+            "QRD|200202150930|R|I|Q-1|||10^RD|EVERYWOMAN|DEM",
+        ];
+        let hl7 = lines.join("\r");
+        let msg = Message::try_from(&*hl7)?;
+
+        for segment in &msg.segments {
+            let typed = segment.typed()?;
+            let typed: &dyn RepeatingSegment = match &typed {
+                TypedSegment::AL1(s) => s,
+                TypedSegment::EVN(s) => s,
+                TypedSegment::MSA(s) => s,
+                TypedSegment::MSH(s) => s,
+                TypedSegment::OBX(s) => s,
+                TypedSegment::PID(s) => s,
+                TypedSegment::PV1(s) => s,
+                TypedSegment::QRD(s) => s,
+                TypedSegment::ROL(s) => s,
+                TypedSegment::TXA(s) => s,
+                TypedSegment::Generic(_) => panic!("{} wasn't typed", segment.segment_name()),
+            };
+
+            // MSH-1/MSH-2 are the separators, which the typed MSH doesn't hold as fields
+            let first = if segment.segment_name() == "MSH" {
+                3
+            } else {
+                1
+            };
+            for n in first..segment.fields.len() + 1 {
+                assert_eq!(
+                    typed.field(n).map_or("", |f| f.source),
+                    segment.field(n).map_or("", |f| f.source),
+                    "{}-{}",
+                    segment.segment_name(),
+                    n
+                );
+            }
+            assert_eq!(typed.field(0), None);
+        }
+        Ok(())
+    }
+
     #[test]
     fn ensure_string_query() {
         let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rOBR|segment^sub&segment";
@@ -215,5 +1058,17 @@ mod tests {
             assert_eq!(s, "segment");
             assert_eq!(oob, "");
         }
+
+        #[test]
+        fn ensure_out_of_range_string_index_is_empty() {
+            let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rOBR|segment^sub&segment";
+            let msg = Message::try_from(hl7).unwrap();
+            let x = &msg.segments[1];
+            assert_eq!(x["F3"], "");
+            assert_eq!(x["F3.R1.C1"], "");
+            assert_eq!(x["F1.R0"], "");
+            assert_eq!(x["FX"], "");
+            assert_eq!(msg.segments[0]["F9.R1.C2"], "R01");
+        }
     }
 }
@@ -0,0 +1,132 @@
+use super::RepeatingSegment;
+use crate::{fields::Field, separators::Separators, Hl7ParseError};
+use std::fmt::Display;
+
+/// The AL1 (Patient Allergy Information) segment, describing a single allergy.  Patients with several allergies will have one AL1 per allergy.
+/// See [the spec](https://hl7-definition.caristix.com/v2/HL7v2.5.1/Segments/AL1) for more info
+#[derive(Debug, PartialEq, Clone)]
+pub struct Al1Segment<'a> {
+    pub source: &'a str,
+    pub al1_1_set_id: Field<'a>,
+    pub al1_2_allergen_type_code: Option<Field<'a>>,
+    pub al1_3_allergen_code: Field<'a>,
+    pub al1_4_allergy_severity_code: Option<Field<'a>>,
+    pub al1_5_allergy_reaction_code: Option<Field<'a>>, // repeating field
+    pub al1_6_identification_date: Option<Field<'a>>,
+}
+
+impl<'a> Al1Segment<'a> {
+    pub fn parse<S: Into<&'a str>>(
+        input: S,
+        delims: &Separators,
+    ) -> Result<Al1Segment<'a>, Hl7ParseError> {
+        let input = input.into();
+
+        let mut fields = input.split(delims.field);
+
+        if !super::has_segment_id(fields.next(), "AL1") {
+            return Err(Hl7ParseError::Generic(
+                "Segment doesn't start with 'AL1'".to_string(),
+            ));
+        }
+
+        let al1 = Al1Segment {
+            source: input,
+            al1_1_set_id: Field::parse_mandatory(fields.next(), delims, Some("AL1-1"))?,
+            al1_2_allergen_type_code: Field::parse_optional(fields.next(), delims)?,
+            al1_3_allergen_code: Field::parse_mandatory(fields.next(), delims, Some("AL1-3"))?,
+            al1_4_allergy_severity_code: Field::parse_optional(fields.next(), delims)?,
+            al1_5_allergy_reaction_code: Field::parse_optional(fields.next(), delims)?,
+            al1_6_identification_date: Field::parse_optional(fields.next(), delims)?,
+        };
+
+        Ok(al1)
+    }
+}
+
+impl<'a> Display for Al1Segment<'a> {
+    /// Required for to_string() and other formatter consumers
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl<'a> RepeatingSegment<'a> for Al1Segment<'a> {
+    /// Fields beyond AL1-6 aren't modelled, so are always `None`.
+    fn field(&self, field_number: usize) -> Option<&Field<'a>> {
+        match field_number {
+            1 => Some(&self.al1_1_set_id),
+            2 => self.al1_2_allergen_type_code.as_ref(),
+            3 => Some(&self.al1_3_allergen_code),
+            4 => self.al1_4_allergy_severity_code.as_ref(),
+            5 => self.al1_5_allergy_reaction_code.as_ref(),
+            6 => self.al1_6_identification_date.as_ref(),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::Message;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn ensure_al1_fields_are_parsed() -> Result<(), Hl7ParseError> {
+        let d = Separators::default();
+        let al1 = Al1Segment::parse("AL1|1|DA^Drug allergy^HL70127|70618^PENICILLIN^RXNORM|SV^Severe^HL70128|Hives~Anaphylaxis|20020215", &d)?;
+
+        assert_eq!(al1.al1_1_set_id.value(), "1");
+        assert_eq!(al1.al1_2_allergen_type_code.as_ref().unwrap()[(0, 0)], "DA");
+        assert_eq!(al1.al1_3_allergen_code[(0, 1)], "PENICILLIN");
+        assert_eq!(
+            al1.al1_4_allergy_severity_code.as_ref().unwrap()[(0, 0)],
+            "SV"
+        );
+        assert_eq!(
+            al1.al1_5_allergy_reaction_code.as_ref().unwrap()[1],
+            "Anaphylaxis"
+        );
+        assert_eq!(
+            al1.al1_6_identification_date.as_ref().unwrap().value(),
+            "20020215"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_truncated_al1_names_missing_field() {
+        let d = Separators::default();
+        match Al1Segment::parse("AL1|1|DA", &d) {
+            Err(e @ Hl7ParseError::MissingRequiredValue { .. }) => assert_eq!(
+                e.to_string(),
+                "Required value AL1-3 missing, the segment may be truncated or malformed"
+            ),
+            _ => panic!("Expected MissingRequiredValue"),
+        }
+    }
+
+    #[test]
+    fn ensure_multiple_al1_segments_are_found() -> Result<(), Hl7ParseError> {
+        let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ADT^A01|CNTRL-3456|P|2.4\rAL1|1|DA|70618^PENICILLIN\rAL1|2|FA|256349002^PEANUT|MO";
+        let msg = Message::try_from(hl7)?;
+
+        let allergies: Result<Vec<Al1Segment>, Hl7ParseError> = msg
+            .segments_by_name("AL1")
+            .map(|s| Al1Segment::parse(s.source, &msg.get_separators()))
+            .collect();
+        let allergies = allergies?;
+
+        assert_eq!(allergies.len(), 2);
+        assert_eq!(allergies[1].al1_3_allergen_code[(0, 1)], "PEANUT");
+        assert!(allergies[0].al1_4_allergy_severity_code.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_missing_allergen_is_an_error() {
+        let d = Separators::default();
+        assert!(Al1Segment::parse("AL1|1|DA", &d).is_err());
+    }
+}
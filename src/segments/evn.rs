@@ -0,0 +1,119 @@
+use super::RepeatingSegment;
+use crate::{fields::Field, separators::Separators, Hl7ParseError};
+use std::fmt::Display;
+
+/// The EVN (Event Type) segment, carried in ADT messages to record the trigger event and when it was recorded/occurred.
+/// See [the spec](https://hl7-definition.caristix.com/v2/HL7v2.5.1/Segments/EVN) for more info
+#[derive(Debug, PartialEq, Clone)]
+pub struct EvnSegment<'a> {
+    pub source: &'a str,
+    /// Retained for backwards compatibility only, the trigger event is in MSH-9.2
+    pub evn_1_event_type_code: Option<Field<'a>>,
+    pub evn_2_recorded_date_time: Field<'a>,
+    pub evn_3_date_time_planned_event: Option<Field<'a>>,
+    pub evn_4_event_reason_code: Option<Field<'a>>,
+    pub evn_5_operator_id: Option<Field<'a>>,
+    pub evn_6_event_occurred: Option<Field<'a>>,
+    pub evn_7_event_facility: Option<Field<'a>>,
+}
+
+impl<'a> EvnSegment<'a> {
+    pub fn parse<S: Into<&'a str>>(
+        input: S,
+        delims: &Separators,
+    ) -> Result<EvnSegment<'a>, Hl7ParseError> {
+        let input = input.into();
+
+        let mut fields = input.split(delims.field);
+
+        if !super::has_segment_id(fields.next(), "EVN") {
+            return Err(Hl7ParseError::Generic(
+                "Segment doesn't start with 'EVN'".to_string(),
+            ));
+        }
+
+        let evn = EvnSegment {
+            source: input,
+            evn_1_event_type_code: Field::parse_optional(fields.next(), delims)?,
+            evn_2_recorded_date_time: Field::parse_mandatory(fields.next(), delims, Some("EVN-2"))?,
+            evn_3_date_time_planned_event: Field::parse_optional(fields.next(), delims)?,
+            evn_4_event_reason_code: Field::parse_optional(fields.next(), delims)?,
+            evn_5_operator_id: Field::parse_optional(fields.next(), delims)?,
+            evn_6_event_occurred: Field::parse_optional(fields.next(), delims)?,
+            evn_7_event_facility: Field::parse_optional(fields.next(), delims)?,
+        };
+
+        Ok(evn)
+    }
+}
+
+impl<'a> Display for EvnSegment<'a> {
+    /// Required for to_string() and other formatter consumers
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl<'a> RepeatingSegment<'a> for EvnSegment<'a> {
+    /// Fields beyond EVN-7 aren't modelled, so are always `None`.
+    fn field(&self, field_number: usize) -> Option<&Field<'a>> {
+        match field_number {
+            1 => self.evn_1_event_type_code.as_ref(),
+            2 => Some(&self.evn_2_recorded_date_time),
+            3 => self.evn_3_date_time_planned_event.as_ref(),
+            4 => self.evn_4_event_reason_code.as_ref(),
+            5 => self.evn_5_operator_id.as_ref(),
+            6 => self.evn_6_event_occurred.as_ref(),
+            7 => self.evn_7_event_facility.as_ref(),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::Message;
+    use crate::segments::TypedSegment;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn ensure_evn_fields_are_parsed() -> Result<(), Hl7ParseError> {
+        let hl7 = "MSH|^~\\&|ADT1|GOOD HEALTH HOSPITAL|GHH LAB, INC.|GOOD HEALTH HOSPITAL|198808181126||ADT^A01^ADT_A01|MSG00001|P|2.5.1\rEVN|A01|200202150930";
+        let msg = Message::try_from(hl7)?;
+
+        let evn = match msg.segments[1].typed()? {
+            TypedSegment::EVN(evn) => evn,
+            _ => panic!("Expected an EVN"),
+        };
+        assert_eq!(evn.evn_1_event_type_code.as_ref().unwrap().value(), "A01");
+        assert_eq!(evn.evn_2_recorded_date_time.value(), "200202150930");
+        assert_eq!(evn.evn_4_event_reason_code, None);
+        assert_eq!(evn.evn_6_event_occurred, None);
+        assert_eq!(evn.to_string(), "EVN|A01|200202150930");
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_all_evn_fields_are_read() -> Result<(), Hl7ParseError> {
+        let d = Separators::default();
+        let evn = EvnSegment::parse(
+            "EVN||200202150930|200202161000|01|555-55-5555^PRIMARY^PATRICIA|200202150915|GHH",
+            &d,
+        )?;
+
+        assert_eq!(evn.evn_1_event_type_code, None);
+        assert_eq!(
+            evn.evn_3_date_time_planned_event.unwrap().value(),
+            "200202161000"
+        );
+        assert_eq!(evn.evn_4_event_reason_code.unwrap().value(), "01");
+        assert_eq!(evn.evn_5_operator_id.unwrap()[(0, 1)], "PRIMARY");
+        assert_eq!(evn.evn_6_event_occurred.unwrap().value(), "200202150915");
+        assert_eq!(evn.evn_7_event_facility.unwrap().value(), "GHH");
+
+        assert!(EvnSegment::parse("EVN|A01", &d).is_err()); // EVN-2 is required
+        assert!(EvnSegment::parse("PID|A01|200202150930", &d).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,120 @@
+use super::RepeatingSegment;
+use crate::{fields::Field, separators::Separators, Hl7ParseError};
+use std::fmt::Display;
+
+/// The MSA (Message Acknowledgment) segment, carried in an ACK to say whether the original message (identified by its control id) was accepted.
+/// See [the spec](https://hl7-definition.caristix.com/v2/HL7v2.5.1/Segments/MSA) for more info
+#[derive(Debug, PartialEq, Clone)]
+pub struct MsaSegment<'a> {
+    pub source: &'a str,
+    pub msa_1_acknowledgment_code: Field<'a>,
+    pub msa_2_message_control_id: Field<'a>,
+    pub msa_3_text_message: Option<Field<'a>>,
+    pub msa_4_expected_sequence_number: Option<Field<'a>>,
+    pub msa_5_delayed_acknowledgment_type: Option<Field<'a>>,
+    pub msa_6_error_condition: Option<Field<'a>>,
+}
+
+impl<'a> MsaSegment<'a> {
+    pub fn parse<S: Into<&'a str>>(
+        input: S,
+        delims: &Separators,
+    ) -> Result<MsaSegment<'a>, Hl7ParseError> {
+        let input = input.into();
+
+        let mut fields = input.split(delims.field);
+
+        if !super::has_segment_id(fields.next(), "MSA") {
+            return Err(Hl7ParseError::Generic(
+                "Segment doesn't start with 'MSA'".to_string(),
+            ));
+        }
+
+        let msa = MsaSegment {
+            source: input,
+            msa_1_acknowledgment_code: Field::parse_mandatory(
+                fields.next(),
+                delims,
+                Some("MSA-1"),
+            )?,
+            msa_2_message_control_id: Field::parse_mandatory(fields.next(), delims, Some("MSA-2"))?,
+            msa_3_text_message: Field::parse_optional(fields.next(), delims)?,
+            msa_4_expected_sequence_number: Field::parse_optional(fields.next(), delims)?,
+            msa_5_delayed_acknowledgment_type: Field::parse_optional(fields.next(), delims)?,
+            msa_6_error_condition: Field::parse_optional(fields.next(), delims)?,
+        };
+
+        Ok(msa)
+    }
+
+    /// Returns true if the acknowledgment code (MSA-1) accepts the original message, that is `AA` (application accept) or `CA` (commit accept,
+    /// in enhanced mode).
+    pub fn is_accept(&self) -> bool {
+        matches!(self.msa_1_acknowledgment_code.value(), "AA" | "CA")
+    }
+}
+
+impl<'a> Display for MsaSegment<'a> {
+    /// Required for to_string() and other formatter consumers
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl<'a> RepeatingSegment<'a> for MsaSegment<'a> {
+    /// Fields beyond MSA-6 aren't modelled, so are always `None`.
+    fn field(&self, field_number: usize) -> Option<&Field<'a>> {
+        match field_number {
+            1 => Some(&self.msa_1_acknowledgment_code),
+            2 => Some(&self.msa_2_message_control_id),
+            3 => self.msa_3_text_message.as_ref(),
+            4 => self.msa_4_expected_sequence_number.as_ref(),
+            5 => self.msa_5_delayed_acknowledgment_type.as_ref(),
+            6 => self.msa_6_error_condition.as_ref(),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::Message;
+    use crate::segments::TypedSegment;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn ensure_msa_fields_are_parsed() -> Result<(), Hl7ParseError> {
+        let hl7 = "MSH|^~\\&|CATH|StJohn|AcmeHIS|StJohn|20061019172719||ACK^O01|MSGID12349876|P|2.3\rMSA|AA|MSGID12349876";
+        let msg = Message::try_from(hl7)?;
+
+        let msa = match msg.segments[1].typed()? {
+            TypedSegment::MSA(msa) => msa,
+            _ => panic!("Expected an MSA"),
+        };
+        assert_eq!(msa.msa_1_acknowledgment_code.value(), "AA");
+        assert_eq!(msa.msa_2_message_control_id.value(), "MSGID12349876");
+        assert_eq!(msa.msa_3_text_message, None);
+        assert_eq!(msa.msa_6_error_condition, None);
+        assert!(msa.is_accept());
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_rejections_are_not_accepted() -> Result<(), Hl7ParseError> {
+        let d = Separators::default();
+        let msa = MsaSegment::parse(
+            "MSA|AR|MSGID12349876|Unknown patient|||204^Unknown key identifier^HL70357",
+            &d,
+        )?;
+
+        assert!(!msa.is_accept());
+        assert_eq!(msa.msa_3_text_message.unwrap().value(), "Unknown patient");
+        assert_eq!(msa.msa_6_error_condition.unwrap()[(0, 0)], "204");
+
+        assert!(MsaSegment::parse("MSA|CA|1", &d)?.is_accept());
+        assert!(!MsaSegment::parse("MSA|CE|1", &d)?.is_accept());
+        assert!(MsaSegment::parse("MSA|AA", &d).is_err());
+        Ok(())
+    }
+}
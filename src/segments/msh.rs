@@ -0,0 +1,423 @@
+use super::RepeatingSegment;
+use crate::parse_options::{self, ParseOptions};
+use crate::{fields::Field, segments::Segment, separators::Separators, Hl7ParseError};
+use log::warn;
+use std::fmt::Display;
+
+/// The most important Segment, almost all HL7 messages have an MSH (MLLP simple ack I'm looking at you).
+/// Given the importance of this segment for driving application behaviour, it gets the special treatment
+/// of a fully typed segment, not just a bag of fields....
+/// See [the spec](https://hl7-definition.caristix.com/v2/HL7v2.5.1/Segments/MSH) for more info
+#[derive(Debug, PartialEq, Clone)]
+pub struct MshSegment<'a> {
+    pub source: &'a str,
+    //this initial layout largely stolen from the _other_ hl7 crate: https://github.com/njaremko/hl7
+    pub msh_1_field_separator: char,
+    pub msh_2_encoding_characters: Separators,
+    pub msh_3_sending_application: Option<Field<'a>>,
+    pub msh_4_sending_facility: Option<Field<'a>>,
+    pub msh_5_receiving_application: Option<Field<'a>>,
+    pub msh_6_receiving_facility: Option<Field<'a>>,
+    pub msh_7_date_time_of_message: Field<'a>,
+    pub msh_8_security: Option<Field<'a>>,
+    pub msh_9_message_type: Field<'a>,
+    pub msh_10_message_control_id: Field<'a>,
+    pub msh_11_processing_id: Field<'a>,
+    pub msh_12_version_id: Field<'a>,
+    pub msh_13_sequence_number: Option<Field<'a>>,
+    pub msh_14_continuation_pointer: Option<Field<'a>>,
+    pub msh_15_accept_acknowledgment_type: Option<Field<'a>>,
+    pub msh_16_application_acknowledgment_type: Option<Field<'a>>,
+    pub msh_17_country_code: Option<Field<'a>>,
+    pub msh_18_character_set: Option<Field<'a>>, // repeating field, see `character_sets()`
+    pub msh_19_principal_language_of_message: Option<Field<'a>>,
+    // pub msh_20_alternate_character_set_handling_scheme: Option<Field<'a>>,
+    // pub msh_21_message_profile_identifier: Option<Vec<Field<'a>>>,
+    // pub msh_22_sending_responsible_organization: Option<Field<'a>>,
+    // pub msh_23_receiving_responsible_organization: Option<Field<'a>>,
+    // pub msh_24_sending_network_address: Option<Field<'a>>,
+    // pub msh_25_receiving_network_address: Option<Field<'a>>,
+}
+
+/// The message type from MSH-9, eg `ORU^R01^ORU_R01`, used to route a message.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct MessageType<'a> {
+    /// MSH-9.1, eg `ORU`
+    pub code: &'a str,
+    /// MSH-9.2, eg `R01`.  `None` for a bare code such as `ACK`.
+    pub trigger_event: Option<&'a str>,
+    /// MSH-9.3, eg `ORU_R01`.  Only present from v2.3.1, and often left off even then.
+    pub structure: Option<&'a str>,
+}
+
+impl<'a> MessageType<'a> {
+    /// Reads the message type from the components of the (first repeat of the) given MSH-9 field.
+    pub fn from_field(field: &Field<'a>) -> MessageType<'a> {
+        let component = |idx: usize| Some(field[(0, idx)]).filter(|c| !c.is_empty());
+        MessageType {
+            code: field[(0, 0)],
+            trigger_event: component(1),
+            structure: component(2),
+        }
+    }
+
+    /// Returns the message structure, from MSH-9.3 where it's given, otherwise inferred as `<code>_<trigger event>` (eg `ADT_A01` for
+    /// `ADT^A01`).  Acknowledgements always have the `ACK` structure, whatever their trigger event, and a bare code is returned as-is.
+    pub fn structure_or_inferred(&self) -> String {
+        match (self.structure, self.trigger_event) {
+            (Some(structure), _) => structure.to_string(),
+            _ if self.code == "ACK" => "ACK".to_string(),
+            (None, Some(event)) => format!("{}_{}", self.code, event),
+            (None, None) => self.code.to_string(),
+        }
+    }
+}
+
+impl<'a> MshSegment<'a> {
+    pub fn parse<S: Into<&'a str>>(
+        input: S,
+        delims: &Separators,
+    ) -> Result<MshSegment<'a>, Hl7ParseError> {
+        let input = input.into();
+
+        let mut fields = input.split(delims.field);
+
+        if !super::has_segment_id(fields.next(), "MSH") {
+            return Err(Hl7ParseError::Generic(
+                "Segment doesn't start with 'MSH'".to_string(),
+            ));
+        }
+
+        // consume the delimiter chars, a line that stops before them (eg a partial read) can't be a MSH
+        match fields.next() {
+            Some(encoding_chars) if !encoding_chars.is_empty() => {}
+            _ => {
+                return Err(Hl7ParseError::InvalidMsh(
+                    "MSH is truncated before the encoding characters (MSH-2)".to_string(),
+                ))
+            }
+        }
+
+        MshSegment::from_fields(input, delims, fields)
+    }
+
+    /// As per `parse()`, but with `options.repair` set a MSH missing the field separator before the encoding chars (eg
+    /// `MSH^~\&|GHH LAB|...`) is parsed as if it were present, logging a warning.  The separators it should have declared are taken
+    /// from the header, and `source` is still the line as given.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::parse_options::ParseOptions;
+    /// # use rusthl7::segments::msh::MshSegment;
+    /// # use rusthl7::separators::Separators;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let options = ParseOptions { repair: true, ..Default::default() };
+    /// let msh = MshSegment::parse_with_options("MSH^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4", &Separators::default(), &options)?;
+    /// assert_eq!(msh.msh_1_field_separator, '|');
+    /// assert_eq!(msh.msh_3_sending_application.unwrap().value(), "GHH LAB");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn parse_with_options<S: Into<&'a str>>(
+        input: S,
+        delims: &Separators,
+        options: &ParseOptions,
+    ) -> Result<MshSegment<'a>, Hl7ParseError> {
+        let input = input.into();
+        let repaired = if options.repair {
+            parse_options::missing_field_separator(input)
+        } else {
+            None
+        };
+
+        match repaired {
+            Some(repaired) => {
+                warn!("MSH segment is missing the field separator before the encoding characters, parsing as if it were present");
+                let delims = Separators {
+                    segment: delims.segment,
+                    ..repaired
+                };
+                let mut fields = input.split(delims.field);
+                fields.next(); // `MSH` run together with the encoding chars
+                MshSegment::from_fields(input, &delims, fields)
+            }
+            None => MshSegment::parse(input, delims),
+        }
+    }
+
+    /// Builds the segment from the fields following MSH-2.
+    fn from_fields(
+        input: &'a str,
+        delims: &Separators,
+        mut fields: std::str::Split<'a, char>,
+    ) -> Result<MshSegment<'a>, Hl7ParseError> {
+        let msh = MshSegment {
+            source: input,
+            msh_1_field_separator: delims.field,
+            msh_2_encoding_characters: delims.to_owned(),
+            msh_3_sending_application: Field::parse_optional(fields.next(), delims)?,
+            msh_4_sending_facility: Field::parse_optional(fields.next(), delims)?,
+            msh_5_receiving_application: Field::parse_optional(fields.next(), delims)?,
+            msh_6_receiving_facility: Field::parse_optional(fields.next(), delims)?,
+            msh_7_date_time_of_message: Field::parse_mandatory(
+                fields.next(),
+                delims,
+                Some("MSH-7"),
+            )?,
+            msh_8_security: Field::parse_optional(fields.next(), delims)?,
+            msh_9_message_type: Field::parse_mandatory(fields.next(), delims, Some("MSH-9"))?,
+            msh_10_message_control_id: Field::parse_mandatory(
+                fields.next(),
+                delims,
+                Some("MSH-10"),
+            )?,
+            msh_11_processing_id: Field::parse_mandatory(fields.next(), delims, Some("MSH-11"))?,
+            msh_12_version_id: Field::parse_mandatory(fields.next(), delims, Some("MSH-12"))?,
+            msh_13_sequence_number: Field::parse_optional(fields.next(), delims)?,
+            msh_14_continuation_pointer: Field::parse_optional(fields.next(), delims)?,
+            msh_15_accept_acknowledgment_type: Field::parse_optional(fields.next(), delims)?,
+            msh_16_application_acknowledgment_type: Field::parse_optional(fields.next(), delims)?,
+            msh_17_country_code: Field::parse_optional(fields.next(), delims)?,
+            msh_18_character_set: Field::parse_optional(fields.next(), delims)?,
+            msh_19_principal_language_of_message: Field::parse_optional(fields.next(), delims)?,
+        };
+
+        Ok(msh)
+    }
+
+    /// Returns the message type (MSH-9), split into its message code, trigger event and message structure.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::segments::msh::MshSegment;
+    /// # use rusthl7::separators::Separators;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let msh = MshSegment::parse("MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4", &Separators::default())?;
+    /// let message_type = msh.message_type();
+    /// assert_eq!(message_type.code, "ORU");
+    /// assert_eq!(message_type.trigger_event, Some("R01"));
+    /// assert_eq!(message_type.structure, None);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn message_type(&self) -> MessageType<'a> {
+        MessageType::from_field(&self.msh_9_message_type)
+    }
+
+    /// Re-assembles the segment using different delimiters, with MSH-1 and MSH-2 rewritten to declare them.  See
+    /// `Segment::rebuild_with()`.
+    pub fn rebuild_with(&self, delims: &Separators) -> String {
+        Segment::split(
+            self.source,
+            &self.msh_2_encoding_characters,
+            &ParseOptions::default(),
+        )
+        .rebuild_with(delims)
+    }
+
+    /// Returns each character set declared in MSH-18 (eg `["ASCII", "8859/1"]`), or an empty vec if none are declared.
+    /// The first is the default character set for the message, any others are alternates switched to via escape sequences.
+    pub fn character_sets(&self) -> Vec<&'a str> {
+        match &self.msh_18_character_set {
+            Some(field) => field.repeats().to_vec(),
+            None => Vec::new(),
+        }
+    }
+}
+
+impl<'a> Display for MshSegment<'a> {
+    /// Required for to_string() and other formatter consumers
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl<'a> RepeatingSegment<'a> for MshSegment<'a> {
+    /// MSH-1 and MSH-2 are parsed into separators rather than fields, and fields beyond MSH-19 aren't modelled, so these are always `None`.
+    fn field(&self, field_number: usize) -> Option<&Field<'a>> {
+        match field_number {
+            3 => self.msh_3_sending_application.as_ref(),
+            4 => self.msh_4_sending_facility.as_ref(),
+            5 => self.msh_5_receiving_application.as_ref(),
+            6 => self.msh_6_receiving_facility.as_ref(),
+            7 => Some(&self.msh_7_date_time_of_message),
+            8 => self.msh_8_security.as_ref(),
+            9 => Some(&self.msh_9_message_type),
+            10 => Some(&self.msh_10_message_control_id),
+            11 => Some(&self.msh_11_processing_id),
+            12 => Some(&self.msh_12_version_id),
+            13 => self.msh_13_sequence_number.as_ref(),
+            14 => self.msh_14_continuation_pointer.as_ref(),
+            15 => self.msh_15_accept_acknowledgment_type.as_ref(),
+            16 => self.msh_16_application_acknowledgment_type.as_ref(),
+            17 => self.msh_17_country_code.as_ref(),
+            18 => self.msh_18_character_set.as_ref(),
+            19 => self.msh_19_principal_language_of_message.as_ref(),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ensure_msh_fields_are_parsed() -> Result<(), Hl7ParseError> {
+        let d = Separators::default();
+        let msh = MshSegment::parse(
+            "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4",
+            &d,
+        )?;
+
+        assert_eq!(msh.msh_1_field_separator, '|');
+        assert_eq!(msh.msh_2_encoding_characters, d);
+        assert_eq!(
+            msh.msh_3_sending_application.as_ref().unwrap().value(),
+            "GHH LAB"
+        );
+        assert_eq!(msh.msh_8_security, None);
+        assert_eq!(msh.msh_9_message_type[(0, 1)], "R01");
+        assert_eq!(msh.msh_12_version_id.value(), "2.4");
+        assert!(msh.character_sets().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_message_types_are_split() -> Result<(), Hl7ParseError> {
+        let d = Separators::default();
+        let msh = MshSegment::parse(
+            "MSH|^~\\&|ADT1|GOOD HEALTH HOSPITAL|GHH LAB|GHH|198808181126|SECURITY|ADT^A01^ADT_A01|MSG00001|P|2.8",
+            &d,
+        )?;
+        assert_eq!(
+            msh.message_type(),
+            MessageType {
+                code: "ADT",
+                trigger_event: Some("A01"),
+                structure: Some("ADT_A01")
+            }
+        );
+
+        let msh = MshSegment::parse(
+            "MSH|^~\\&|CATH|StJohn|AcmeHIS|StJohn|20061019172719||ACK|MSGID12349876|P|2.3",
+            &d,
+        )?;
+        let message_type = msh.message_type();
+        assert_eq!(message_type.code, "ACK");
+        assert_eq!(message_type.trigger_event, None);
+        assert_eq!(message_type.structure, None);
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_structure_is_inferred() -> Result<(), Hl7ParseError> {
+        let d = Separators::default();
+        let structure = |msh_9: &str| -> Result<String, Hl7ParseError> {
+            let field = Field::parse(msh_9, &d)?;
+            Ok(MessageType::from_field(&field).structure_or_inferred())
+        };
+
+        assert_eq!(structure("ORU^R01^ORU_R01")?, "ORU_R01");
+        assert_eq!(structure("ADT^A04^ADT_A01")?, "ADT_A01");
+        assert_eq!(structure("ADT^A01")?, "ADT_A01");
+        assert_eq!(structure("ACK")?, "ACK");
+        assert_eq!(structure("ACK^A01")?, "ACK");
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_msh_is_rebuilt_with_new_delimiters() -> Result<(), Hl7ParseError> {
+        let msh = MshSegment::parse(
+            "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4",
+            &Separators::default(),
+        )?;
+        let hashes = Separators {
+            field: '#',
+            ..Separators::default()
+        };
+
+        assert_eq!(
+            msh.rebuild_with(&hashes),
+            "MSH#^~\\&#GHH LAB#ELAB-3#GHH OE#BLDG4#200202150930##ORU^R01#CNTRL-3456#P#2.4"
+        );
+        assert_eq!(msh.rebuild_with(&Separators::default()), msh.source);
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_short_lines_are_errors() {
+        let d = Separators::default();
+        for line in ["", "M", "   ", "MSH"] {
+            assert!(
+                MshSegment::parse(line, &d).is_err(),
+                "{:?} should be rejected",
+                line
+            );
+        }
+    }
+
+    #[test]
+    fn ensure_missing_field_separator_is_only_repaired_when_asked() -> Result<(), Hl7ParseError> {
+        let d = Separators::default();
+        let broken = "MSH^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4";
+        assert!(MshSegment::parse_with_options(broken, &d, &ParseOptions::default()).is_err());
+
+        let options = ParseOptions {
+            repair: true,
+            ..Default::default()
+        };
+        let msh = MshSegment::parse_with_options(broken, &d, &options)?;
+        assert_eq!(msh.source, broken);
+        assert_eq!(msh.msh_2_encoding_characters, d);
+        assert_eq!(msh.msh_9_message_type[(0, 1)], "R01");
+        assert_eq!(msh.msh_12_version_id.value(), "2.4");
+
+        // a v2.7 truncation char isn't mistaken for a missing separator
+        let msh = MshSegment::parse_with_options(
+            "MSH|^~\\&#|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.7",
+            &d,
+            &options,
+        )?;
+        assert_eq!(msh.msh_1_field_separator, '|');
+        assert_eq!(msh.msh_3_sending_application.unwrap().value(), "GHH LAB");
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_truncated_msh_is_rejected() {
+        let d = Separators::default();
+        for line in ["MSH", "MSH|"] {
+            assert!(
+                matches!(
+                    MshSegment::parse(line, &d),
+                    Err(Hl7ParseError::InvalidMsh(_))
+                ),
+                "{:?} should be an invalid MSH",
+                line
+            );
+        }
+
+        // the encoding chars are there, but nothing after them
+        assert!(matches!(
+            MshSegment::parse("MSH|^~\\&", &d),
+            Err(Hl7ParseError::MissingRequiredValue { field: Some(f) }) if f == "MSH-7"
+        ));
+    }
+
+    #[test]
+    fn ensure_repeating_character_sets_are_split() -> Result<(), Hl7ParseError> {
+        let d = Separators::default();
+        let msh = MshSegment::parse(
+            "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ADT^A01|CNTRL-3456|P|2.5||||||ASCII~8859/1|EN",
+            &d,
+        )?;
+
+        assert_eq!(msh.character_sets(), vec!["ASCII", "8859/1"]);
+        assert_eq!(
+            msh.msh_19_principal_language_of_message.unwrap().value(),
+            "EN"
+        );
+        Ok(())
+    }
+}
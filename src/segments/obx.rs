@@ -0,0 +1,366 @@
+use super::RepeatingSegment;
+use crate::datatypes::sn::parse_nm;
+use crate::{fields::Field, separators::Separators, Hl7ParseError};
+use std::fmt::Display;
+
+/// The OBX (Observation/Result) segment, carrying a single observation such as a lab result, or an attached document.
+/// The type of the value in OBX-5 is given by OBX-2.
+/// See [the spec](https://hl7-definition.caristix.com/v2/HL7v2.5.1/Segments/OBX) for more info
+#[derive(Debug, PartialEq, Clone)]
+pub struct ObxSegment<'a> {
+    pub source: &'a str,
+    pub obx_1_set_id: Option<Field<'a>>,
+    pub obx_2_value_type: Option<Field<'a>>,
+    pub obx_3_observation_identifier: Field<'a>,
+    pub obx_4_observation_sub_id: Option<Field<'a>>,
+    pub obx_5_observation_value: Option<Field<'a>>, // repeating field
+    pub obx_6_units: Option<Field<'a>>,
+    pub obx_7_references_range: Option<Field<'a>>,
+    pub obx_8_abnormal_flags: Option<Field<'a>>, // repeating field
+    pub obx_9_probability: Option<Field<'a>>,
+    pub obx_10_nature_of_abnormal_test: Option<Field<'a>>, // repeating field
+    pub obx_11_observation_result_status: Field<'a>,
+    pub obx_12_effective_date_of_reference_range: Option<Field<'a>>,
+    pub obx_13_user_defined_access_checks: Option<Field<'a>>,
+    pub obx_14_date_time_of_the_observation: Option<Field<'a>>,
+    pub obx_15_producers_id: Option<Field<'a>>,
+    pub obx_16_responsible_observer: Option<Field<'a>>, // repeating field
+    pub obx_17_observation_method: Option<Field<'a>>,   // repeating field
+    pub obx_18_equipment_instance_identifier: Option<Field<'a>>, // repeating field
+    pub obx_19_date_time_of_the_analysis: Option<Field<'a>>,
+}
+
+/// A numeric reference range read from OBX-7, see `ObxSegment::reference_range()`.  A bound is `None` when the range is open on that
+/// side, eg `<10` has no lower bound.  Bounds are inclusive unless marked exclusive, as only a strict comparator (`<10` or `>5`)
+/// excludes its bound.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct ReferenceRange {
+    pub low: Option<f64>,
+    pub high: Option<f64>,
+    pub low_exclusive: bool,
+    pub high_exclusive: bool,
+}
+
+impl ReferenceRange {
+    /// Reads a reference range in any of the common vendor forms: `70_105` or `10-20` (low and high), or a comparator and a single bound
+    /// such as `<10` or `>=5`.  Returns `None` for anything else, eg a textual range such as `NEGATIVE`.
+    pub fn parse(value: &str) -> Option<ReferenceRange> {
+        let value = value.trim();
+
+        for (comparator, is_upper, exclusive) in [
+            ("<=", true, false),
+            (">=", false, false),
+            ("<", true, true),
+            (">", false, true),
+        ] {
+            if let Some(bound) = value.strip_prefix(comparator) {
+                let bound = parse_nm(bound).ok()?;
+                let range = if is_upper {
+                    ReferenceRange {
+                        low: None,
+                        high: Some(bound),
+                        low_exclusive: false,
+                        high_exclusive: exclusive,
+                    }
+                } else {
+                    ReferenceRange {
+                        low: Some(bound),
+                        high: None,
+                        low_exclusive: exclusive,
+                        high_exclusive: false,
+                    }
+                };
+                return Some(range);
+            }
+        }
+
+        // a hyphen separating the bounds comes after the first char, which may be the sign of a negative low bound
+        let split = value
+            .find('_')
+            .or_else(|| value.get(1..)?.find('-').map(|idx| idx + 1))?;
+        let (low, high) = (&value[..split], &value[split + 1..]);
+        Some(ReferenceRange {
+            low: Some(parse_nm(low).ok()?),
+            high: Some(parse_nm(high).ok()?),
+            low_exclusive: false,
+            high_exclusive: false,
+        })
+    }
+
+    /// Returns true if the value is within the range, honouring whether each bound is exclusive.
+    pub fn contains(&self, value: f64) -> bool {
+        let above_low = match self.low {
+            Some(low) if self.low_exclusive => value > low,
+            Some(low) => value >= low,
+            None => true,
+        };
+        let below_high = match self.high {
+            Some(high) if self.high_exclusive => value < high,
+            Some(high) => value <= high,
+            None => true,
+        };
+        above_low && below_high
+    }
+}
+
+impl<'a> ObxSegment<'a> {
+    pub fn parse<S: Into<&'a str>>(
+        input: S,
+        delims: &Separators,
+    ) -> Result<ObxSegment<'a>, Hl7ParseError> {
+        let input = input.into();
+
+        let mut fields = input.split(delims.field);
+
+        if !super::has_segment_id(fields.next(), "OBX") {
+            return Err(Hl7ParseError::Generic(
+                "Segment doesn't start with 'OBX'".to_string(),
+            ));
+        }
+
+        let obx = ObxSegment {
+            source: input,
+            obx_1_set_id: Field::parse_optional(fields.next(), delims)?,
+            obx_2_value_type: Field::parse_optional(fields.next(), delims)?,
+            obx_3_observation_identifier: Field::parse_mandatory(
+                fields.next(),
+                delims,
+                Some("OBX-3"),
+            )?,
+            obx_4_observation_sub_id: Field::parse_optional(fields.next(), delims)?,
+            obx_5_observation_value: Field::parse_optional(fields.next(), delims)?,
+            obx_6_units: Field::parse_optional(fields.next(), delims)?,
+            obx_7_references_range: Field::parse_optional(fields.next(), delims)?,
+            obx_8_abnormal_flags: Field::parse_optional(fields.next(), delims)?,
+            obx_9_probability: Field::parse_optional(fields.next(), delims)?,
+            obx_10_nature_of_abnormal_test: Field::parse_optional(fields.next(), delims)?,
+            obx_11_observation_result_status: Field::parse_mandatory(
+                fields.next(),
+                delims,
+                Some("OBX-11"),
+            )?,
+            obx_12_effective_date_of_reference_range: Field::parse_optional(fields.next(), delims)?,
+            obx_13_user_defined_access_checks: Field::parse_optional(fields.next(), delims)?,
+            obx_14_date_time_of_the_observation: Field::parse_optional(fields.next(), delims)?,
+            obx_15_producers_id: Field::parse_optional(fields.next(), delims)?,
+            obx_16_responsible_observer: Field::parse_optional(fields.next(), delims)?,
+            obx_17_observation_method: Field::parse_optional(fields.next(), delims)?,
+            obx_18_equipment_instance_identifier: Field::parse_optional(fields.next(), delims)?,
+            obx_19_date_time_of_the_analysis: Field::parse_optional(fields.next(), delims)?,
+        };
+
+        Ok(obx)
+    }
+
+    /// Returns the numeric reference range from OBX-7 (eg `70_105`, `10-20` or `<10`), or `None` if it's absent or not a numeric range.
+    /// See `ReferenceRange::parse()`.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::segments::obx::ObxSegment;
+    /// # use rusthl7::separators::Separators;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let obx = ObxSegment::parse("OBX|1|NM|1554-5^GLUCOSE||182|mg/dl|70_105|H|||F", &Separators::default())?;
+    /// let range = obx.reference_range().unwrap();
+    /// assert_eq!((range.low, range.high), (Some(70.0), Some(105.0)));
+    /// assert!(!range.contains(obx.obx_5_observation_value.unwrap().as_f64()?));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn reference_range(&self) -> Option<ReferenceRange> {
+        ReferenceRange::parse(self.obx_7_references_range.as_ref()?.value())
+    }
+
+    /// Returns true if OBX-2 declares the value as `ED` (Encapsulated Data).
+    fn is_encapsulated_data(&self) -> bool {
+        matches!(&self.obx_2_value_type, Some(t) if t.value() == "ED")
+    }
+
+    /// Returns the data subtype (ED.3, eg `PDF` or `RTF`) of an encapsulated data value, or `None` if OBX-2 isn't `ED` or the subtype isn't provided.
+    pub fn content_type(&self) -> Option<&'a str> {
+        if !self.is_encapsulated_data() {
+            return None;
+        }
+        match self.obx_5_observation_value.as_ref()?[(0, 2)] {
+            "" => None,
+            subtype => Some(subtype),
+        }
+    }
+
+    /// Decodes the payload of an encapsulated data value (ED.5), eg an attached PDF report.  Requires the `base64` feature.
+    ///
+    /// Returns `None` if OBX-2 isn't `ED`, the encoding (ED.4) isn't `Base64`, or the payload isn't valid base64.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::segments::obx::ObxSegment;
+    /// # use rusthl7::separators::Separators;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let obx = ObxSegment::parse("OBX|1|ED|11490-0^DISCHARGE SUMMARY^LN||GHH^AP^PDF^Base64^JVBERi0xLjQ=||||||F", &Separators::default())?;
+    /// assert_eq!(obx.content_type(), Some("PDF"));
+    /// assert_eq!(obx.encapsulated_data().unwrap(), b"%PDF-1.4");
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "base64")]
+    pub fn encapsulated_data(&self) -> Option<Vec<u8>> {
+        use base64::Engine;
+
+        if !self.is_encapsulated_data() {
+            return None;
+        }
+        let value = self.obx_5_observation_value.as_ref()?;
+        if !value[(0, 3)].eq_ignore_ascii_case("Base64") {
+            return None;
+        }
+
+        // senders commonly wrap long payloads, which isn't valid base64 so strip any whitespace
+        let payload: String = value[(0, 4)]
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect();
+        base64::engine::general_purpose::STANDARD
+            .decode(payload)
+            .ok()
+    }
+}
+
+impl<'a> Display for ObxSegment<'a> {
+    /// Required for to_string() and other formatter consumers
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl<'a> RepeatingSegment<'a> for ObxSegment<'a> {
+    /// Fields beyond OBX-19 aren't modelled, so are always `None`.
+    fn field(&self, field_number: usize) -> Option<&Field<'a>> {
+        match field_number {
+            1 => self.obx_1_set_id.as_ref(),
+            2 => self.obx_2_value_type.as_ref(),
+            3 => Some(&self.obx_3_observation_identifier),
+            4 => self.obx_4_observation_sub_id.as_ref(),
+            5 => self.obx_5_observation_value.as_ref(),
+            6 => self.obx_6_units.as_ref(),
+            7 => self.obx_7_references_range.as_ref(),
+            8 => self.obx_8_abnormal_flags.as_ref(),
+            9 => self.obx_9_probability.as_ref(),
+            10 => self.obx_10_nature_of_abnormal_test.as_ref(),
+            11 => Some(&self.obx_11_observation_result_status),
+            12 => self.obx_12_effective_date_of_reference_range.as_ref(),
+            13 => self.obx_13_user_defined_access_checks.as_ref(),
+            14 => self.obx_14_date_time_of_the_observation.as_ref(),
+            15 => self.obx_15_producers_id.as_ref(),
+            16 => self.obx_16_responsible_observer.as_ref(),
+            17 => self.obx_17_observation_method.as_ref(),
+            18 => self.obx_18_equipment_instance_identifier.as_ref(),
+            19 => self.obx_19_date_time_of_the_analysis.as_ref(),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ensure_obx_fields_are_parsed() -> Result<(), Hl7ParseError> {
+        let d = Separators::default();
+        let obx = ObxSegment::parse(
+            "OBX|1|NM|2951-2^SODIUM^LN||140|mmol/l|135-145||||F|||200202150930",
+            &d,
+        )?;
+
+        assert_eq!(obx.obx_2_value_type.as_ref().unwrap().value(), "NM");
+        assert_eq!(obx.obx_3_observation_identifier[(0, 1)], "SODIUM");
+        assert_eq!(obx.obx_5_observation_value.as_ref().unwrap().value(), "140");
+        assert_eq!(obx.obx_11_observation_result_status.value(), "F");
+        assert!(obx.obx_19_date_time_of_the_analysis.is_none());
+
+        // not encapsulated data
+        assert_eq!(obx.content_type(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_reference_ranges_are_parsed() -> Result<(), Hl7ParseError> {
+        let d = Separators::default();
+        let range = |obx_7: &str| -> Result<Option<ReferenceRange>, Hl7ParseError> {
+            let source = format!("OBX|1|NM|1554-5^GLUCOSE||182|mg/dl|{}||||F", obx_7);
+            Ok(ObxSegment::parse(&*source, &d)?.reference_range())
+        };
+        let bounds = |low, high| {
+            Some(ReferenceRange {
+                low,
+                high,
+                low_exclusive: false,
+                high_exclusive: false,
+            })
+        };
+
+        assert_eq!(range("70_105")?, bounds(Some(70.0), Some(105.0)));
+        assert_eq!(range("10-20")?, bounds(Some(10.0), Some(20.0)));
+        assert_eq!(range("-5-5")?, bounds(Some(-5.0), Some(5.0)));
+        assert_eq!(range(" 3.5 - 5.0 ")?, bounds(Some(3.5), Some(5.0)));
+        assert_eq!(range("<=10")?, bounds(None, Some(10.0)));
+        assert_eq!(range(">=5")?, bounds(Some(5.0), None));
+        assert_eq!(
+            range("<10")?,
+            Some(ReferenceRange {
+                high_exclusive: true,
+                ..bounds(None, Some(10.0)).unwrap()
+            })
+        );
+        assert_eq!(
+            range(">5")?,
+            Some(ReferenceRange {
+                low_exclusive: true,
+                ..bounds(Some(5.0), None).unwrap()
+            })
+        );
+
+        for unparseable in ["", "NEGATIVE", "10", "<", "10-", "a_b", "1-2-3"] {
+            assert_eq!(range(unparseable)?, None, "{:?} isn't a range", unparseable);
+        }
+
+        let normal = ReferenceRange::parse("70_105").unwrap();
+        assert!(normal.contains(70.0) && normal.contains(105.0));
+        assert!(!normal.contains(182.0));
+        assert!(ReferenceRange::parse("<10").unwrap().contains(-3.0));
+
+        // only strict comparators exclude the bound itself
+        assert!(!ReferenceRange::parse("<10").unwrap().contains(10.0));
+        assert!(ReferenceRange::parse("<=10").unwrap().contains(10.0));
+        assert!(!ReferenceRange::parse(">5").unwrap().contains(5.0));
+        assert!(ReferenceRange::parse(">5").unwrap().contains(5.1));
+        assert!(ReferenceRange::parse(">=5").unwrap().contains(5.0));
+        Ok(())
+    }
+
+    #[cfg(feature = "base64")]
+    #[test]
+    fn ensure_encapsulated_data_is_decoded() -> Result<(), Hl7ParseError> {
+        let d = Separators::default();
+        let obx = ObxSegment::parse(
+            "OBX|1|ED|11490-0^DISCHARGE SUMMARY^LN||GHH^AP^PDF^Base64^SGVsbG8s IHdvcmxkIQ==||||||F",
+            &d,
+        )?;
+        assert_eq!(obx.content_type(), Some("PDF"));
+        assert_eq!(obx.encapsulated_data().unwrap(), b"Hello, world!");
+
+        // not base64 encoded
+        let obx = ObxSegment::parse("OBX|1|ED|11490-0||GHH^TEXT^^A^Hello||||||F", &d)?;
+        assert_eq!(obx.encapsulated_data(), None);
+
+        // corrupt payload
+        let obx = ObxSegment::parse("OBX|1|ED|11490-0||GHH^AP^PDF^Base64^!!!!||||||F", &d)?;
+        assert_eq!(obx.encapsulated_data(), None);
+
+        // wrong value type
+        let obx = ObxSegment::parse("OBX|1|ST|11490-0||GHH^AP^PDF^Base64^SGVsbG8=||||||F", &d)?;
+        assert_eq!(obx.content_type(), None);
+        assert_eq!(obx.encapsulated_data(), None);
+        Ok(())
+    }
+}
@@ -0,0 +1,284 @@
+use super::RepeatingSegment;
+use crate::{fields::Field, separators::Separators, Hl7ParseError};
+use std::fmt::Display;
+use std::ops::Index;
+
+/// The PID (Patient Identification) segment, the primary means of communicating patient identification and demographic information.
+/// See [the spec](https://hl7-definition.caristix.com/v2/HL7v2.5.1/Segments/PID) for more info
+#[derive(Debug, PartialEq, Clone)]
+pub struct PidSegment<'a> {
+    pub source: &'a str,
+    pub pid_1_set_id: Option<Field<'a>>,
+    pub pid_2_patient_id: Option<Field<'a>>,
+    pub pid_3_patient_identifier_list: Field<'a>, // repeating field
+    pub pid_4_alternate_patient_id: Option<Field<'a>>,
+    pub pid_5_patient_name: Field<'a>,
+    pub pid_6_mothers_maiden_name: Option<Field<'a>>,
+    pub pid_7_date_of_birth: Option<Field<'a>>,
+    pub pid_8_sex: Option<Field<'a>>,
+    pub pid_9_patient_alias: Option<Field<'a>>,
+    pub pid_10_race: Option<Field<'a>>,
+    pub pid_11_address: Option<Field<'a>>,
+    pub pid_12_county_code: Option<Field<'a>>,
+    pub pid_13_phone_number_home: Option<Field<'a>>,
+    pub pid_14_phone_number_business: Option<Field<'a>>,
+    pub pid_15_primary_language: Option<Field<'a>>,
+    pub pid_16_marital_status: Option<Field<'a>>,
+    pub pid_17_religion: Option<Field<'a>>,
+    pub pid_18_patient_account_number: Option<Field<'a>>,
+    pub pid_19_ssn_number: Option<Field<'a>>,
+    pub pid_20_drivers_license_number: Option<Field<'a>>,
+    pub pid_21_mothers_identifier: Option<Field<'a>>,
+    pub pid_22_ethnic_group: Option<Field<'a>>,
+    pub pid_23_birth_place: Option<Field<'a>>,
+    pub pid_24_multiple_birth_indicator: Option<Field<'a>>,
+    pub pid_25_birth_order: Option<Field<'a>>,
+    pub pid_26_citizenship: Option<Field<'a>>,
+    pub pid_27_veterans_military_status: Option<Field<'a>>,
+    pub pid_28_nationality: Option<Field<'a>>,
+    pub pid_29_patient_death_date_and_time: Option<Field<'a>>,
+    pub pid_30_patient_death_indicator: Option<Field<'a>>,
+    // pub pid_31_identity_unknown_indicator: Option<Field<'a>>,
+    // pub pid_32_identity_reliability_code: Option<Field<'a>>,
+    // pub pid_33_last_update_date_time: Option<Field<'a>>,
+    // pub pid_34_last_update_facility: Option<Field<'a>>,
+    // pub pid_35_species_code: Option<Field<'a>>,
+    // pub pid_36_breed_code: Option<Field<'a>>,
+    // pub pid_37_strain: Option<Field<'a>>,
+    // pub pid_38_production_class_code: Option<Field<'a>>,
+    // pub pid_39_tribal_citizenship: Option<Field<'a>>,
+}
+
+impl<'a> PidSegment<'a> {
+    pub fn parse<S: Into<&'a str>>(
+        input: S,
+        delims: &Separators,
+    ) -> Result<PidSegment<'a>, Hl7ParseError> {
+        let input = input.into();
+
+        let mut fields = input.split(delims.field);
+
+        if !super::has_segment_id(fields.next(), "PID") {
+            return Err(Hl7ParseError::Generic(
+                "Segment doesn't start with 'PID'".to_string(),
+            ));
+        }
+
+        let pid = PidSegment {
+            source: input,
+            pid_1_set_id: Field::parse_optional(fields.next(), delims)?,
+            pid_2_patient_id: Field::parse_optional(fields.next(), delims)?,
+            pid_3_patient_identifier_list: Field::parse_mandatory(
+                fields.next(),
+                delims,
+                Some("PID-3"),
+            )?,
+            pid_4_alternate_patient_id: Field::parse_optional(fields.next(), delims)?,
+            pid_5_patient_name: Field::parse_mandatory(fields.next(), delims, Some("PID-5"))?,
+            pid_6_mothers_maiden_name: Field::parse_optional(fields.next(), delims)?,
+            pid_7_date_of_birth: Field::parse_optional(fields.next(), delims)?,
+            pid_8_sex: Field::parse_optional(fields.next(), delims)?,
+            pid_9_patient_alias: Field::parse_optional(fields.next(), delims)?,
+            pid_10_race: Field::parse_optional(fields.next(), delims)?,
+            pid_11_address: Field::parse_optional(fields.next(), delims)?,
+            pid_12_county_code: Field::parse_optional(fields.next(), delims)?,
+            pid_13_phone_number_home: Field::parse_optional(fields.next(), delims)?,
+            pid_14_phone_number_business: Field::parse_optional(fields.next(), delims)?,
+            pid_15_primary_language: Field::parse_optional(fields.next(), delims)?,
+            pid_16_marital_status: Field::parse_optional(fields.next(), delims)?,
+            pid_17_religion: Field::parse_optional(fields.next(), delims)?,
+            pid_18_patient_account_number: Field::parse_optional(fields.next(), delims)?,
+            pid_19_ssn_number: Field::parse_optional(fields.next(), delims)?,
+            pid_20_drivers_license_number: Field::parse_optional(fields.next(), delims)?,
+            pid_21_mothers_identifier: Field::parse_optional(fields.next(), delims)?,
+            pid_22_ethnic_group: Field::parse_optional(fields.next(), delims)?,
+            pid_23_birth_place: Field::parse_optional(fields.next(), delims)?,
+            pid_24_multiple_birth_indicator: Field::parse_optional(fields.next(), delims)?,
+            pid_25_birth_order: Field::parse_optional(fields.next(), delims)?,
+            pid_26_citizenship: Field::parse_optional(fields.next(), delims)?,
+            pid_27_veterans_military_status: Field::parse_optional(fields.next(), delims)?,
+            pid_28_nationality: Field::parse_optional(fields.next(), delims)?,
+            pid_29_patient_death_date_and_time: Field::parse_optional(fields.next(), delims)?,
+            pid_30_patient_death_indicator: Field::parse_optional(fields.next(), delims)?,
+        };
+
+        Ok(pid)
+    }
+
+    /// Returns the patient's date of birth (PID-7), or `None` if it's absent or not a valid date.
+    /// Any time component is ignored, but the value needs at least day (`YYYYMMDD`) precision.
+    #[cfg(feature = "chrono")]
+    pub fn date_of_birth(&self) -> Option<chrono::NaiveDate> {
+        crate::timestamp::parse_hl7_date(self.pid_7_date_of_birth.as_ref()?.value())
+    }
+
+    /// Returns the patient's age in whole years on the given date, or `None` if the date of birth is unavailable or after the reference date.
+    #[cfg(feature = "chrono")]
+    pub fn age_at(&self, reference: chrono::NaiveDate) -> Option<u32> {
+        reference.years_since(self.date_of_birth()?)
+    }
+}
+
+impl<'a> Display for PidSegment<'a> {
+    /// Required for to_string() and other formatter consumers
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl<'a> RepeatingSegment<'a> for PidSegment<'a> {
+    /// Fields beyond PID-30 aren't modelled, so are always `None`.
+    fn field(&self, field_number: usize) -> Option<&Field<'a>> {
+        match field_number {
+            1 => self.pid_1_set_id.as_ref(),
+            2 => self.pid_2_patient_id.as_ref(),
+            3 => Some(&self.pid_3_patient_identifier_list),
+            4 => self.pid_4_alternate_patient_id.as_ref(),
+            5 => Some(&self.pid_5_patient_name),
+            6 => self.pid_6_mothers_maiden_name.as_ref(),
+            7 => self.pid_7_date_of_birth.as_ref(),
+            8 => self.pid_8_sex.as_ref(),
+            9 => self.pid_9_patient_alias.as_ref(),
+            10 => self.pid_10_race.as_ref(),
+            11 => self.pid_11_address.as_ref(),
+            12 => self.pid_12_county_code.as_ref(),
+            13 => self.pid_13_phone_number_home.as_ref(),
+            14 => self.pid_14_phone_number_business.as_ref(),
+            15 => self.pid_15_primary_language.as_ref(),
+            16 => self.pid_16_marital_status.as_ref(),
+            17 => self.pid_17_religion.as_ref(),
+            18 => self.pid_18_patient_account_number.as_ref(),
+            19 => self.pid_19_ssn_number.as_ref(),
+            20 => self.pid_20_drivers_license_number.as_ref(),
+            21 => self.pid_21_mothers_identifier.as_ref(),
+            22 => self.pid_22_ethnic_group.as_ref(),
+            23 => self.pid_23_birth_place.as_ref(),
+            24 => self.pid_24_multiple_birth_indicator.as_ref(),
+            25 => self.pid_25_birth_order.as_ref(),
+            26 => self.pid_26_citizenship.as_ref(),
+            27 => self.pid_27_veterans_military_status.as_ref(),
+            28 => self.pid_28_nationality.as_ref(),
+            29 => self.pid_29_patient_death_date_and_time.as_ref(),
+            30 => self.pid_30_patient_death_indicator.as_ref(),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> Index<usize> for PidSegment<'a> {
+    type Output = &'a str;
+    /// Access the raw value of a field by its HL7 field number (so `pid[5]` is PID-5), consistent with `Segment`'s indexing.
+    /// Absent fields, and fields beyond PID-30, return `""`.
+    fn index(&self, fidx: usize) -> &Self::Output {
+        match self.field(fidx) {
+            Some(f) => &f.source,
+            None => &"",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::segments::Segment;
+
+    #[test]
+    fn ensure_pid_fields_are_parsed() -> Result<(), Hl7ParseError> {
+        let d = Separators::default();
+        let pid = PidSegment::parse("PID|||555-44-4444||EVERYWOMAN^EVE^E^^^^L|JONES|19620320|F|||153 FERNWOOD DR.^^STATESVILLE^OH^35292||(206)3345232|(206)752-121||||AC555444444||67-A4335^OH^20030520", &d)?;
+
+        assert!(pid.pid_1_set_id.is_none());
+        assert_eq!(pid.pid_3_patient_identifier_list.value(), "555-44-4444");
+        assert_eq!(pid.pid_5_patient_name[(0, 1)], "EVE");
+        assert_eq!(
+            pid.pid_7_date_of_birth.as_ref().unwrap().value(),
+            "19620320"
+        );
+        assert_eq!(pid.pid_8_sex.as_ref().unwrap().value(), "F");
+        assert_eq!(pid.pid_11_address.as_ref().unwrap()[(0, 4)], "35292");
+        assert_eq!(
+            pid.pid_20_drivers_license_number.as_ref().unwrap()[(0, 0)],
+            "67-A4335"
+        );
+        assert!(pid.pid_30_patient_death_indicator.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_pid_fields_are_indexed_by_field_number() -> Result<(), Hl7ParseError> {
+        let d = Separators::default();
+        let source = "PID|1||555-44-4444||EVERYWOMAN^EVE^E^^^^L|JONES|19620320|F";
+        let pid = PidSegment::parse(source, &d)?;
+
+        assert_eq!(pid[1], "1");
+        assert_eq!(pid[2], "");
+        assert_eq!(pid[5], "EVERYWOMAN^EVE^E^^^^L");
+        assert_eq!(pid[8], "F");
+        assert_eq!(pid[30], "");
+        assert_eq!(pid[99], "");
+
+        // consistent with the generic segment
+        let generic = Segment::parse(source, &d)?;
+        for i in 1..=8 {
+            assert_eq!(pid[i], generic[i]);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_repeats_are_accessible_by_field_number() -> Result<(), Hl7ParseError> {
+        let d = Separators::default();
+        let source = "PID|||555-44-4444^^^SSA^SS~MRN-1^^^GHH^MR||EVERYWOMAN^EVE";
+        let pid = PidSegment::parse(source, &d)?;
+
+        assert_eq!(
+            pid.field_repeats(3),
+            vec!["555-44-4444^^^SSA^SS", "MRN-1^^^GHH^MR"]
+        );
+        assert_eq!(pid.field_repeats(5), vec!["EVERYWOMAN^EVE"]);
+        assert!(pid.field_repeats(7).is_empty());
+        assert!(pid.field_repeats(99).is_empty());
+
+        // consistent with the generic segment
+        let generic = Segment::parse(source, &d)?;
+        assert_eq!(pid.field_repeats(3), generic.field_repeats(3));
+        Ok(())
+    }
+
+    #[cfg(feature = "chrono")]
+    mod chrono_tests {
+        use super::*;
+        use chrono::NaiveDate;
+
+        #[test]
+        fn ensure_age_is_derived_from_date_of_birth() -> Result<(), Hl7ParseError> {
+            let d = Separators::default();
+            let pid = PidSegment::parse("PID|||555-44-4444||EVERYWOMAN^EVE|JONES|19620320|F", &d)?;
+            let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+
+            assert_eq!(pid.date_of_birth(), Some(date(1962, 3, 20)));
+            assert_eq!(pid.age_at(date(2002, 3, 19)), Some(39));
+            assert_eq!(pid.age_at(date(2002, 3, 20)), Some(40));
+            assert_eq!(pid.age_at(date(1960, 1, 1)), None);
+            Ok(())
+        }
+
+        #[test]
+        fn ensure_date_of_birth_handles_precision() -> Result<(), Hl7ParseError> {
+            let d = Separators::default();
+            let dob = |value: &str| {
+                let source = format!("PID|||555-44-4444||EVERYWOMAN^EVE||{}", value);
+                PidSegment::parse(&*source, &d).unwrap().date_of_birth()
+            };
+
+            assert_eq!(
+                dob("196203200930+1000"),
+                NaiveDate::from_ymd_opt(1962, 3, 20)
+            );
+            assert_eq!(dob("196203"), None);
+            assert_eq!(dob("19621340"), None);
+            assert_eq!(dob(""), None);
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,272 @@
+use super::RepeatingSegment;
+use crate::datatypes::pl::Pl;
+use crate::{fields::Field, separators::Separators, Hl7ParseError};
+use std::fmt::Display;
+
+/// The PV1 (Patient Visit) segment, carrying the details of an encounter such as the patient class, location and attending doctor.
+/// See [the spec](https://hl7-definition.caristix.com/v2/HL7v2.5.1/Segments/PV1) for more info
+#[derive(Debug, PartialEq, Clone)]
+pub struct Pv1Segment<'a> {
+    pub source: &'a str,
+    pub pv1_1_set_id: Option<Field<'a>>,
+    pub pv1_2_patient_class: Field<'a>,
+    pub pv1_3_assigned_patient_location: Option<Field<'a>>,
+    pub pv1_4_admission_type: Option<Field<'a>>,
+    pub pv1_5_preadmit_number: Option<Field<'a>>,
+    pub pv1_6_prior_patient_location: Option<Field<'a>>,
+    pub pv1_7_attending_doctor: Option<Field<'a>>, // repeating field
+    pub pv1_8_referring_doctor: Option<Field<'a>>, // repeating field
+    pub pv1_9_consulting_doctor: Option<Field<'a>>, // repeating field
+    pub pv1_10_hospital_service: Option<Field<'a>>,
+    pub pv1_11_temporary_location: Option<Field<'a>>,
+    pub pv1_12_preadmit_test_indicator: Option<Field<'a>>,
+    pub pv1_13_re_admission_indicator: Option<Field<'a>>,
+    pub pv1_14_admit_source: Option<Field<'a>>,
+    pub pv1_15_ambulatory_status: Option<Field<'a>>,
+    pub pv1_16_vip_indicator: Option<Field<'a>>,
+    pub pv1_17_admitting_doctor: Option<Field<'a>>, // repeating field
+    pub pv1_18_patient_type: Option<Field<'a>>,
+    pub pv1_19_visit_number: Option<Field<'a>>,
+    pub pv1_20_financial_class: Option<Field<'a>>,
+    pub pv1_21_charge_price_indicator: Option<Field<'a>>,
+    pub pv1_22_courtesy_code: Option<Field<'a>>,
+    pub pv1_23_credit_rating: Option<Field<'a>>,
+    pub pv1_24_contract_code: Option<Field<'a>>,
+    pub pv1_25_contract_effective_date: Option<Field<'a>>,
+    pub pv1_26_contract_amount: Option<Field<'a>>,
+    pub pv1_27_contract_period: Option<Field<'a>>,
+    pub pv1_28_interest_code: Option<Field<'a>>,
+    pub pv1_29_transfer_to_bad_debt_code: Option<Field<'a>>,
+    pub pv1_30_transfer_to_bad_debt_date: Option<Field<'a>>,
+    pub pv1_31_bad_debt_agency_code: Option<Field<'a>>,
+    pub pv1_32_bad_debt_transfer_amount: Option<Field<'a>>,
+    pub pv1_33_bad_debt_recovery_amount: Option<Field<'a>>,
+    pub pv1_34_delete_account_indicator: Option<Field<'a>>,
+    pub pv1_35_delete_account_date: Option<Field<'a>>,
+    pub pv1_36_discharge_disposition: Option<Field<'a>>,
+    pub pv1_37_discharged_to_location: Option<Field<'a>>,
+    pub pv1_38_diet_type: Option<Field<'a>>,
+    pub pv1_39_servicing_facility: Option<Field<'a>>,
+    pub pv1_40_bed_status: Option<Field<'a>>,
+    pub pv1_41_account_status: Option<Field<'a>>,
+    pub pv1_42_pending_location: Option<Field<'a>>,
+    pub pv1_43_prior_temporary_location: Option<Field<'a>>,
+    pub pv1_44_admit_date_time: Option<Field<'a>>,
+    pub pv1_45_discharge_date_time: Option<Field<'a>>,
+    pub pv1_46_current_patient_balance: Option<Field<'a>>,
+    pub pv1_47_total_charges: Option<Field<'a>>,
+    pub pv1_48_total_adjustments: Option<Field<'a>>,
+    pub pv1_49_total_payments: Option<Field<'a>>,
+    pub pv1_50_alternate_visit_id: Option<Field<'a>>,
+    pub pv1_51_visit_indicator: Option<Field<'a>>,
+    pub pv1_52_other_healthcare_provider: Option<Field<'a>>, // repeating field
+}
+
+impl<'a> Pv1Segment<'a> {
+    pub fn parse<S: Into<&'a str>>(
+        input: S,
+        delims: &Separators,
+    ) -> Result<Pv1Segment<'a>, Hl7ParseError> {
+        let input = input.into();
+
+        let mut fields = input.split(delims.field);
+
+        if !super::has_segment_id(fields.next(), "PV1") {
+            return Err(Hl7ParseError::Generic(
+                "Segment doesn't start with 'PV1'".to_string(),
+            ));
+        }
+
+        let pv1 = Pv1Segment {
+            source: input,
+            pv1_1_set_id: Field::parse_optional(fields.next(), delims)?,
+            pv1_2_patient_class: Field::parse_mandatory(fields.next(), delims, Some("PV1-2"))?,
+            pv1_3_assigned_patient_location: Field::parse_optional(fields.next(), delims)?,
+            pv1_4_admission_type: Field::parse_optional(fields.next(), delims)?,
+            pv1_5_preadmit_number: Field::parse_optional(fields.next(), delims)?,
+            pv1_6_prior_patient_location: Field::parse_optional(fields.next(), delims)?,
+            pv1_7_attending_doctor: Field::parse_optional(fields.next(), delims)?,
+            pv1_8_referring_doctor: Field::parse_optional(fields.next(), delims)?,
+            pv1_9_consulting_doctor: Field::parse_optional(fields.next(), delims)?,
+            pv1_10_hospital_service: Field::parse_optional(fields.next(), delims)?,
+            pv1_11_temporary_location: Field::parse_optional(fields.next(), delims)?,
+            pv1_12_preadmit_test_indicator: Field::parse_optional(fields.next(), delims)?,
+            pv1_13_re_admission_indicator: Field::parse_optional(fields.next(), delims)?,
+            pv1_14_admit_source: Field::parse_optional(fields.next(), delims)?,
+            pv1_15_ambulatory_status: Field::parse_optional(fields.next(), delims)?,
+            pv1_16_vip_indicator: Field::parse_optional(fields.next(), delims)?,
+            pv1_17_admitting_doctor: Field::parse_optional(fields.next(), delims)?,
+            pv1_18_patient_type: Field::parse_optional(fields.next(), delims)?,
+            pv1_19_visit_number: Field::parse_optional(fields.next(), delims)?,
+            pv1_20_financial_class: Field::parse_optional(fields.next(), delims)?,
+            pv1_21_charge_price_indicator: Field::parse_optional(fields.next(), delims)?,
+            pv1_22_courtesy_code: Field::parse_optional(fields.next(), delims)?,
+            pv1_23_credit_rating: Field::parse_optional(fields.next(), delims)?,
+            pv1_24_contract_code: Field::parse_optional(fields.next(), delims)?,
+            pv1_25_contract_effective_date: Field::parse_optional(fields.next(), delims)?,
+            pv1_26_contract_amount: Field::parse_optional(fields.next(), delims)?,
+            pv1_27_contract_period: Field::parse_optional(fields.next(), delims)?,
+            pv1_28_interest_code: Field::parse_optional(fields.next(), delims)?,
+            pv1_29_transfer_to_bad_debt_code: Field::parse_optional(fields.next(), delims)?,
+            pv1_30_transfer_to_bad_debt_date: Field::parse_optional(fields.next(), delims)?,
+            pv1_31_bad_debt_agency_code: Field::parse_optional(fields.next(), delims)?,
+            pv1_32_bad_debt_transfer_amount: Field::parse_optional(fields.next(), delims)?,
+            pv1_33_bad_debt_recovery_amount: Field::parse_optional(fields.next(), delims)?,
+            pv1_34_delete_account_indicator: Field::parse_optional(fields.next(), delims)?,
+            pv1_35_delete_account_date: Field::parse_optional(fields.next(), delims)?,
+            pv1_36_discharge_disposition: Field::parse_optional(fields.next(), delims)?,
+            pv1_37_discharged_to_location: Field::parse_optional(fields.next(), delims)?,
+            pv1_38_diet_type: Field::parse_optional(fields.next(), delims)?,
+            pv1_39_servicing_facility: Field::parse_optional(fields.next(), delims)?,
+            pv1_40_bed_status: Field::parse_optional(fields.next(), delims)?,
+            pv1_41_account_status: Field::parse_optional(fields.next(), delims)?,
+            pv1_42_pending_location: Field::parse_optional(fields.next(), delims)?,
+            pv1_43_prior_temporary_location: Field::parse_optional(fields.next(), delims)?,
+            pv1_44_admit_date_time: Field::parse_optional(fields.next(), delims)?,
+            pv1_45_discharge_date_time: Field::parse_optional(fields.next(), delims)?,
+            pv1_46_current_patient_balance: Field::parse_optional(fields.next(), delims)?,
+            pv1_47_total_charges: Field::parse_optional(fields.next(), delims)?,
+            pv1_48_total_adjustments: Field::parse_optional(fields.next(), delims)?,
+            pv1_49_total_payments: Field::parse_optional(fields.next(), delims)?,
+            pv1_50_alternate_visit_id: Field::parse_optional(fields.next(), delims)?,
+            pv1_51_visit_indicator: Field::parse_optional(fields.next(), delims)?,
+            pv1_52_other_healthcare_provider: Field::parse_optional(fields.next(), delims)?,
+        };
+
+        Ok(pv1)
+    }
+
+    /// Returns the patient's assigned location (PV1-3), eg their point of care, room and bed, or `None` if it's not given.
+    pub fn assigned_location(&self) -> Option<Pl<'a>> {
+        self.pv1_3_assigned_patient_location
+            .as_ref()
+            .map(|f| Pl::from_repeat(f, 0))
+    }
+}
+
+impl<'a> Display for Pv1Segment<'a> {
+    /// Required for to_string() and other formatter consumers
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl<'a> RepeatingSegment<'a> for Pv1Segment<'a> {
+    /// Fields beyond PV1-52 aren't modelled, so are always `None`.
+    fn field(&self, field_number: usize) -> Option<&Field<'a>> {
+        match field_number {
+            1 => self.pv1_1_set_id.as_ref(),
+            2 => Some(&self.pv1_2_patient_class),
+            3 => self.pv1_3_assigned_patient_location.as_ref(),
+            4 => self.pv1_4_admission_type.as_ref(),
+            5 => self.pv1_5_preadmit_number.as_ref(),
+            6 => self.pv1_6_prior_patient_location.as_ref(),
+            7 => self.pv1_7_attending_doctor.as_ref(),
+            8 => self.pv1_8_referring_doctor.as_ref(),
+            9 => self.pv1_9_consulting_doctor.as_ref(),
+            10 => self.pv1_10_hospital_service.as_ref(),
+            11 => self.pv1_11_temporary_location.as_ref(),
+            12 => self.pv1_12_preadmit_test_indicator.as_ref(),
+            13 => self.pv1_13_re_admission_indicator.as_ref(),
+            14 => self.pv1_14_admit_source.as_ref(),
+            15 => self.pv1_15_ambulatory_status.as_ref(),
+            16 => self.pv1_16_vip_indicator.as_ref(),
+            17 => self.pv1_17_admitting_doctor.as_ref(),
+            18 => self.pv1_18_patient_type.as_ref(),
+            19 => self.pv1_19_visit_number.as_ref(),
+            20 => self.pv1_20_financial_class.as_ref(),
+            21 => self.pv1_21_charge_price_indicator.as_ref(),
+            22 => self.pv1_22_courtesy_code.as_ref(),
+            23 => self.pv1_23_credit_rating.as_ref(),
+            24 => self.pv1_24_contract_code.as_ref(),
+            25 => self.pv1_25_contract_effective_date.as_ref(),
+            26 => self.pv1_26_contract_amount.as_ref(),
+            27 => self.pv1_27_contract_period.as_ref(),
+            28 => self.pv1_28_interest_code.as_ref(),
+            29 => self.pv1_29_transfer_to_bad_debt_code.as_ref(),
+            30 => self.pv1_30_transfer_to_bad_debt_date.as_ref(),
+            31 => self.pv1_31_bad_debt_agency_code.as_ref(),
+            32 => self.pv1_32_bad_debt_transfer_amount.as_ref(),
+            33 => self.pv1_33_bad_debt_recovery_amount.as_ref(),
+            34 => self.pv1_34_delete_account_indicator.as_ref(),
+            35 => self.pv1_35_delete_account_date.as_ref(),
+            36 => self.pv1_36_discharge_disposition.as_ref(),
+            37 => self.pv1_37_discharged_to_location.as_ref(),
+            38 => self.pv1_38_diet_type.as_ref(),
+            39 => self.pv1_39_servicing_facility.as_ref(),
+            40 => self.pv1_40_bed_status.as_ref(),
+            41 => self.pv1_41_account_status.as_ref(),
+            42 => self.pv1_42_pending_location.as_ref(),
+            43 => self.pv1_43_prior_temporary_location.as_ref(),
+            44 => self.pv1_44_admit_date_time.as_ref(),
+            45 => self.pv1_45_discharge_date_time.as_ref(),
+            46 => self.pv1_46_current_patient_balance.as_ref(),
+            47 => self.pv1_47_total_charges.as_ref(),
+            48 => self.pv1_48_total_adjustments.as_ref(),
+            49 => self.pv1_49_total_payments.as_ref(),
+            50 => self.pv1_50_alternate_visit_id.as_ref(),
+            51 => self.pv1_51_visit_indicator.as_ref(),
+            52 => self.pv1_52_other_healthcare_provider.as_ref(),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::Message;
+    use crate::segments::TypedSegment;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn ensure_pv1_fields_are_parsed() -> Result<(), Hl7ParseError> {
+        let hl7 = "MSH|^~\\&|ADT1|GOOD HEALTH HOSPITAL|GHH LAB, INC.|GOOD HEALTH HOSPITAL|198808181126||ADT^A01^ADT_A01|MSG00001|P|2.5.1\rPV1|1|I|2000^2012^01^GHH|E|||004777^ATTEND^AARON^A|||SUR|||||||||V1001^^^GHH^VN|||||||||||||||||||||||||200202150930";
+        let msg = Message::try_from(hl7)?;
+
+        let pv1 = match msg.segments[1].typed()? {
+            TypedSegment::PV1(pv1) => pv1,
+            _ => panic!("Expected a PV1"),
+        };
+        assert_eq!(pv1.pv1_1_set_id.as_ref().unwrap().value(), "1");
+        assert_eq!(pv1.pv1_2_patient_class.value(), "I");
+        assert_eq!(pv1.pv1_4_admission_type.as_ref().unwrap().value(), "E");
+        assert_eq!(
+            pv1.pv1_7_attending_doctor.as_ref().unwrap()[(0, 1)],
+            "ATTEND"
+        );
+        assert_eq!(pv1.pv1_10_hospital_service.as_ref().unwrap().value(), "SUR");
+        assert_eq!(
+            pv1.pv1_19_visit_number
+                .as_ref()
+                .unwrap()
+                .as_cx()
+                .identifier_type_code,
+            "VN"
+        );
+        assert_eq!(
+            pv1.pv1_44_admit_date_time.as_ref().unwrap().value(),
+            "200202150930"
+        );
+        assert_eq!(pv1.pv1_45_discharge_date_time, None);
+        assert_eq!(pv1.pv1_52_other_healthcare_provider, None);
+
+        let location = pv1.assigned_location().unwrap();
+        assert_eq!(location.point_of_care, "2000");
+        assert_eq!(location.room, "2012");
+        assert_eq!(location.bed, "01");
+        assert_eq!(location.facility, "GHH");
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_short_pv1_is_parsed() -> Result<(), Hl7ParseError> {
+        let d = Separators::default();
+        let pv1 = Pv1Segment::parse("PV1|1|O", &d)?;
+        assert_eq!(pv1.pv1_2_patient_class.value(), "O");
+        assert_eq!(pv1.assigned_location(), None);
+
+        assert!(Pv1Segment::parse("PV1|1", &d).is_err()); // PV1-2 is required
+        Ok(())
+    }
+}
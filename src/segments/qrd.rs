@@ -0,0 +1,126 @@
+use super::RepeatingSegment;
+use crate::{fields::Field, separators::Separators, Hl7ParseError};
+use std::fmt::Display;
+
+/// The QRD (Original-Style Query Definition) segment, defining the parameters of a query in (original mode) QRY messages, eg a patient lookup.
+/// See [the spec](https://hl7-definition.caristix.com/v2/HL7v2.5.1/Segments/QRD) for more info
+#[derive(Debug, PartialEq, Clone)]
+pub struct QrdSegment<'a> {
+    pub source: &'a str,
+    pub qrd_1_query_date_time: Field<'a>,
+    pub qrd_2_query_format_code: Field<'a>,
+    pub qrd_3_query_priority: Field<'a>,
+    pub qrd_4_query_id: Field<'a>,
+    pub qrd_5_deferred_response_type: Option<Field<'a>>,
+    pub qrd_6_deferred_response_date_time: Option<Field<'a>>,
+    pub qrd_7_quantity_limited_request: Field<'a>,
+    pub qrd_8_who_subject_filter: Field<'a>, // repeating field
+    pub qrd_9_what_subject_filter: Field<'a>, // repeating field
+    // the spec requires QRD-10, but it's very commonly truncated by senders so we're lenient
+    pub qrd_10_what_department_data_code: Option<Field<'a>>, // repeating field
+    pub qrd_11_what_data_code_value_qual: Option<Field<'a>>, // repeating field
+    pub qrd_12_query_results_level: Option<Field<'a>>,
+}
+
+impl<'a> QrdSegment<'a> {
+    pub fn parse<S: Into<&'a str>>(
+        input: S,
+        delims: &Separators,
+    ) -> Result<QrdSegment<'a>, Hl7ParseError> {
+        let input = input.into();
+
+        let mut fields = input.split(delims.field);
+
+        if !super::has_segment_id(fields.next(), "QRD") {
+            return Err(Hl7ParseError::Generic(
+                "Segment doesn't start with 'QRD'".to_string(),
+            ));
+        }
+
+        let qrd = QrdSegment {
+            source: input,
+            qrd_1_query_date_time: Field::parse_mandatory(fields.next(), delims, Some("QRD-1"))?,
+            qrd_2_query_format_code: Field::parse_mandatory(fields.next(), delims, Some("QRD-2"))?,
+            qrd_3_query_priority: Field::parse_mandatory(fields.next(), delims, Some("QRD-3"))?,
+            qrd_4_query_id: Field::parse_mandatory(fields.next(), delims, Some("QRD-4"))?,
+            qrd_5_deferred_response_type: Field::parse_optional(fields.next(), delims)?,
+            qrd_6_deferred_response_date_time: Field::parse_optional(fields.next(), delims)?,
+            qrd_7_quantity_limited_request: Field::parse_mandatory(
+                fields.next(),
+                delims,
+                Some("QRD-7"),
+            )?,
+            qrd_8_who_subject_filter: Field::parse_mandatory(fields.next(), delims, Some("QRD-8"))?,
+            qrd_9_what_subject_filter: Field::parse_mandatory(
+                fields.next(),
+                delims,
+                Some("QRD-9"),
+            )?,
+            qrd_10_what_department_data_code: Field::parse_optional(fields.next(), delims)?,
+            qrd_11_what_data_code_value_qual: Field::parse_optional(fields.next(), delims)?,
+            qrd_12_query_results_level: Field::parse_optional(fields.next(), delims)?,
+        };
+
+        Ok(qrd)
+    }
+}
+
+impl<'a> Display for QrdSegment<'a> {
+    /// Required for to_string() and other formatter consumers
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl<'a> RepeatingSegment<'a> for QrdSegment<'a> {
+    /// Fields beyond QRD-12 aren't modelled, so are always `None`.
+    fn field(&self, field_number: usize) -> Option<&Field<'a>> {
+        match field_number {
+            1 => Some(&self.qrd_1_query_date_time),
+            2 => Some(&self.qrd_2_query_format_code),
+            3 => Some(&self.qrd_3_query_priority),
+            4 => Some(&self.qrd_4_query_id),
+            5 => self.qrd_5_deferred_response_type.as_ref(),
+            6 => self.qrd_6_deferred_response_date_time.as_ref(),
+            7 => Some(&self.qrd_7_quantity_limited_request),
+            8 => Some(&self.qrd_8_who_subject_filter),
+            9 => Some(&self.qrd_9_what_subject_filter),
+            10 => self.qrd_10_what_department_data_code.as_ref(),
+            11 => self.qrd_11_what_data_code_value_qual.as_ref(),
+            12 => self.qrd_12_query_results_level.as_ref(),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ensure_qrd_fields_are_parsed() -> Result<(), Hl7ParseError> {
+        let d = Separators::default();
+        let qrd = QrdSegment::parse(
+            "QRD|200202150930|R|I|Q1004|||1^RD|555-44-4444^EVERYWOMAN^EVE|DEM",
+            &d,
+        )?;
+
+        assert_eq!(qrd.qrd_1_query_date_time.value(), "200202150930");
+        assert_eq!(qrd.qrd_2_query_format_code.value(), "R");
+        assert_eq!(qrd.qrd_3_query_priority.value(), "I");
+        assert_eq!(qrd.qrd_4_query_id.value(), "Q1004");
+        assert!(qrd.qrd_5_deferred_response_type.is_none());
+        assert_eq!(qrd.qrd_7_quantity_limited_request[(0, 0)], "1");
+        assert_eq!(qrd.qrd_7_quantity_limited_request[(0, 1)], "RD");
+        assert_eq!(qrd.qrd_8_who_subject_filter[(0, 0)], "555-44-4444");
+        assert_eq!(qrd.qrd_9_what_subject_filter.value(), "DEM");
+        assert!(qrd.qrd_10_what_department_data_code.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_missing_subject_filter_is_an_error() {
+        let d = Separators::default();
+        assert!(QrdSegment::parse("QRD|200202150930|R|I|Q1004|||1^RD", &d).is_err());
+    }
+}
@@ -0,0 +1,121 @@
+use super::RepeatingSegment;
+use crate::{fields::Field, separators::Separators, Hl7ParseError};
+use std::fmt::Display;
+
+/// The ROL (Role) segment, describing the role a person (typically a provider) plays in the care of a patient,
+/// eg attending, consulting or referring.  Messages frequently contain several of these in a row.
+/// See [the spec](https://hl7-definition.caristix.com/v2/HL7v2.5.1/Segments/ROL) for more info
+#[derive(Debug, PartialEq, Clone)]
+pub struct RolSegment<'a> {
+    pub source: &'a str,
+    pub rol_1_role_instance_id: Option<Field<'a>>,
+    pub rol_2_action_code: Field<'a>,
+    pub rol_3_role: Field<'a>,
+    pub rol_4_role_person: Field<'a>, // repeating field
+    pub rol_5_role_begin_date_time: Option<Field<'a>>,
+    pub rol_6_role_end_date_time: Option<Field<'a>>,
+}
+
+impl<'a> RolSegment<'a> {
+    pub fn parse<S: Into<&'a str>>(
+        input: S,
+        delims: &Separators,
+    ) -> Result<RolSegment<'a>, Hl7ParseError> {
+        let input = input.into();
+
+        let mut fields = input.split(delims.field);
+
+        if !super::has_segment_id(fields.next(), "ROL") {
+            return Err(Hl7ParseError::Generic(
+                "Segment doesn't start with 'ROL'".to_string(),
+            ));
+        }
+
+        let rol = RolSegment {
+            source: input,
+            rol_1_role_instance_id: Field::parse_optional(fields.next(), delims)?,
+            rol_2_action_code: Field::parse_mandatory(fields.next(), delims, Some("ROL-2"))?,
+            rol_3_role: Field::parse_mandatory(fields.next(), delims, Some("ROL-3"))?,
+            rol_4_role_person: Field::parse_mandatory(fields.next(), delims, Some("ROL-4"))?,
+            rol_5_role_begin_date_time: Field::parse_optional(fields.next(), delims)?,
+            rol_6_role_end_date_time: Field::parse_optional(fields.next(), delims)?,
+        };
+
+        Ok(rol)
+    }
+}
+
+impl<'a> Display for RolSegment<'a> {
+    /// Required for to_string() and other formatter consumers
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl<'a> RepeatingSegment<'a> for RolSegment<'a> {
+    /// Fields beyond ROL-6 aren't modelled, so are always `None`.
+    fn field(&self, field_number: usize) -> Option<&Field<'a>> {
+        match field_number {
+            1 => self.rol_1_role_instance_id.as_ref(),
+            2 => Some(&self.rol_2_action_code),
+            3 => Some(&self.rol_3_role),
+            4 => Some(&self.rol_4_role_person),
+            5 => self.rol_5_role_begin_date_time.as_ref(),
+            6 => self.rol_6_role_end_date_time.as_ref(),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::Message;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn ensure_rol_fields_are_parsed() -> Result<(), Hl7ParseError> {
+        let d = Separators::default();
+        let rol = RolSegment::parse(
+            "ROL|1|AD|AT^Attending^HL70443|1234^HIPPOCRATES^HOWARD^H^^^MD|200202150930|200202201200",
+            &d,
+        )?;
+
+        assert_eq!(rol.rol_1_role_instance_id.unwrap().value(), "1");
+        assert_eq!(rol.rol_2_action_code.value(), "AD");
+        assert_eq!(rol.rol_3_role[(0, 0)], "AT");
+        assert_eq!(rol.rol_4_role_person[(0, 1)], "HIPPOCRATES");
+        assert_eq!(
+            rol.rol_5_role_begin_date_time.unwrap().value(),
+            "200202150930"
+        );
+        assert_eq!(
+            rol.rol_6_role_end_date_time.unwrap().value(),
+            "200202201200"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_multiple_rol_segments_are_found() -> Result<(), Hl7ParseError> {
+        let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ADT^A01|CNTRL-3456|P|2.4\rROL||AD|AT|1234^HIPPOCRATES\rROL||AD|CP|5678^GALEN";
+        let msg = Message::try_from(hl7)?;
+
+        let roles: Result<Vec<RolSegment>, Hl7ParseError> = msg
+            .segments_by_name("ROL")
+            .map(|s| RolSegment::parse(s.source, &msg.get_separators()))
+            .collect();
+        let roles = roles?;
+
+        assert_eq!(roles.len(), 2);
+        assert_eq!(roles[1].rol_3_role.value(), "CP");
+        assert!(roles[1].rol_5_role_begin_date_time.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_missing_role_person_is_an_error() {
+        let d = Separators::default();
+        assert!(RolSegment::parse("ROL||AD|AT", &d).is_err());
+    }
+}
@@ -0,0 +1,170 @@
+use super::RepeatingSegment;
+use crate::{fields::Field, separators::Separators, Hl7ParseError};
+use std::fmt::Display;
+
+/// The TXA (Transcription Document Header) segment, describing a clinical document in MDM (Medical Document Management) messages.
+/// The document content itself is typically carried in the following OBX segments.
+/// See [the spec](https://hl7-definition.caristix.com/v2/HL7v2.5.1/Segments/TXA) for more info
+#[derive(Debug, PartialEq, Clone)]
+pub struct TxaSegment<'a> {
+    pub source: &'a str,
+    pub txa_1_set_id: Field<'a>,
+    pub txa_2_document_type: Field<'a>,
+    pub txa_3_document_content_presentation: Option<Field<'a>>,
+    pub txa_4_activity_date_time: Option<Field<'a>>,
+    pub txa_5_primary_activity_provider: Option<Field<'a>>, // repeating field
+    pub txa_6_origination_date_time: Option<Field<'a>>,
+    pub txa_7_transcription_date_time: Option<Field<'a>>,
+    pub txa_8_edit_date_time: Option<Field<'a>>, // repeating field
+    pub txa_9_originator: Option<Field<'a>>,     // repeating field
+    pub txa_10_assigned_document_authenticator: Option<Field<'a>>, // repeating field
+    pub txa_11_transcriptionist: Option<Field<'a>>, // repeating field
+    pub txa_12_unique_document_number: Field<'a>,
+    pub txa_13_parent_document_number: Option<Field<'a>>,
+    pub txa_14_placer_order_number: Option<Field<'a>>, // repeating field
+    pub txa_15_filler_order_number: Option<Field<'a>>,
+    pub txa_16_unique_document_file_name: Option<Field<'a>>,
+    pub txa_17_document_completion_status: Field<'a>,
+    pub txa_18_document_confidentiality_status: Option<Field<'a>>,
+    pub txa_19_document_availability_status: Option<Field<'a>>,
+    pub txa_20_document_storage_status: Option<Field<'a>>,
+    pub txa_21_document_change_reason: Option<Field<'a>>,
+    pub txa_22_authentication_person_time_stamp: Option<Field<'a>>, // repeating field
+    pub txa_23_distributed_copies: Option<Field<'a>>,               // repeating field
+}
+
+impl<'a> TxaSegment<'a> {
+    pub fn parse<S: Into<&'a str>>(
+        input: S,
+        delims: &Separators,
+    ) -> Result<TxaSegment<'a>, Hl7ParseError> {
+        let input = input.into();
+
+        let mut fields = input.split(delims.field);
+
+        if !super::has_segment_id(fields.next(), "TXA") {
+            return Err(Hl7ParseError::Generic(
+                "Segment doesn't start with 'TXA'".to_string(),
+            ));
+        }
+
+        let txa = TxaSegment {
+            source: input,
+            txa_1_set_id: Field::parse_mandatory(fields.next(), delims, Some("TXA-1"))?,
+            txa_2_document_type: Field::parse_mandatory(fields.next(), delims, Some("TXA-2"))?,
+            txa_3_document_content_presentation: Field::parse_optional(fields.next(), delims)?,
+            txa_4_activity_date_time: Field::parse_optional(fields.next(), delims)?,
+            txa_5_primary_activity_provider: Field::parse_optional(fields.next(), delims)?,
+            txa_6_origination_date_time: Field::parse_optional(fields.next(), delims)?,
+            txa_7_transcription_date_time: Field::parse_optional(fields.next(), delims)?,
+            txa_8_edit_date_time: Field::parse_optional(fields.next(), delims)?,
+            txa_9_originator: Field::parse_optional(fields.next(), delims)?,
+            txa_10_assigned_document_authenticator: Field::parse_optional(fields.next(), delims)?,
+            txa_11_transcriptionist: Field::parse_optional(fields.next(), delims)?,
+            txa_12_unique_document_number: Field::parse_mandatory(
+                fields.next(),
+                delims,
+                Some("TXA-12"),
+            )?,
+            txa_13_parent_document_number: Field::parse_optional(fields.next(), delims)?,
+            txa_14_placer_order_number: Field::parse_optional(fields.next(), delims)?,
+            txa_15_filler_order_number: Field::parse_optional(fields.next(), delims)?,
+            txa_16_unique_document_file_name: Field::parse_optional(fields.next(), delims)?,
+            txa_17_document_completion_status: Field::parse_mandatory(
+                fields.next(),
+                delims,
+                Some("TXA-17"),
+            )?,
+            txa_18_document_confidentiality_status: Field::parse_optional(fields.next(), delims)?,
+            txa_19_document_availability_status: Field::parse_optional(fields.next(), delims)?,
+            txa_20_document_storage_status: Field::parse_optional(fields.next(), delims)?,
+            txa_21_document_change_reason: Field::parse_optional(fields.next(), delims)?,
+            txa_22_authentication_person_time_stamp: Field::parse_optional(fields.next(), delims)?,
+            txa_23_distributed_copies: Field::parse_optional(fields.next(), delims)?,
+        };
+
+        Ok(txa)
+    }
+}
+
+impl<'a> Display for TxaSegment<'a> {
+    /// Required for to_string() and other formatter consumers
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl<'a> RepeatingSegment<'a> for TxaSegment<'a> {
+    /// Fields beyond TXA-23 aren't modelled, so are always `None`.
+    fn field(&self, field_number: usize) -> Option<&Field<'a>> {
+        match field_number {
+            1 => Some(&self.txa_1_set_id),
+            2 => Some(&self.txa_2_document_type),
+            3 => self.txa_3_document_content_presentation.as_ref(),
+            4 => self.txa_4_activity_date_time.as_ref(),
+            5 => self.txa_5_primary_activity_provider.as_ref(),
+            6 => self.txa_6_origination_date_time.as_ref(),
+            7 => self.txa_7_transcription_date_time.as_ref(),
+            8 => self.txa_8_edit_date_time.as_ref(),
+            9 => self.txa_9_originator.as_ref(),
+            10 => self.txa_10_assigned_document_authenticator.as_ref(),
+            11 => self.txa_11_transcriptionist.as_ref(),
+            12 => Some(&self.txa_12_unique_document_number),
+            13 => self.txa_13_parent_document_number.as_ref(),
+            14 => self.txa_14_placer_order_number.as_ref(),
+            15 => self.txa_15_filler_order_number.as_ref(),
+            16 => self.txa_16_unique_document_file_name.as_ref(),
+            17 => Some(&self.txa_17_document_completion_status),
+            18 => self.txa_18_document_confidentiality_status.as_ref(),
+            19 => self.txa_19_document_availability_status.as_ref(),
+            20 => self.txa_20_document_storage_status.as_ref(),
+            21 => self.txa_21_document_change_reason.as_ref(),
+            22 => self.txa_22_authentication_person_time_stamp.as_ref(),
+            23 => self.txa_23_distributed_copies.as_ref(),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ensure_txa_fields_are_parsed() -> Result<(), Hl7ParseError> {
+        let d = Separators::default();
+        let txa = TxaSegment::parse(
+            "TXA|1|DS^Discharge Summary|TX|200202150930|1234^HIPPOCRATES^HOWARD||||||5678^SCRIBE^SAM|DOC-0001^GHH||||ds-0001.txt|AU|UC|AV",
+            &d,
+        )?;
+
+        assert_eq!(txa.txa_1_set_id.value(), "1");
+        assert_eq!(txa.txa_2_document_type[(0, 0)], "DS");
+        assert_eq!(
+            txa.txa_4_activity_date_time.as_ref().unwrap().value(),
+            "200202150930"
+        );
+        assert_eq!(
+            txa.txa_5_primary_activity_provider.as_ref().unwrap()[(0, 1)],
+            "HIPPOCRATES"
+        );
+        assert!(txa.txa_6_origination_date_time.is_none());
+        assert_eq!(txa.txa_12_unique_document_number[(0, 0)], "DOC-0001");
+        assert_eq!(txa.txa_17_document_completion_status.value(), "AU");
+        assert_eq!(
+            txa.txa_19_document_availability_status
+                .as_ref()
+                .unwrap()
+                .value(),
+            "AV"
+        );
+        assert!(txa.txa_23_distributed_copies.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_missing_completion_status_is_an_error() {
+        let d = Separators::default();
+        assert!(TxaSegment::parse("TXA|1|DS|TX|200202150930||||||||DOC-0001", &d).is_err());
+    }
+}
@@ -6,8 +6,10 @@ use std::str::FromStr;
 /// Note that HL7 allows each _message_ to define it's own separators, although most messages
 /// use a default set (available from `Separators::default()`)
 #[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Separators {
-    /// constant value, spec fixed to '\r' (ASCII 13, 0x0D)
+    /// The separator written between segments, spec fixed to '\r' (ASCII 13, 0x0D).  Parsing also accepts `\n` and `\r\n` line endings,
+    /// see `Message::try_from()`.
     pub segment: char,
     pub field: char,
     pub repeat: char,
@@ -17,9 +19,9 @@ pub struct Separators {
     pub escape_char: char,
 }
 
-impl Separators {
+impl Default for Separators {
     /// Create a Separator with the default (most common) HL7 values
-    pub fn default() -> Separators {
+    fn default() -> Separators {
         Separators {
             segment: '\r',
             field: '|',
@@ -29,30 +31,109 @@ impl Separators {
             escape_char: '\\',
         }
     }
+}
 
-    // Create a Separators with the values provided in the message.
-    // This assumes the message starts with `MSH|^~\&|` or equiv for custom Separators
-    fn new(message: &str) -> Result<Separators, Hl7ParseError> {
-        //assuming we have a valid message
-        let mut chars = message.char_indices();
-
-        if Some((0, 'M')) != chars.next()
-            || Some((1, 'S')) != chars.next()
-            || Some((2, 'H')) != chars.next()
-        {
-            return Err(Hl7ParseError::Msh1Msh2(
-                "Message doesn't start with 'MSH'".to_string(),
-            ));
+impl Separators {
+    /// Creates a set of separators (with the spec's `\r` segment separator), rejecting any set that uses the same char for more than one
+    /// delimiter as it couldn't be parsed unambiguously.  Use `Separators::default()` for the standard `|^~\&` set.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::separators::Separators;
+    /// let delims = Separators::new('#', '@', '~', '\\', '&').unwrap();
+    /// assert_eq!(delims.to_string(), "@~\\&");
+    /// assert!(Separators::new('|', '|', '~', '\\', '&').is_err());
+    /// ```
+    pub fn new(
+        field: char,
+        component: char,
+        repeat: char,
+        escape_char: char,
+        subcomponent: char,
+    ) -> Result<Separators, Hl7ParseError> {
+        let delims = Separators {
+            segment: '\r',
+            field,
+            repeat,
+            component,
+            subcomponent,
+            escape_char,
+        };
+        match delims.duplicate() {
+            Some(c) => Err(Hl7ParseError::Generic(format!(
+                "{:?} can't be used for more than one delimiter",
+                c
+            ))),
+            None => Ok(delims),
         }
+    }
 
-        Ok(Separators {
+    /// Returns the first char used for more than one delimiter, if any.
+    pub(crate) fn duplicate(&self) -> Option<char> {
+        let chars = [
+            self.segment,
+            self.field,
+            self.component,
+            self.repeat,
+            self.escape_char,
+            self.subcomponent,
+        ];
+        chars
+            .iter()
+            .enumerate()
+            .find(|(i, c)| chars[i + 1..].contains(c))
+            .map(|(_, c)| *c)
+    }
+
+    /// Reads the separators declared by a MSH segment (typically the start of a message), ie the field separator (MSH-1) from the 4th char,
+    /// and the component, repeat, escape and subcomponent chars (MSH-2) from the following four.  The segment separator is fixed by the spec.
+    /// ## Example:
+    /// ```
+    /// # use rusthl7::Hl7ParseError;
+    /// # use rusthl7::separators::Separators;
+    /// # fn main() -> Result<(), Hl7ParseError> {
+    /// let delims = Separators::from_msh("MSH#@~\\&#GHH LAB#ELAB-3")?;
+    /// assert_eq!(delims.field, '#');
+    /// assert_eq!(delims.component, '@');
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_msh(input: &str) -> Result<Separators, Hl7ParseError> {
+        Separators::from_header(input, "MSH")
+    }
+
+    /// Reads the separators from any segment laid out like MSH, ie the batch (BHS) and file (FHS) headers which declare their own separators.
+    pub(crate) fn from_header(input: &str, name: &str) -> Result<Separators, Hl7ParseError> {
+        let mut chars = input
+            .strip_prefix(name)
+            .map(|rest| rest.chars())
+            .ok_or_else(|| {
+                Hl7ParseError::Msh1Msh2(format!("Message doesn't start with '{}'", name))
+            })?;
+
+        let mut next = || {
+            chars.next().ok_or_else(|| {
+                Hl7ParseError::Msh1Msh2(format!(
+                    "{} is too short to contain the field separator and encoding chars",
+                    name
+                ))
+            })
+        };
+
+        let delims = Separators {
             segment: '\r',
-            field: chars.next().unwrap().1,
-            component: chars.next().unwrap().1,
-            repeat: chars.next().unwrap().1,
-            escape_char: chars.next().unwrap().1,
-            subcomponent: chars.next().unwrap().1,
-        })
+            field: next()?,
+            component: next()?,
+            repeat: next()?,
+            escape_char: next()?,
+            subcomponent: next()?,
+        };
+        match delims.duplicate() {
+            Some(c) => Err(Hl7ParseError::Msh1Msh2(format!(
+                "{} declares {:?} as more than one delimiter",
+                name, c
+            ))),
+            None => Ok(delims),
+        }
     }
 }
 
@@ -73,7 +154,7 @@ impl FromStr for Separators {
     type Err = Hl7ParseError;
 
     fn from_str(input: &str) -> Result<Self, Self::Err> {
-        Separators::new(input)
+        Separators::from_msh(input)
     }
 }
 
@@ -85,7 +166,7 @@ mod tests {
     #[test]
     fn ensure_separators_load_correctly() -> Result<(), Hl7ParseError> {
         let expected = Separators::default();
-        let actual = Separators::new("MSH|^~\\&|CATH|StJohn|AcmeHIS|StJohn|20061019172719||ACK^O01|MSGID12349876|P|2.3\rMSA|AA|MSGID12349876")?;
+        let actual = Separators::from_msh("MSH|^~\\&|CATH|StJohn|AcmeHIS|StJohn|20061019172719||ACK^O01|MSGID12349876|P|2.3\rMSA|AA|MSGID12349876")?;
 
         assert_eq!(expected.component, actual.component);
         assert_eq!(expected.escape_char, actual.escape_char);
@@ -115,10 +196,58 @@ mod tests {
     #[test]
     fn ensure_missing_msh_causes_error() {
         //note the missing M
-        let result = Separators::new("SH|^~\\&|CATH|StJohn|AcmeHIS|StJohn|20061019172719||ACK^O01|MSGID12349876|P|2.3\rMSA|AA|MSGID12349876");
+        let result = Separators::from_msh("SH|^~\\&|CATH|StJohn|AcmeHIS|StJohn|20061019172719||ACK^O01|MSGID12349876|P|2.3\rMSA|AA|MSGID12349876");
         assert!(result.is_err());
     }
 
+    #[test]
+    fn ensure_custom_separators_are_detected() -> Result<(), Hl7ParseError> {
+        let actual = Separators::from_msh(
+            "MSH#@~\\&#GHH LAB#ELAB-3#GHH OE#BLDG4#200202150930##ORU@R01#CNTRL-3456#P#2.4",
+        )?;
+
+        assert_eq!(actual.field, '#');
+        assert_eq!(actual.component, '@');
+        assert_eq!(actual.repeat, '~');
+        assert_eq!(actual.escape_char, '\\');
+        assert_eq!(actual.subcomponent, '&');
+        assert_eq!(actual.segment, '\r');
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_truncated_msh_causes_error() {
+        for input in ["MSH", "MSH|^~", "MSH|^~\\"] {
+            assert!(matches!(
+                Separators::from_msh(input),
+                Err(Hl7ParseError::Msh1Msh2(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn ensure_duplicate_delimiters_are_rejected() {
+        match Separators::new('|', '^', '^', '\\', '&') {
+            Err(Hl7ParseError::Generic(e)) => {
+                assert_eq!(e, "'^' can't be used for more than one delimiter")
+            }
+            other => panic!("Expected a duplicate delimiter error, got {:?}", other),
+        }
+        assert!(Separators::new('\r', '^', '~', '\\', '&').is_err()); // clashes with the segment separator
+        assert_eq!(
+            Separators::new('|', '^', '~', '\\', '&').unwrap(),
+            Separators::default()
+        );
+
+        for msh in ["MSH|^^\\&|GHH LAB", "MSH|^~&|GHH LAB"] {
+            assert!(
+                matches!(Separators::from_msh(msh), Err(Hl7ParseError::Msh1Msh2(_))),
+                "{:?} should be rejected",
+                msh
+            );
+        }
+    }
+
     #[test]
     fn ensure_separators_to_string() {
         assert_eq!("^~\\&", Separators::default().to_string());
@@ -0,0 +1,234 @@
+/*!
+Helpers for working with HL7 `TS`/`DTM` timestamp values, eg when stamping MSH-7 (Date/Time of Message) on outbound messages.
+
+Conversion to/from `chrono` types is only available with the `chrono` feature enabled, keeping the core crate free of date handling dependencies.
+Without it callers always need to supply their own (already formatted) timestamps.
+*/
+
+use super::*;
+
+/// Formats the given date/time in the standard HL7 `YYYYMMDDHHMMSS` format.
+/// ## Example:
+/// ```
+/// # use rusthl7::timestamp::format_hl7_timestamp;
+/// use chrono::NaiveDate;
+/// let dt = NaiveDate::from_ymd_opt(2002, 2, 15).unwrap().and_hms_opt(9, 30, 0).unwrap();
+/// assert_eq!(format_hl7_timestamp(&dt), "20020215093000");
+/// ```
+#[cfg(feature = "chrono")]
+pub fn format_hl7_timestamp(timestamp: &chrono::NaiveDateTime) -> String {
+    timestamp.format("%Y%m%d%H%M%S").to_string()
+}
+
+/// Parses the date portion of a HL7 `TS`/`DTM`/`DT` value (`YYYYMMDD`), ignoring any time or timezone that follows.
+/// Returns `None` if the value has less than day precision, or isn't a valid date.
+/// ## Example:
+/// ```
+/// # use rusthl7::timestamp::parse_hl7_date;
+/// use chrono::NaiveDate;
+/// assert_eq!(parse_hl7_date("200202150930"), NaiveDate::from_ymd_opt(2002, 2, 15));
+/// assert_eq!(parse_hl7_date("200202"), None);
+/// ```
+#[cfg(feature = "chrono")]
+pub fn parse_hl7_date(value: &str) -> Option<chrono::NaiveDate> {
+    let date = value.get(..8)?;
+    chrono::NaiveDate::parse_from_str(date, "%Y%m%d").ok()
+}
+
+/// Parses a HL7 `TS`/`DTM` value (`YYYY[MM[DD[HH[MM[SS[.S[S[S[S]]]]]]]]][+/-ZZZZ]`) into a date/time.
+///
+/// The value needs at least day precision to be represented as a date/time, coarser values (`YYYY` and `YYYYMM`) are an error.  Missing time
+/// parts default to zero, so a bare date is midnight.  Any timezone offset is validated, but not applied, so the result is the time as the
+/// sender recorded it.
+/// ## Example:
+/// ```
+/// # use rusthl7::timestamp::parse_hl7_datetime;
+/// use chrono::NaiveDate;
+/// let expected = NaiveDate::from_ymd_opt(2002, 2, 15).unwrap().and_hms_opt(9, 30, 0).unwrap();
+/// assert_eq!(parse_hl7_datetime("200202150930+1000").unwrap(), expected);
+/// assert!(parse_hl7_datetime("2002").is_err());
+/// ```
+#[cfg(feature = "chrono")]
+pub fn parse_hl7_datetime(value: &str) -> Result<chrono::NaiveDateTime, Hl7ParseError> {
+    let invalid = |reason: &str| {
+        Hl7ParseError::Generic(format!("Invalid HL7 timestamp '{}': {}", value, reason))
+    };
+
+    // split off (and validate) any timezone offset
+    let (timestamp, offset) = match value.find(['+', '-']) {
+        Some(idx) => (&value[..idx], Some(&value[idx + 1..])),
+        None => (value, None),
+    };
+    if let Some(offset) = offset {
+        if offset.len() != 4 || !offset.chars().all(|c| c.is_ascii_digit()) {
+            return Err(invalid("the timezone offset must be 4 digits (+/-HHMM)"));
+        }
+    }
+
+    let (digits, fraction) = match timestamp.split_once('.') {
+        Some((digits, fraction)) => (digits, Some(fraction)),
+        None => (timestamp, None),
+    };
+    if !digits.chars().all(|c| c.is_ascii_digit()) {
+        return Err(invalid("expected only digits"));
+    }
+
+    match digits.len() {
+        4 | 6 => {
+            return Err(invalid(
+                "only year or month precision, a full date (YYYYMMDD) is needed for a date/time",
+            ))
+        }
+        8 | 10 | 12 | 14 => {}
+        _ => return Err(invalid("expected YYYYMMDD[HH[MM[SS]]]")),
+    }
+
+    let nanos = match fraction {
+        None => 0,
+        Some(f)
+            if digits.len() == 14
+                && (1..=4).contains(&f.len())
+                && f.chars().all(|c| c.is_ascii_digit()) =>
+        {
+            // right pad to nanoseconds, eg `.5` is 500,000,000ns
+            format!("{:0<9}", f).parse::<u32>().unwrap()
+        }
+        Some(_) => {
+            return Err(invalid(
+                "fractional seconds must be 1-4 digits, following a full time (HHMMSS)",
+            ))
+        }
+    };
+
+    // the length was checked above, so these are all present (or default to zero for missing time parts)
+    let part = |from: usize, to: usize| {
+        digits
+            .get(from..to)
+            .map_or(0, |p| p.parse::<u32>().unwrap())
+    };
+    chrono::NaiveDate::from_ymd_opt(part(0, 4) as i32, part(4, 6), part(6, 8))
+        .and_then(|date| date.and_hms_nano_opt(part(8, 10), part(10, 12), part(12, 14), nanos))
+        .ok_or_else(|| invalid("not a valid date/time"))
+}
+
+/// Returns the current (local) system time as a HL7 timestamp.
+#[cfg(feature = "chrono")]
+pub fn now() -> String {
+    format_hl7_timestamp(&chrono::Local::now().naive_local())
+}
+
+/// Resolves the value to stamp into a timestamp field (typically MSH-7) of an outbound message.
+///
+/// An explicitly supplied timestamp always wins (which keeps things testable), otherwise the current system time is used when the `chrono`
+/// feature is enabled.  Without the `chrono` feature an explicit timestamp is required, and an error is returned if one isn't provided.
+pub fn timestamp_or_now(explicit: Option<&str>) -> Result<String, Hl7ParseError> {
+    match explicit {
+        Some(timestamp) => Ok(timestamp.to_string()),
+
+        #[cfg(feature = "chrono")]
+        None => Ok(now()),
+
+        #[cfg(not(feature = "chrono"))]
+        None => Err(Hl7ParseError::Generic(
+            "No timestamp supplied, and the `chrono` feature is disabled so the system clock can't be used".to_string(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ensure_explicit_timestamp_is_used() -> Result<(), Hl7ParseError> {
+        assert_eq!(timestamp_or_now(Some("200202150930"))?, "200202150930");
+        Ok(())
+    }
+
+    #[cfg(not(feature = "chrono"))]
+    #[test]
+    fn ensure_missing_timestamp_is_an_error_without_chrono() {
+        assert!(timestamp_or_now(None).is_err());
+    }
+
+    #[cfg(feature = "chrono")]
+    mod chrono_tests {
+        use super::*;
+        use chrono::NaiveDate;
+
+        #[test]
+        fn ensure_fixed_timestamp_is_formatted() {
+            let dt = NaiveDate::from_ymd_opt(2021, 7, 4)
+                .unwrap()
+                .and_hms_opt(16, 5, 9)
+                .unwrap();
+            assert_eq!(format_hl7_timestamp(&dt), "20210704160509");
+        }
+
+        #[test]
+        fn ensure_each_precision_is_parsed() -> Result<(), Hl7ParseError> {
+            let date = NaiveDate::from_ymd_opt(2002, 2, 15).unwrap();
+
+            assert_eq!(
+                parse_hl7_datetime("20020215")?,
+                date.and_hms_opt(0, 0, 0).unwrap()
+            );
+            assert_eq!(
+                parse_hl7_datetime("2002021509")?,
+                date.and_hms_opt(9, 0, 0).unwrap()
+            );
+            assert_eq!(
+                parse_hl7_datetime("200202150930")?,
+                date.and_hms_opt(9, 30, 0).unwrap()
+            );
+            assert_eq!(
+                parse_hl7_datetime("20020215093015")?,
+                date.and_hms_opt(9, 30, 15).unwrap()
+            );
+            assert_eq!(
+                parse_hl7_datetime("20020215093015.5")?,
+                date.and_hms_milli_opt(9, 30, 15, 500).unwrap()
+            );
+            assert_eq!(
+                parse_hl7_datetime("20020215093015.1234-0500")?,
+                date.and_hms_micro_opt(9, 30, 15, 123_400).unwrap()
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn ensure_bad_timestamps_are_errors() {
+            for value in [
+                "2002",
+                "200202",
+                "",
+                "2002021",
+                "20021315",
+                "20020230",
+                "2002021525",
+                "200202150930.5",
+                "20020215093015.12345",
+                "200202150930+10",
+                "2002O215",
+            ] {
+                assert!(
+                    parse_hl7_datetime(value).is_err(),
+                    "{} should be invalid",
+                    value
+                );
+            }
+
+            let err = parse_hl7_datetime("200202").unwrap_err().to_string();
+            assert!(err.contains("'200202'"), "{}", err);
+            assert!(err.contains("month precision"), "{}", err);
+        }
+
+        #[test]
+        fn ensure_missing_timestamp_defaults_to_now() -> Result<(), Hl7ParseError> {
+            let timestamp = timestamp_or_now(None)?;
+            assert_eq!(timestamp.len(), 14);
+            assert!(timestamp.chars().all(|c| c.is_ascii_digit()));
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,166 @@
+/*!
+Serialization of messages to the HL7 v2.xml encoding, see `Message::to_v2xml()`.
+*/
+
+use crate::datatypes::{field_datatype, DataType};
+use crate::escape_sequence::EscapeSequence;
+use crate::fields::Field;
+use crate::message::Message;
+
+/// Renders the message as v2.xml, see `Message::to_v2xml()` for the details.
+pub(crate) fn to_v2xml(msg: &Message) -> String {
+    let delims = msg.get_separators();
+    let decoder = EscapeSequence::new(delims);
+    let root = msg
+        .message_type()
+        .map_or_else(|| "MSG".to_string(), |t| t.structure_or_inferred());
+
+    let mut xml = format!("<{}>", root);
+    for segment in &msg.segments {
+        let name = segment.fields[0].source.trim_matches('\n');
+        if name.is_empty() {
+            continue; // trailing segment separator
+        }
+
+        xml.push_str(&format!("<{}>", name));
+        let fields = segment.fields.iter().enumerate().skip(1);
+        for (i, field) in fields {
+            let number = segment.field_number(i);
+            if name == "MSH" && i == 1 {
+                xml.push_str(&format!(
+                    "<MSH.1>{}</MSH.1><MSH.2>{}</MSH.2>",
+                    escape_xml(&delims.field.to_string()),
+                    escape_xml(field.source)
+                ));
+                continue;
+            }
+            write_field(&mut xml, &decoder, name, number, field);
+        }
+        xml.push_str(&format!("</{}>", name));
+    }
+    xml.push_str(&format!("</{}>", root));
+    xml
+}
+
+/// Writes one element per (non-empty) repeat of the field.
+fn write_field(
+    xml: &mut String,
+    decoder: &EscapeSequence,
+    segment: &str,
+    number: usize,
+    field: &Field,
+) {
+    let element = format!("{}.{}", segment, number);
+    let datatype = field_datatype(segment, number);
+
+    for (r, components) in field.subcomponents().iter().enumerate() {
+        let repeat = field.repeats()[r].trim_end_matches('\n');
+        if repeat.is_empty() {
+            continue;
+        }
+
+        xml.push_str(&format!("<{}>", element));
+        let composite = match datatype {
+            Some(DataType::Varies) | None => components.len() > 1 || components[0].len() > 1,
+            Some(t) => !t.component_names().is_empty(),
+        };
+
+        if composite {
+            for (c, subs) in components.iter().enumerate() {
+                if subs.iter().all(|s| s.trim_end_matches('\n').is_empty()) {
+                    continue;
+                }
+
+                // components are named for their data type where we know it, otherwise for their position in the segment
+                let component = match datatype {
+                    Some(t) if t != DataType::Varies => format!("{:?}.{}", t, c + 1),
+                    _ => format!("{}.{}", element, c + 1),
+                };
+                xml.push_str(&format!("<{}>", component));
+                if subs.len() == 1 {
+                    write_value(xml, decoder, subs[0]);
+                } else {
+                    for (s, value) in subs.iter().enumerate() {
+                        let value = value.trim_end_matches('\n');
+                        if !value.is_empty() {
+                            xml.push_str(&format!("<{}.{}>", component, s + 1));
+                            write_value(xml, decoder, value);
+                            xml.push_str(&format!("</{}.{}>", component, s + 1));
+                        }
+                    }
+                }
+                xml.push_str(&format!("</{}>", component));
+            }
+        } else {
+            write_value(xml, decoder, repeat);
+        }
+        xml.push_str(&format!("</{}>", element));
+    }
+}
+
+/// Writes the value with any HL7 escape sequences decoded, and XML special chars escaped.
+fn write_value(xml: &mut String, decoder: &EscapeSequence, value: &str) {
+    let value = value.trim_end_matches('\n');
+    xml.push_str(&escape_xml(&decoder.decode(value)));
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Hl7ParseError;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn ensure_structure_is_serialized() -> Result<(), Hl7ParseError> {
+        let hl7 = "MSH|^~\\&|GHH LAB|ELAB-3|GHH OE|BLDG4|200202150930||ORU^R01|CNTRL-3456|P|2.4\rPID|||555-44-4444~123^^^GHH&1.2.3&ISO||EVERYWOMAN^EVE^E|\rOBX|1|ST|1554-5^GLUCOSE||Fasting \\T\\ <100>|\r";
+        let msg = Message::try_from(hl7)?;
+
+        assert_eq!(
+            to_v2xml(&msg),
+            concat!(
+                "<ORU_R01>",
+                "<MSH><MSH.1>|</MSH.1><MSH.2>^~\\&amp;</MSH.2><MSH.3><HD.1>GHH LAB</HD.1></MSH.3><MSH.4><HD.1>ELAB-3</HD.1></MSH.4>",
+                "<MSH.5><HD.1>GHH OE</HD.1></MSH.5><MSH.6><HD.1>BLDG4</HD.1></MSH.6><MSH.7><TS.1>200202150930</TS.1></MSH.7>",
+                "<MSH.9><MSG.1>ORU</MSG.1><MSG.2>R01</MSG.2></MSH.9><MSH.10>CNTRL-3456</MSH.10><MSH.11><PT.1>P</PT.1></MSH.11>",
+                "<MSH.12><VID.1>2.4</VID.1></MSH.12></MSH>",
+                "<PID><PID.3><CX.1>555-44-4444</CX.1></PID.3><PID.3><CX.1>123</CX.1><CX.4><CX.4.1>GHH</CX.4.1><CX.4.2>1.2.3</CX.4.2><CX.4.3>ISO</CX.4.3></CX.4></PID.3>",
+                "<PID.5><XPN.1>EVERYWOMAN</XPN.1><XPN.2>EVE</XPN.2><XPN.3>E</XPN.3></PID.5></PID>",
+                "<OBX><OBX.1>1</OBX.1><OBX.2>ST</OBX.2><OBX.3><CE.1>1554-5</CE.1><CE.2>GLUCOSE</CE.2></OBX.3><OBX.5>Fasting &amp; &lt;100&gt;</OBX.5></OBX>",
+                "</ORU_R01>"
+            )
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_acknowledgments_use_the_ack_structure() -> Result<(), Hl7ParseError> {
+        let hl7 = "MSH|^~\\&|GHH OE|BLDG4|GHH LAB|ELAB-3|200202150931||ACK^A01|ACK-1|P|2.4\rMSA|AA|CNTRL-3456";
+        let msg = Message::try_from(hl7)?;
+
+        let xml = to_v2xml(&msg);
+        assert!(xml.starts_with("<ACK><MSH>"));
+        assert!(xml.ends_with("</MSA></ACK>"));
+        Ok(())
+    }
+
+    #[test]
+    fn ensure_unknown_fields_are_named_by_position() -> Result<(), Hl7ParseError> {
+        let hl7 = "MSH|^~\\&|GHH LAB\rZPD|simple|first^second&sub";
+        let msg = Message::try_from(hl7)?;
+
+        assert_eq!(
+            to_v2xml(&msg),
+            "<MSG><MSH><MSH.1>|</MSH.1><MSH.2>^~\\&amp;</MSH.2><MSH.3><HD.1>GHH LAB</HD.1></MSH.3></MSH><ZPD><ZPD.1>simple</ZPD.1><ZPD.2><ZPD.2.1>first</ZPD.2.1><ZPD.2.2><ZPD.2.2.1>second</ZPD.2.2.1><ZPD.2.2.2>sub</ZPD.2.2.2></ZPD.2.2></ZPD.2></ZPD></MSG>"
+        );
+        Ok(())
+    }
+}